@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS phone_verifications (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    user_id         BIGINT SIGNED UNIQUE NOT NULL,
+    phone_number    VARCHAR(20) NOT NULL,
+    code_hash       VARCHAR(255) NOT NULL,
+    attempts        INT NOT NULL DEFAULT 0,
+    expires_at      TIMESTAMP NOT NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    FOREIGN KEY     (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
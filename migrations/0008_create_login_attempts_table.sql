@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS login_attempts (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    username        VARCHAR(255) NOT NULL,
+    ip_address      VARCHAR(45) NOT NULL,
+    succeeded       BOOLEAN NOT NULL,
+    reason          VARCHAR(64),
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX           (username),
+    INDEX           (ip_address)
+);
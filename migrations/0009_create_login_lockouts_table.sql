@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS login_lockouts (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    lock_key        VARCHAR(255) UNIQUE NOT NULL,
+    failed_attempts INT NOT NULL DEFAULT 0,
+    locked_until    TIMESTAMP NULL,
+    last_failed_at  TIMESTAMP NOT NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
@@ -0,0 +1,11 @@
+ALTER TABLE api_keys
+    ADD COLUMN key_prefix   VARCHAR(32) NOT NULL DEFAULT '' AFTER id,
+    ADD COLUMN key_hash     CHAR(64) NOT NULL DEFAULT '' AFTER key_prefix,
+    ADD COLUMN scopes       VARCHAR(1024) NOT NULL DEFAULT '' AFTER contact_email;
+
+-- Keep existing keys working: hash them in place and give them the only access they ever had.
+UPDATE api_keys SET key_hash = SHA2(api_key, 256), key_prefix = LEFT(api_key, 8), scopes = 'users:read';
+
+ALTER TABLE api_keys
+    DROP COLUMN api_key,
+    ADD UNIQUE INDEX (key_hash);
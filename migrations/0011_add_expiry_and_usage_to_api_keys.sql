@@ -0,0 +1,9 @@
+-- Rotation creates a second key for the same client, so the contact email can no longer be unique.
+ALTER TABLE api_keys
+    DROP INDEX contact_email,
+    ADD COLUMN expires_at       TIMESTAMP NULL AFTER is_active,
+    ADD COLUMN last_used_at     TIMESTAMP NULL AFTER expires_at,
+    ADD COLUMN usage_count      BIGINT NOT NULL DEFAULT 0 AFTER last_used_at,
+    ADD COLUMN replaced_by_id   BIGINT SIGNED NULL AFTER usage_count,
+    ADD INDEX (contact_email),
+    ADD INDEX (expires_at);
@@ -0,0 +1 @@
+ALTER TABLE api_keys ADD COLUMN signing_secret VARCHAR(255) NULL AFTER key_hash;
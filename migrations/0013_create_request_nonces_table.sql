@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS request_nonces (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    api_key_id      BIGINT SIGNED NOT NULL,
+    nonce           VARCHAR(128) NOT NULL,
+    expires_at      TIMESTAMP NOT NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE INDEX    (api_key_id, nonce),
+    INDEX           (expires_at),
+    FOREIGN KEY     (api_key_id) REFERENCES api_keys(id) ON DELETE CASCADE
+);
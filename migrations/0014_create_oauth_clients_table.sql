@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS oauth_clients (
+    id                  BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    client_id           VARCHAR(64) UNIQUE NOT NULL,
+    client_secret_hash  CHAR(64) NULL,
+    client_name         VARCHAR(255) NOT NULL,
+    contact_email       VARCHAR(255) NOT NULL,
+    redirect_uris       TEXT NOT NULL,
+    scopes              VARCHAR(255) NOT NULL,
+    is_active           BOOLEAN NOT NULL DEFAULT true,
+    created_at          TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at          TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
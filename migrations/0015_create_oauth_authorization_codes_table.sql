@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS oauth_authorization_codes (
+    id                  BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    code_hash           CHAR(64) UNIQUE NOT NULL,
+    client_id           BIGINT SIGNED NOT NULL,
+    user_id             BIGINT SIGNED NOT NULL,
+    redirect_uri        VARCHAR(2048) NOT NULL,
+    scopes              VARCHAR(255) NOT NULL,
+    code_challenge      VARCHAR(128) NOT NULL,
+    expires_at          TIMESTAMP NOT NULL,
+    created_at          TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX               (expires_at),
+    FOREIGN KEY         (client_id) REFERENCES oauth_clients(id) ON DELETE CASCADE,
+    FOREIGN KEY         (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
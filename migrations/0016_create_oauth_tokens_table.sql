@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS oauth_tokens (
+    id                  BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    token_hash          CHAR(64) UNIQUE NOT NULL,
+    -- "access" or "refresh"
+    token_type          VARCHAR(16) NOT NULL,
+    -- Shared by every token issued from one authorization, so a grant can be revoked as a whole.
+    family              CHAR(32) NOT NULL,
+    client_id           BIGINT SIGNED NOT NULL,
+    user_id             BIGINT SIGNED NOT NULL,
+    scopes              VARCHAR(255) NOT NULL,
+    expires_at          TIMESTAMP NOT NULL,
+    revoked_at          TIMESTAMP NULL,
+    created_at          TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at          TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    INDEX               (family),
+    FOREIGN KEY         (client_id) REFERENCES oauth_clients(id) ON DELETE CASCADE,
+    FOREIGN KEY         (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
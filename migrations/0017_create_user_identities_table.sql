@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS user_identities (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    user_id         BIGINT SIGNED NOT NULL,
+    -- Name of the provider in OIDC_PROVIDERS, e.g. "acme".
+    provider        VARCHAR(64) NOT NULL,
+    -- The provider's "sub" claim, stable for the user at that provider.
+    subject         VARCHAR(255) NOT NULL,
+    email           VARCHAR(255),
+    last_login_at   TIMESTAMP NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    UNIQUE INDEX    (provider, subject),
+    FOREIGN KEY     (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
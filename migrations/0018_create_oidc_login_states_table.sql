@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS oidc_login_states (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    state           CHAR(43) UNIQUE NOT NULL,
+    provider        VARCHAR(64) NOT NULL,
+    nonce           CHAR(43) NOT NULL,
+    code_verifier   CHAR(64) NOT NULL,
+    expires_at      TIMESTAMP NOT NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX           (expires_at)
+);
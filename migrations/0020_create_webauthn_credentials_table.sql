@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS webauthn_credentials (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    user_id         BIGINT SIGNED NOT NULL,
+    -- base64url, as the browser reports it
+    credential_id   VARCHAR(255) UNIQUE NOT NULL,
+    -- base64url of the COSE_Key from the attested credential data
+    public_key      TEXT NOT NULL,
+    -- COSE algorithm: -7 ES256, -8 EdDSA, -257 RS256
+    algorithm       INT NOT NULL,
+    sign_count      BIGINT NOT NULL DEFAULT 0,
+    name            VARCHAR(255) NOT NULL,
+    transports      VARCHAR(255),
+    last_used_at    TIMESTAMP NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    FOREIGN KEY     (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
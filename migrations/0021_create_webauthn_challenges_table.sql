@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS webauthn_challenges (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    challenge       CHAR(43) UNIQUE NOT NULL,
+    -- "registration" or "authentication"
+    ceremony        VARCHAR(16) NOT NULL,
+    -- Always set for registration. For authentication only when a username was given.
+    user_id         BIGINT SIGNED NULL,
+    expires_at      TIMESTAMP NOT NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX           (expires_at),
+    FOREIGN KEY     (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS impersonation_sessions (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    admin_id        BIGINT SIGNED NOT NULL,
+    user_id         BIGINT SIGNED NOT NULL,
+    reason          VARCHAR(255) NOT NULL,
+    ip_address      VARCHAR(45) NOT NULL,
+    expires_at      TIMESTAMP NOT NULL,
+    ended_at        TIMESTAMP NULL,
+    ended_by        BIGINT SIGNED NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    INDEX           (admin_id),
+    INDEX           (user_id),
+    FOREIGN KEY     (admin_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY     (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS events (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    user_id         BIGINT SIGNED NOT NULL,
+    type            VARCHAR(64) NOT NULL,
+    data            TEXT NOT NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX           (user_id, id),
+    INDEX           (created_at),
+    FOREIGN KEY     (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
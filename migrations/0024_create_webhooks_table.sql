@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS webhooks (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    user_id         BIGINT SIGNED NULL,
+    api_key_id      BIGINT SIGNED NULL,
+    url             VARCHAR(2048) NOT NULL,
+    event_types     VARCHAR(512) NOT NULL,
+    secret          VARCHAR(64) NOT NULL,
+    is_active       BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    INDEX           (user_id),
+    INDEX           (api_key_id),
+    FOREIGN KEY     (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY     (api_key_id) REFERENCES api_keys(id) ON DELETE CASCADE
+);
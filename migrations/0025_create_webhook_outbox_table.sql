@@ -0,0 +1,12 @@
+-- Written in the same transaction as the change it describes. No foreign key on user_id:
+-- the outbox row of a deleted user has to outlive the user.
+CREATE TABLE IF NOT EXISTS webhook_outbox (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    event_id        CHAR(36) UNIQUE NOT NULL,
+    event_type      VARCHAR(64) NOT NULL,
+    user_id         BIGINT SIGNED NOT NULL,
+    payload         TEXT NOT NULL,
+    dispatched_at   TIMESTAMP NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX           (dispatched_at, id)
+);
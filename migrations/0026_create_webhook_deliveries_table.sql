@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    id                  BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    webhook_id          BIGINT SIGNED NOT NULL,
+    event_id            CHAR(36) NOT NULL,
+    event_type          VARCHAR(64) NOT NULL,
+    payload             TEXT NOT NULL,
+    status              VARCHAR(16) NOT NULL DEFAULT 'pending',
+    attempts            INT NOT NULL DEFAULT 0,
+    next_attempt_at     TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    locked_until        TIMESTAMP NULL,
+    last_status_code    INT NULL,
+    last_error          VARCHAR(1024) NULL,
+    delivered_at        TIMESTAMP NULL,
+    created_at          TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at          TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    UNIQUE              (webhook_id, event_id),
+    INDEX               (status, next_attempt_at),
+    FOREIGN KEY         (webhook_id) REFERENCES webhooks(id) ON DELETE CASCADE
+);
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS webhook_delivery_attempts (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    delivery_id     BIGINT SIGNED NOT NULL,
+    status_code     INT NULL,
+    error           VARCHAR(1024) NULL,
+    duration_ms     INT NOT NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX           (delivery_id),
+    FOREIGN KEY     (delivery_id) REFERENCES webhook_deliveries(id) ON DELETE CASCADE
+);
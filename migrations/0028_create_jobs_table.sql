@@ -0,0 +1,20 @@
+-- Background jobs. unique_key is only set while a job is pending or running, so a second job
+-- with the same key is not enqueued until the first one has finished.
+CREATE TABLE IF NOT EXISTS jobs (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    kind            VARCHAR(64) NOT NULL,
+    payload         TEXT NOT NULL,
+    unique_key      VARCHAR(191) NULL UNIQUE,
+    status          VARCHAR(16) NOT NULL DEFAULT 'pending',
+    attempts        INT NOT NULL DEFAULT 0,
+    max_attempts    INT NOT NULL DEFAULT 5,
+    run_at          TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    locked_by       VARCHAR(64) NULL,
+    locked_until    TIMESTAMP NULL,
+    last_error      VARCHAR(1024) NULL,
+    finished_at     TIMESTAMP NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    INDEX           (status, run_at),
+    INDEX           (status, locked_until)
+);
@@ -0,0 +1,10 @@
+-- When each recurring job is next due. Shared by every instance of the server, so a job
+-- runs once per slot no matter how many are running.
+CREATE TABLE IF NOT EXISTS job_schedules (
+    name            VARCHAR(64) PRIMARY KEY NOT NULL,
+    cron            VARCHAR(128) NOT NULL,
+    next_run_at     TIMESTAMP NOT NULL,
+    last_run_at     TIMESTAMP NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
@@ -0,0 +1,4 @@
+ALTER TABLE todos
+    ADD COLUMN due_at       TIMESTAMP NULL,
+    ADD COLUMN remind_at    TIMESTAMP NULL,
+    ADD INDEX  (remind_at);
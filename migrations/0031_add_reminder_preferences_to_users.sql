@@ -0,0 +1,7 @@
+-- time_zone is an IANA name, e.g. Asia/Dhaka. Quiet hours are local times in that zone; a
+-- window may wrap past midnight. reminder_channels is space separated.
+ALTER TABLE users
+    ADD COLUMN time_zone            VARCHAR(64) NOT NULL DEFAULT 'UTC',
+    ADD COLUMN quiet_hours_start    TIME NULL,
+    ADD COLUMN quiet_hours_end      TIME NULL,
+    ADD COLUMN reminder_channels    VARCHAR(64) NOT NULL DEFAULT 'email in_app';
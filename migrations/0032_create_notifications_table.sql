@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS notifications (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    user_id         BIGINT SIGNED NOT NULL,
+    kind            VARCHAR(64) NOT NULL,
+    title           VARCHAR(255) NOT NULL,
+    body            TEXT NOT NULL,
+    data            TEXT NULL,
+    read_at         TIMESTAMP NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX           (user_id, id),
+    FOREIGN KEY     (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,17 @@
+-- One row per reminder that fired. The unique key is what makes each remind_at of a todo
+-- fire once; sent_channels records which channels have it, so a retry skips them.
+CREATE TABLE IF NOT EXISTS todo_reminders (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    todo_id         BIGINT SIGNED NOT NULL,
+    user_id         BIGINT SIGNED NOT NULL,
+    remind_at       TIMESTAMP NOT NULL,
+    status          VARCHAR(16) NOT NULL DEFAULT 'pending',
+    sent_channels   VARCHAR(64) NOT NULL DEFAULT '',
+    deliver_at      TIMESTAMP NOT NULL,
+    sent_at         TIMESTAMP NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    UNIQUE          (todo_id, remind_at),
+    FOREIGN KEY     (todo_id) REFERENCES todos(id) ON DELETE CASCADE,
+    FOREIGN KEY     (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,14 @@
+-- Devices each user has signed in from, to tell them about sign-ins from new ones.
+-- device_hash is the SHA-256 of the device cookie, or of the user agent for clients
+-- without cookies.
+CREATE TABLE IF NOT EXISTS known_devices (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    user_id         BIGINT SIGNED NOT NULL,
+    device_hash     CHAR(64) NOT NULL,
+    user_agent      VARCHAR(512) NULL,
+    ip_address      VARCHAR(45) NOT NULL,
+    last_seen_at    TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE          (user_id, device_hash),
+    FOREIGN KEY     (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
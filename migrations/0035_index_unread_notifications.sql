@@ -0,0 +1,2 @@
+-- Unread counts are a COUNT over this index.
+ALTER TABLE notifications ADD INDEX (user_id, read_at);
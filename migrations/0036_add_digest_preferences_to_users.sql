@@ -0,0 +1,9 @@
+-- Emailed summaries of overdue, due-today and recently completed todos. digest_frequency is
+-- off, daily or weekly. A digest goes out at digest_hour local time (see time_zone), and
+-- weekly ones on digest_weekday (1 = Monday). next_digest_at is when the next one is due.
+ALTER TABLE users
+    ADD COLUMN digest_frequency VARCHAR(16) NOT NULL DEFAULT 'off',
+    ADD COLUMN digest_hour      TINYINT UNSIGNED NOT NULL DEFAULT 8,
+    ADD COLUMN digest_weekday   TINYINT UNSIGNED NOT NULL DEFAULT 1,
+    ADD COLUMN next_digest_at   TIMESTAMP NULL,
+    ADD INDEX  (next_digest_at);
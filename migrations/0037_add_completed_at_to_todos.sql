@@ -0,0 +1,7 @@
+-- When a todo was last marked done, for digests. Existing done todos count as completed at
+-- their last update.
+ALTER TABLE todos
+    ADD COLUMN completed_at TIMESTAMP NULL,
+    ADD INDEX  (user_id, completed_at);
+
+UPDATE todos SET completed_at = updated_at WHERE done;
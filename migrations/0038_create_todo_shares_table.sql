@@ -0,0 +1,18 @@
+-- Todos shared with other users. role is viewer, editor or owner; status is pending until
+-- the invitee accepts or declines, and only accepted shares grant access.
+CREATE TABLE IF NOT EXISTS todo_shares (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    todo_id         BIGINT SIGNED NOT NULL,
+    user_id         BIGINT SIGNED NOT NULL,
+    role            VARCHAR(16) NOT NULL,
+    status          VARCHAR(16) NOT NULL DEFAULT 'pending',
+    invited_by      BIGINT SIGNED NOT NULL,
+    responded_at    TIMESTAMP NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    UNIQUE          (todo_id, user_id),
+    INDEX           (user_id, status),
+    FOREIGN KEY     (todo_id) REFERENCES todos(id) ON DELETE CASCADE,
+    FOREIGN KEY     (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY     (invited_by) REFERENCES users(id) ON DELETE CASCADE
+);
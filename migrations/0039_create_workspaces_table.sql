@@ -0,0 +1,12 @@
+-- Workspaces own lists and todos. Every user has a personal one (personal_user_id), made
+-- when they first need it; others are shared by membership.
+CREATE TABLE IF NOT EXISTS workspaces (
+    id                  BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    name                VARCHAR(100) NOT NULL,
+    personal_user_id    BIGINT SIGNED NULL UNIQUE,
+    created_by          BIGINT SIGNED NULL,
+    created_at          TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at          TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    FOREIGN KEY         (personal_user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY         (created_by) REFERENCES users(id) ON DELETE SET NULL
+);
@@ -0,0 +1,13 @@
+-- role is member, admin or owner.
+CREATE TABLE IF NOT EXISTS workspace_members (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    workspace_id    BIGINT SIGNED NOT NULL,
+    user_id         BIGINT SIGNED NOT NULL,
+    role            VARCHAR(16) NOT NULL DEFAULT 'member',
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    UNIQUE          (workspace_id, user_id),
+    INDEX           (user_id),
+    FOREIGN KEY     (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE,
+    FOREIGN KEY     (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
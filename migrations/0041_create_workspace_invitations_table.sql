@@ -0,0 +1,17 @@
+-- status is pending until the invitee accepts, which makes them a member, or declines.
+CREATE TABLE IF NOT EXISTS workspace_invitations (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    workspace_id    BIGINT SIGNED NOT NULL,
+    user_id         BIGINT SIGNED NOT NULL,
+    role            VARCHAR(16) NOT NULL DEFAULT 'member',
+    status          VARCHAR(16) NOT NULL DEFAULT 'pending',
+    invited_by      BIGINT SIGNED NOT NULL,
+    responded_at    TIMESTAMP NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    UNIQUE          (workspace_id, user_id),
+    INDEX           (user_id, status),
+    FOREIGN KEY     (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE,
+    FOREIGN KEY     (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY     (invited_by) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,12 @@
+-- Lists group the todos of a workspace. Every member can see and edit the todos on them.
+CREATE TABLE IF NOT EXISTS lists (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    workspace_id    BIGINT SIGNED NOT NULL,
+    name            VARCHAR(100) NOT NULL,
+    created_by      BIGINT SIGNED NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    INDEX           (workspace_id),
+    FOREIGN KEY     (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE,
+    FOREIGN KEY     (created_by) REFERENCES users(id) ON DELETE SET NULL
+);
@@ -0,0 +1,19 @@
+-- Existing users get their personal workspace now, and their todos move into it.
+INSERT INTO workspaces (name, personal_user_id, created_by)
+    SELECT 'Personal', id, id FROM users
+    WHERE id NOT IN (SELECT personal_user_id FROM workspaces WHERE personal_user_id IS NOT NULL);
+
+INSERT IGNORE INTO workspace_members (workspace_id, user_id, role)
+    SELECT id, personal_user_id, 'owner' FROM workspaces WHERE personal_user_id IS NOT NULL;
+
+ALTER TABLE todos
+    ADD COLUMN workspace_id BIGINT SIGNED NULL,
+    ADD COLUMN list_id      BIGINT SIGNED NULL;
+
+UPDATE todos t JOIN workspaces w ON w.personal_user_id = t.user_id SET t.workspace_id = w.id;
+
+ALTER TABLE todos
+    MODIFY COLUMN workspace_id BIGINT SIGNED NOT NULL,
+    ADD INDEX     (workspace_id, user_id),
+    ADD FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE,
+    ADD FOREIGN KEY (list_id) REFERENCES lists(id) ON DELETE SET NULL;
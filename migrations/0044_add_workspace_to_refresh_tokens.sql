@@ -0,0 +1,5 @@
+-- The workspace the session was switched to, so refreshed access tokens stay in it. NULL
+-- is the user's personal workspace.
+ALTER TABLE refresh_tokens
+    ADD COLUMN workspace_id BIGINT SIGNED NULL,
+    ADD FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE SET NULL;
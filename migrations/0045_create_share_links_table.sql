@@ -0,0 +1,24 @@
+-- Public, read-only links to a list or a single todo. Exactly one of list_id and todo_id is
+-- set. Only the SHA-256 of the token is kept; the token itself is shown once, on creation.
+CREATE TABLE IF NOT EXISTS share_links (
+    id              BIGINT SIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    workspace_id    BIGINT SIGNED NOT NULL,
+    list_id         BIGINT SIGNED NULL,
+    todo_id         BIGINT SIGNED NULL,
+    token_prefix    VARCHAR(16) NOT NULL,
+    token_hash      CHAR(64) NOT NULL,
+    password_hash   VARCHAR(255) NULL,
+    expires_at      TIMESTAMP NULL,
+    revoked_at      TIMESTAMP NULL,
+    view_count      BIGINT NOT NULL DEFAULT 0,
+    last_viewed_at  TIMESTAMP NULL,
+    created_by      BIGINT SIGNED NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    UNIQUE          (token_hash),
+    INDEX           (workspace_id),
+    FOREIGN KEY     (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE,
+    FOREIGN KEY     (list_id) REFERENCES lists(id) ON DELETE CASCADE,
+    FOREIGN KEY     (todo_id) REFERENCES todos(id) ON DELETE CASCADE,
+    FOREIGN KEY     (created_by) REFERENCES users(id) ON DELETE SET NULL
+);
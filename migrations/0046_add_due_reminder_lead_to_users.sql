@@ -0,0 +1,4 @@
+-- Todos with a due date but no remind_at are reminded this many minutes before they are
+-- due. NULL turns these reminders off; an explicit remind_at always wins.
+ALTER TABLE users
+    ADD COLUMN due_reminder_minutes INT NULL DEFAULT 60;
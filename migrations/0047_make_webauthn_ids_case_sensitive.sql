@@ -0,0 +1,9 @@
+-- base64url is case sensitive, so credential ids and challenges must be compared byte for
+-- byte, not under the default case-insensitive collation. Credential ids can be up to 1023
+-- bytes, 1364 characters of base64url. ASCII keeps that within the index key limit, where
+-- utf8mb4 would not.
+ALTER TABLE webauthn_credentials
+    MODIFY credential_id VARCHAR(1364) CHARACTER SET ascii COLLATE ascii_bin NOT NULL;
+
+ALTER TABLE webauthn_challenges
+    MODIFY challenge CHAR(43) CHARACTER SET ascii COLLATE ascii_bin NOT NULL;
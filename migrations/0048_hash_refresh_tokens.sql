@@ -0,0 +1,3 @@
+-- Refresh tokens are stored as the hex SHA-256 of the token, like API keys, so a leaked
+-- table does not hand out sessions.
+UPDATE refresh_tokens SET token = SHA2(token, 256);
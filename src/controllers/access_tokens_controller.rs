@@ -28,7 +28,7 @@ use crate::{
 pub async fn access_tokens_index(
     Extension(pool): Extension<MySqlPool>
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let q = &format!("SELECT * FROM access_tokens");
+    let q = "SELECT * FROM access_tokens";
 
     let access_tokens = sqlx::query_as::<_, AccessToken>(q)
         .fetch_all(&pool)
@@ -83,8 +83,8 @@ pub async fn create_access_token(
     let now_in_dhaka: DateTime<FixedOffset> = Utc::now().with_timezone(&offset.unwrap());
     let expires_at = now_in_dhaka + Duration::days(7);
     let expires_at_formatted = expires_at.with_timezone(&Utc);
-    let token = generate_access_token(user_id, pool).await?;
-    let q = &format!("INSERT INTO access_tokens (user_id, token, expires_at) VALUES (?, ?, ?)");
+    let token = generate_access_token(user_id, None, pool).await?;
+    let q = "INSERT INTO access_tokens (user_id, token, expires_at) VALUES (?, ?, ?)";
 
     let access_token_id = sqlx::query(q)
         .bind(user_id)
@@ -1,17 +1,27 @@
+use chrono::{DateTime, Duration, Utc};
+use dotenv::dotenv;
 use sqlx::MySqlPool;
 use validator::Validate;
 
-use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension, Json};
+use axum::{extract::{Path, Query}, http::StatusCode, response::IntoResponse, Extension, Json};
 
 use crate::{
-    models::api_key::{ApiKey, CreateApiKey, FieldValue, UpdateApiKey},
-    utils::{input_validation::handle_validation_errors, tokens::generate_api_key},
+    models::api_key::{
+        ApiKey, CreateApiKey, CreatedApiKey, ExpiringApiKeysFilter, FieldValue, RotateApiKey, SigningSecret,
+        UpdateApiKey
+    },
+    utils::{
+        env::env_or,
+        input_validation::handle_validation_errors,
+        request_signing::generate_signing_secret,
+        tokens::{api_key_prefix, generate_api_key, hash_api_key}
+    },
 };
 
 pub async fn api_keys_index(
     Extension(pool): Extension<MySqlPool>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let q = &format!("SELECT * FROM api_keys");
+    let q = "SELECT * FROM api_keys";
 
     let api_keys = sqlx::query_as::<_, ApiKey>(q)
         .fetch_all(&pool)
@@ -63,24 +73,45 @@ pub async fn api_keys_create(
         )
     })?;
 
-    let api_key = create_api_key(&pool, &input.client_name, &input.contact_email).await?;
+    let expires_at = input
+        .expires_in_days
+        .map(|days| Utc::now() + Duration::days(days));
+
+    let api_key = create_api_key(
+        &pool,
+        &input.client_name,
+        &input.contact_email,
+        &input.scopes.join(" "),
+        expires_at,
+        false,
+    ).await?;
 
     Ok((StatusCode::CREATED, Json(api_key)))
 }
 
 // Helper function for creating api key.
+// The full secret is only part of the return value; the database keeps its hash and prefix.
 pub async fn create_api_key(
     pool: &MySqlPool,
     client_name: &str,
     contact_email: &str,
-) -> Result<ApiKey, (StatusCode, String)> {
-    let api_key = generate_api_key().await;
-    let q = &format!("INSERT INTO api_keys (api_key, client_name, contact_email) VALUES (?, ?, ?)");
+    scopes: &str,
+    expires_at: Option<DateTime<Utc>>,
+    is_active: bool,
+) -> Result<CreatedApiKey, (StatusCode, String)> {
+    let secret = generate_api_key().await;
+    let signing_secret = generate_signing_secret();
+    let q = "INSERT INTO api_keys (key_prefix, key_hash, signing_secret, client_name, contact_email, scopes, expires_at, is_active) VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
 
     let api_key_id = sqlx::query(q)
-        .bind(api_key)
+        .bind(api_key_prefix(&secret))
+        .bind(hash_api_key(&secret))
+        .bind(&signing_secret)
         .bind(client_name)
         .bind(contact_email)
+        .bind(scopes)
+        .bind(expires_at)
+        .bind(is_active)
         .execute(pool)
         .await
         .map_err(|e| {
@@ -92,7 +123,128 @@ pub async fn create_api_key(
 
     let api_key = fetch_api_key(pool, api_key_id.last_insert_id() as i32).await?;
 
-    Ok(api_key)
+    Ok(CreatedApiKey { api_key, secret, signing_secret })
+}
+
+// Issues a new HMAC signing secret for the key, replacing any previous one.
+// Also how keys created before request signing existed get their first secret.
+pub async fn api_keys_signing_secret(
+    Extension(pool): Extension<MySqlPool>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let signing_secret = generate_signing_secret();
+
+    let result = sqlx::query("UPDATE api_keys SET signing_secret = ? WHERE id = ?")
+        .bind(&signing_secret)
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to store signing secret in database: {}", e),
+            )
+        })?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Api key not found".to_string()));
+    }
+
+    Ok((StatusCode::CREATED, Json(SigningSecret { api_key_id: id, signing_secret })))
+}
+
+// Issues a replacement key with the same client details and scopes. The old key stays
+// valid for a grace period so the client can roll the new one out without downtime.
+pub async fn api_keys_rotate(
+    Extension(pool): Extension<MySqlPool>,
+    Path(id): Path<i32>,
+    input: Option<Json<RotateApiKey>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let input = input.map(|Json(input)| input).unwrap_or_default();
+    input.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Validation failed: {}", error_string),
+        )
+    })?;
+
+    let old_key = fetch_api_key(&pool, id).await?;
+
+    if old_key.replaced_by_id.is_some() {
+        return Err((StatusCode::CONFLICT, "Api key has already been rotated".to_string()));
+    }
+
+    let now = Utc::now();
+    let grace_period_hours = input.grace_period_hours.unwrap_or_else(default_rotation_grace_hours);
+
+    // Without an explicit lifetime the new key lives as long as the old one did.
+    let expires_at = match (input.expires_in_days, old_key.expires_at) {
+        (Some(days), _) => Some(now + Duration::days(days)),
+        (None, Some(old_expiry)) => Some(now + (old_expiry.with_timezone(&Utc) - old_key.created_at.with_timezone(&Utc))),
+        (None, None) => None,
+    };
+
+    let new_key = create_api_key(
+        &pool,
+        &old_key.client_name,
+        &old_key.contact_email,
+        &old_key.scopes,
+        expires_at,
+        old_key.is_active,
+    ).await?;
+
+    // Never extend the old key: keep its own expiry if that comes first.
+    let grace_ends_at = now + Duration::hours(grace_period_hours);
+    let old_expires_at = match old_key.expires_at {
+        Some(old_expiry) if old_expiry.with_timezone(&Utc) < grace_ends_at => old_expiry.with_timezone(&Utc),
+        _ => grace_ends_at,
+    };
+
+    sqlx::query("UPDATE api_keys SET expires_at = ?, replaced_by_id = ? WHERE id = ?")
+        .bind(old_expires_at)
+        .bind(new_key.api_key.id)
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retire rotated api key in database: {}", e),
+            )
+        })?;
+
+    Ok((StatusCode::CREATED, Json(new_key)))
+}
+
+// Active keys that expire within the next `within_days` days (default 30), soonest first.
+pub async fn api_keys_expiring(
+    Extension(pool): Extension<MySqlPool>,
+    Query(filter): Query<ExpiringApiKeysFilter>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let now = Utc::now();
+    let until = now + Duration::days(filter.within_days.unwrap_or(30).clamp(0, 3650));
+
+    let q = "SELECT * FROM api_keys WHERE is_active = true AND expires_at > ? AND expires_at <= ? ORDER BY expires_at ASC";
+
+    let api_keys = sqlx::query_as::<_, ApiKey>(q)
+        .bind(now)
+        .bind(until)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch expiring api keys from database: {}", e),
+            )
+        })?;
+
+    Ok((StatusCode::OK, Json(api_keys)))
+}
+
+fn default_rotation_grace_hours() -> i64 {
+    dotenv().ok();
+    env_or("API_KEY_ROTATION_GRACE_HOURS", 24)
 }
 
 pub async fn api_keys_update(
@@ -100,6 +252,14 @@ pub async fn api_keys_update(
     Path(id): Path<i32>,
     Json(updates): Json<UpdateApiKey>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
+    updates.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Validation failed: {}", error_string),
+        )
+    })?;
+
     let mut query_string = "UPDATE api_keys SET ".to_string();
     let mut params: Vec<String> = vec![];
 
@@ -107,11 +267,16 @@ pub async fn api_keys_update(
     if !params.is_empty() {
         query_string.truncate(query_string.len() - 2);
     }
-    query_string.push_str(&format!(" WHERE id = {}", id));
+    query_string.push_str(" WHERE id = ?");
 
-    println!("query_string: {}", query_string);
+    // Bind the values in the order their placeholders were added, then the id.
+    let mut query = sqlx::query(&query_string);
+    for param in params {
+        query = query.bind(param);
+    }
 
-    sqlx::query(&query_string)
+    query
+        .bind(id)
         .execute(&pool)
         .await
         .map_err(|e| {
@@ -126,6 +291,8 @@ pub async fn api_keys_update(
     Ok((StatusCode::OK, Json(api_key)))
 }
 
+// Every value gets a placeholder in the query string and is added to params, to be bound
+// in the same order.
 pub async fn api_keys_update_query_builder(
     query: &mut String,
     params: &mut Vec<String>,
@@ -133,28 +300,30 @@ pub async fn api_keys_update_query_builder(
 ) {
     for (field, value) in updates.clone().into_iter() {
         match value {
-            FieldValue::ApiKey(api_key) => {
-                if let Some(api_key) = api_key {
-                    query.push_str(&format!("{} = '{}', ", field, api_key));
-                    params.push(api_key);
-                }
-            }
             FieldValue::ClientName(client_name) => {
                 if let Some(client_name) = client_name {
-                    query.push_str(&format!("{} = '{}', ", field, client_name));
+                    query.push_str(&format!("{} = ?, ", field));
                     params.push(client_name);
                 }
             }
             FieldValue::ContactEmail(contact_email) => {
                 if let Some(contact_email) = contact_email {
-                    query.push_str(&format!("{} = '{}', ", field, contact_email));
+                    query.push_str(&format!("{} = ?, ", field));
                     params.push(contact_email);
                 }
             }
+            FieldValue::Scopes(scopes) => {
+                if let Some(scopes) = scopes {
+                    // Scopes are checked against API_KEY_SCOPES before we get here.
+                    let scopes = scopes.join(" ");
+                    query.push_str(&format!("{} = ?, ", field));
+                    params.push(scopes);
+                }
+            }
             FieldValue::IsActive(is_active) => {
                 if let Some(is_active) = is_active {
-                    query.push_str(&format!("{} = {}, ", field, is_active));
-                    params.push(is_active.to_string());
+                    query.push_str(&format!("{} = ?, ", field));
+                    params.push((is_active as i32).to_string());
                 }
             }
         }
@@ -1,24 +1,46 @@
 use argon2::{PasswordHash, PasswordVerifier};
-use axum::{extract::Extension, http::StatusCode, response::IntoResponse, Json};
+use axum::{extract::Extension, http::StatusCode, response::{IntoResponse, Response}, Json};
 use chrono::{Duration, FixedOffset, Utc};
 use sqlx::MySqlPool;
 use validator::Validate;
 
-use tower_cookies::{Cookie, Cookies};
+use tower_cookies::Cookies;
 
 use crate::{
     models::{
-        auth::{LoginUser, LogoutUser, RefreshUser, ResponseMessage}, refresh_token::RefreshToken, user::User
+        auth::{LoginUser, ResponseMessage, TokenRequest, TokenResponse},
+        refresh_token::RefreshToken,
+        user::User
     },
     utils::{
+        client_ip::ClientIp,
+        cookies::{
+            access_token_cookie, csrf_cookie, generate_csrf_token, refresh_token_cookie, removal_cookies,
+            CSRF_COOKIE, REFRESH_TOKEN_TTL_DAYS,
+        },
         input_validation::handle_validation_errors,
-        tokens::{generate_access_token, generate_refresh_token},
+        lockout::{
+            clear_lockout, ip_key, locked_response, record_attempt, register_failure, retry_after, user_key,
+            LockoutPolicy,
+        },
+        notifications::LoginDevices,
+        password::{hash_password, needs_rehash},
+        tokens::{generate_access_token, generate_refresh_token, hash_api_key, ACCESS_TOKEN_TTL_MINUTES},
     },
 };
 
+// Result of checking a username and password.
+pub enum Authentication {
+    Authenticated(User),
+    LockedOut { retry_after: i64 },
+}
+
 pub async fn login(
     Extension(pool): Extension<MySqlPool>,
+    Extension(lockout_policy): Extension<LockoutPolicy>,
+    ClientIp(ip_address): ClientIp,
     cookies: Cookies,
+    login_devices: LoginDevices,
     Json(payload): Json<LoginUser>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     payload.validate().map_err(|errors| {
@@ -26,123 +48,250 @@ pub async fn login(
         (StatusCode::BAD_REQUEST, error_message)
     })?;
 
-    let q = format!(
-        "SELECT * FROM users WHERE username = '{}'",
-        payload.username
-    );
+    let user = match authenticate(&pool, &lockout_policy, &ip_address, &payload).await? {
+        Authentication::Authenticated(user) => user,
+        Authentication::LockedOut { retry_after } => return Ok(locked_response(retry_after)),
+    };
 
-    let user_result = sqlx::query_as::<_, User>(&q).fetch_one(&pool).await;
+    let (token, refresh_token) = issue_tokens(&pool, &user).await?;
+    set_session_cookies(&cookies, token, refresh_token);
+    login_devices.notice_browser_login(&user, &ip_address).await;
 
-    match user_result {
-        Ok(user) => {
-            let parsed_hash = PasswordHash::new(&user.password_hash).map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to parse password hash: {}", e),
-                )
+    // Build the response and attach cookies
+    let response = (
+        StatusCode::OK,
+        Json(ResponseMessage {
+            message: "Login Successful".to_string(),
+        }),
+    )
+        .into_response();
+
+    Ok(response)
+}
+
+// Token endpoint for CLI tools and server-to-server callers that cannot hold cookies.
+// Tokens are returned in the body; send the access token back as `Authorization: Bearer <jwt>`.
+pub async fn token(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(lockout_policy): Extension<LockoutPolicy>,
+    ClientIp(ip_address): ClientIp,
+    login_devices: LoginDevices,
+    Json(payload): Json<TokenRequest>,
+) -> Result<Response, (StatusCode, String)> {
+    let (access_token, refresh_token) = match payload {
+        TokenRequest::Password { username, password } => {
+            let credentials = LoginUser { username, password };
+            credentials.validate().map_err(|errors| {
+                let error_message = handle_validation_errors(errors);
+                (StatusCode::BAD_REQUEST, error_message)
             })?;
 
-            if argon2::Argon2::default()
-                .verify_password(payload.password.as_bytes(), &parsed_hash)
-                .is_ok()
-            {
-                // Successful authentication
-
-                // Generate JWT access token
-                let token = generate_access_token(&user.id, &pool).await?;
-
-                println!("Generated JWT access token: {}", token);
-
-                // Generate refresh token
-                let refresh_token = generate_refresh_token(&pool).await;
-
-                // Store refresh token in the database
-                let offset = FixedOffset::east_opt(6 * 3600);
-                let now_dhaka = Utc::now().with_timezone(&offset.unwrap());
-                let expires_at = now_dhaka + Duration::days(7);
-                let expires_at_formatted = expires_at.with_timezone(&Utc);
-
-                let q = format!(
-                    "INSERT INTO refresh_tokens (token, user_id, expires_at) VALUES (?, ?, ?)"
-                );
-
-                sqlx::query(&q)
-                    .bind(&refresh_token)
-                    .bind(&user.id)
-                    .bind(&expires_at_formatted)
-                    .execute(&pool)
-                    .await
-                    .map_err(|e| {
-                        (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            format!("Failed to store refresh token in database: {}", e),
-                        )
-                    })?;
-
-                // Create cookies for access and refresh tokens
-                let access_token_cookie = Cookie::build(("access_token", token))
-                    .http_only(true)
-                    .path("/api")
-                    .build();
-
-                let refresh_token_cookie = Cookie::build(("refresh_token", refresh_token))
-                    .http_only(true)
-                    .path("/api") // Restrict to refresh route only
-                    .build();
-
-                // Add the cookies to the existing cookies object
-                cookies.add(access_token_cookie);
-                cookies.add(refresh_token_cookie);
-
-                // Build the response and attach cookies
-                let response = (
-                    StatusCode::OK,
-                    Json(ResponseMessage {
-                        message: "Login Successful".to_string(),
-                    }),
-                )
-                    .into_response();
+            let user = match authenticate(&pool, &lockout_policy, &ip_address, &credentials).await? {
+                Authentication::Authenticated(user) => user,
+                Authentication::LockedOut { retry_after } => return Ok(locked_response(retry_after)),
+            };
+
+            login_devices.notice_api_login(&user, &ip_address).await;
 
-                Ok(response)
-            } else {
-                Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()))
+            issue_tokens(&pool, &user).await?
+        }
+        TokenRequest::RefreshToken { refresh_token } => {
+            let token_data = sqlx::query_as::<_, RefreshToken>("SELECT * FROM refresh_tokens WHERE token = ?")
+                .bind(hash_api_key(&refresh_token))
+                .fetch_optional(&pool)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch refresh token from database: {}", e)))?
+                .ok_or((StatusCode::UNAUTHORIZED, "Invalid refresh token".to_string()))?;
+
+            if token_data.expires_at < Utc::now() {
+                return Err((StatusCode::UNAUTHORIZED, "Refresh token expired".to_string()));
             }
+
+            let access_token = generate_access_token(&token_data.user_id, token_data.workspace_id, &pool).await?;
+
+            // Rotate the refresh token so a leaked one can only be used once.
+            let new_refresh_token = generate_refresh_token(&pool).await;
+
+            sqlx::query("UPDATE refresh_tokens SET token = ?, expires_at = ? WHERE id = ?")
+                .bind(hash_api_key(&new_refresh_token))
+                .bind(Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS))
+                .bind(token_data.id)
+                .execute(&pool)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update refresh token in database: {}", e)))?;
+
+            (access_token, new_refresh_token)
         }
-        Err(_) => Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string())),
+    };
+
+    let response = (
+        StatusCode::OK,
+        Json(TokenResponse {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: ACCESS_TOKEN_TTL_MINUTES * 60,
+            refresh_token,
+        }),
+    )
+        .into_response();
+
+    Ok(response)
+}
+
+// Checks the credentials, applying lockouts and recording the attempt.
+// Shared by every endpoint that accepts a username and password.
+pub async fn authenticate(
+    pool: &MySqlPool,
+    lockout_policy: &LockoutPolicy,
+    ip_address: &str,
+    payload: &LoginUser,
+) -> Result<Authentication, (StatusCode, String)> {
+    // Refuse to even look at the password while the username or the caller's IP is locked out.
+    let user_lock_key = user_key(&payload.username);
+    let lock_keys = [user_lock_key.clone(), ip_key(ip_address)];
+
+    if let Some(seconds) = retry_after(pool, &lock_keys).await? {
+        record_attempt(pool, &payload.username, ip_address, false, Some("locked_out")).await?;
+        return Ok(Authentication::LockedOut { retry_after: seconds });
+    }
+
+    let user_result = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
+        .bind(&payload.username)
+        .fetch_one(pool)
+        .await;
+
+    let Ok(user) = user_result else {
+        return login_failed(pool, lockout_policy, &payload.username, ip_address, "unknown_user").await;
+    };
+
+    let parsed_hash = PasswordHash::new(&user.password_hash).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to parse password hash: {}", e),
+        )
+    })?;
+
+    if argon2::Argon2::default()
+        .verify_password(payload.password.as_bytes(), &parsed_hash)
+        .is_err()
+    {
+        return login_failed(pool, lockout_policy, &payload.username, ip_address, "invalid_password").await;
+    }
+
+    // Successful authentication
+    record_attempt(pool, &payload.username, ip_address, true, None).await?;
+    // Only the account counter is reset. The IP counter keeps running so that one
+    // valid account cannot be used to wipe the history of a password-spraying client.
+    clear_lockout(pool, &user_lock_key).await?;
+
+    // Upgrade hashes made with older Argon2 settings while we have the plaintext.
+    if needs_rehash(&parsed_hash) {
+        let password_hash = hash_password(&payload.password)?;
+
+        sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+            .bind(password_hash)
+            .bind(user.id)
+            .execute(pool)
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to update password hash in database: {}", e),
+                )
+            })?;
     }
+
+    Ok(Authentication::Authenticated(user))
+}
+
+// Creates a new access token and a stored refresh token for the user.
+pub async fn issue_tokens(pool: &MySqlPool, user: &User) -> Result<(String, String), (StatusCode, String)> {
+    issue_workspace_tokens(pool, user.id, None).await
+}
+
+// Tokens acting in the given workspace, which the refresh token keeps through refreshes.
+// None is the user's personal workspace.
+pub async fn issue_workspace_tokens(pool: &MySqlPool, user_id: i32, workspace_id: Option<i32>) -> Result<(String, String), (StatusCode, String)> {
+    // Generate JWT access token
+    let token = generate_access_token(&user_id, workspace_id, pool).await?;
+
+    // Generate refresh token
+    let refresh_token = generate_refresh_token(pool).await;
+
+    // Store refresh token in the database
+    let offset = FixedOffset::east_opt(6 * 3600);
+    let now_dhaka = Utc::now().with_timezone(&offset.unwrap());
+    let expires_at = now_dhaka + Duration::days(REFRESH_TOKEN_TTL_DAYS);
+    let expires_at_formatted = expires_at.with_timezone(&Utc);
+
+    let q = "INSERT INTO refresh_tokens (token, user_id, expires_at, workspace_id) VALUES (?, ?, ?, ?)";
+
+    sqlx::query(q)
+        .bind(hash_api_key(&refresh_token))
+        .bind(user_id)
+        .bind(expires_at_formatted)
+        .bind(workspace_id)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to store refresh token in database: {}", e),
+            )
+        })?;
+
+    Ok((token, refresh_token))
 }
 
+// Create cookies for access and refresh tokens, plus the CSRF token the client must echo back.
+// Every browser login ends here, whichever way the user authenticated.
+pub fn set_session_cookies(cookies: &Cookies, access_token: String, refresh_token: String) {
+    cookies.add(access_token_cookie(access_token));
+    cookies.add(refresh_token_cookie(refresh_token));
+    cookies.add(csrf_cookie(generate_csrf_token()));
+}
+
+// Records a failed login against both the username and the IP and answers with the generic error.
+async fn login_failed(
+    pool: &MySqlPool,
+    lockout_policy: &LockoutPolicy,
+    username: &str,
+    ip_address: &str,
+    reason: &str,
+) -> Result<Authentication, (StatusCode, String)> {
+    record_attempt(pool, username, ip_address, false, Some(reason)).await?;
+    register_failure(pool, lockout_policy, &user_key(username)).await?;
+    register_failure(pool, lockout_policy, &ip_key(ip_address)).await?;
+
+    Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()))
+}
+
+// Ends the session of the browser it is called from: its refresh token, taken from the
+// cookie, is deleted. Other sessions of the user are left alone.
 pub async fn logout(
     Extension(pool): Extension<MySqlPool>,
     cookies: Cookies,
-    Json(payload): Json<LogoutUser>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    // Clear the access token cookie
-    // Clear the refresh token cookie
-    let access_token_cookie = Cookie::build(("access_token", ""))
-        .http_only(true)
-        .path("/api")
-        .build();
-
-    let refresh_token_cookie = Cookie::build(("refresh_token", ""))
-        .http_only(true)
-        .path("/api") // Restrict to refresh route only
-        .build();
+    let refresh_token = cookies.get("refresh_token").map(|cookie| cookie.value().to_string());
 
-    cookies.add(access_token_cookie);
-    cookies.add(refresh_token_cookie);
+    // Clear the access token, refresh token and CSRF cookies
+    for cookie in removal_cookies() {
+        cookies.add(cookie);
+    }
 
     // Invalidate the refresh token in the database
-    let q = format!(
-        "DELETE FROM refresh_tokens WHERE user_id = '{}'",
-        payload.user_id
-    );
-    sqlx::query(&q).execute(&pool).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to invalidate refresh token in database: {}", e),
-        )
-    })?;
+    if let Some(refresh_token) = refresh_token {
+        sqlx::query("DELETE FROM refresh_tokens WHERE token = ?")
+            .bind(hash_api_key(&refresh_token))
+            .execute(&pool)
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to invalidate refresh token in database: {}", e),
+                )
+            })?;
+    }
 
     // Build the response.
     let response = (
@@ -159,7 +308,6 @@ pub async fn logout(
 pub async fn refresh(
     Extension(pool): Extension<MySqlPool>,
     cookies: Cookies,
-    Json(payload): Json<RefreshUser>
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     let refresh_token = cookies
         .get("refresh_token")
@@ -167,11 +315,9 @@ pub async fn refresh(
         .value()
         .to_string();
 
-    println!("Refresh token: {}", refresh_token);
     // 1. Retrieve the refresh token from the database
-    let q = &format!("SELECT * FROM refresh_tokens WHERE token = '{refresh_token}'");
-
-    let token_data = sqlx::query_as::<_, RefreshToken>(q)
+    let token_data = sqlx::query_as::<_, RefreshToken>("SELECT * FROM refresh_tokens WHERE token = ?")
+        .bind(hash_api_key(&refresh_token))
         .fetch_optional(&pool)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch refresh token from database: {}", e)))?;
@@ -183,11 +329,8 @@ pub async fn refresh(
         return Err((StatusCode::UNAUTHORIZED, "Refresh token expired".to_string()));
     }
 
-    // Convert to i32
-    let user_id = payload.user_id.parse::<i32>().unwrap();
-
-    // 3. If the refresh token is valid, generate a new access token
-    let new_access_token = generate_access_token(&user_id, &pool)
+    // 3. If the refresh token is valid, generate a new access token for its user
+    let new_access_token = generate_access_token(&token_data.user_id, token_data.workspace_id, &pool)
         .await
         .map_err(|_| {(StatusCode::INTERNAL_SERVER_ERROR, "Failed to generate access token".to_string())})?;
 
@@ -195,19 +338,19 @@ pub async fn refresh(
     let new_refresh_token = generate_refresh_token(&pool).await;
 
     sqlx::query("UPDATE refresh_tokens SET token = ?, expires_at = ? WHERE id = ?")
-        .bind(&new_refresh_token)
-        .bind(Utc::now() + Duration::days(30))
-        .bind(&payload.user_id)
+        .bind(hash_api_key(&new_refresh_token))
+        .bind(Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS))
+        .bind(token_data.id)
         .execute(&pool)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update refresh token in database: {}", e)))?;
 
-    // 5. Set the new access token as a cookie
-    let access_token_cookie = Cookie::build(("access_token", new_access_token))
-        .http_only(true)
-        .path("/api")
-        .build();
-    cookies.add(access_token_cookie);
+    // 5. Set the new access and refresh tokens as cookies
+    cookies.add(access_token_cookie(new_access_token));
+    cookies.add(refresh_token_cookie(new_refresh_token));
+    if cookies.get(CSRF_COOKIE).is_none() {
+        cookies.add(csrf_cookie(generate_csrf_token()));
+    }
 
     // 6. Build the response and attach the new access token cookie
     let response = (
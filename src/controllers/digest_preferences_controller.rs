@@ -0,0 +1,61 @@
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension, Json};
+use sqlx::MySqlPool;
+use validator::Validate;
+
+use crate::{
+    models::digest::{DigestPreferences, UpdateDigestPreferences},
+    utils::{digests::reschedule_digest, input_validation::handle_validation_errors, workspaces::Tenant},
+};
+
+pub async fn digest_preferences_find(
+    Extension(pool): Extension<MySqlPool>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    Ok((StatusCode::OK, Json(fetch_digest_preferences(&pool, id).await?)))
+}
+
+// PATCH /api/users/:id/digest_preferences
+// {"frequency": "weekly", "hour": 8, "weekday": 1}
+pub async fn digest_preferences_update(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i32>,
+    Json(updates): Json<UpdateDigestPreferences>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    updates.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string))
+    })?;
+
+    tenant.require_self_or_admin(id)?;
+    fetch_digest_preferences(&pool, id).await?;
+
+    let q = "UPDATE users SET digest_frequency = COALESCE(?, digest_frequency), digest_hour = COALESCE(?, digest_hour), \
+             digest_weekday = COALESCE(?, digest_weekday) WHERE id = ?";
+
+    sqlx::query(q)
+        .bind(&updates.frequency)
+        .bind(updates.hour)
+        .bind(updates.weekday)
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update digest preferences: {}", e)))?;
+
+    reschedule_digest(&pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok((StatusCode::OK, Json(fetch_digest_preferences(&pool, id).await?)))
+}
+
+async fn fetch_digest_preferences(pool: &MySqlPool, id: i32) -> Result<DigestPreferences, (StatusCode, String)> {
+    let q = "SELECT time_zone, digest_frequency, digest_hour, digest_weekday, next_digest_at FROM users WHERE id = ?";
+
+    sqlx::query_as::<_, DigestPreferences>(q)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch digest preferences from database: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))
+}
@@ -0,0 +1,257 @@
+use std::{convert::Infallible, future::Future, time::Duration};
+
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Query,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Extension,
+};
+use chrono::Utc;
+use serde::Serialize;
+use tokio::{sync::mpsc, time::{interval, sleep, Instant}};
+use tokio_stream::wrappers::ReceiverStream;
+use url::Url;
+
+use crate::{
+    models::{
+        auth::Claims,
+        event::{ClientMessage, ControlMessage, ResumeQuery},
+    },
+    utils::events::{Delivery, EventBus, Subscription},
+};
+
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+// A ping goes out this often; a client that has not been heard from for the timeout is dropped.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(90);
+
+// GET /api/ws?last_event_id=<id>
+// Pushes the user's todo and account events. Pass the id of the last event seen to get the
+// ones missed while disconnected; if they are no longer available a "resync" message is sent
+// instead.
+pub async fn events_ws(
+    ws: WebSocketUpgrade,
+    Extension(bus): Extension<EventBus>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Query(resume): Query<ResumeQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    check_origin(&headers)?;
+
+    let user_id = claims_user_id(&claims)?;
+    let (subscription, backlog) = bus
+        .subscribe(user_id, resume.last_event_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read event log: {}", e)))?;
+    let token_ttl = token_ttl(&claims);
+    let closed = bus.closed();
+
+    Ok(ws.on_upgrade(move |socket| stream_events(socket, subscription, backlog, token_ttl, closed)))
+}
+
+// GET /api/events
+// The same events as /api/ws as Server-Sent Events, for clients behind proxies that break
+// WebSockets. EventSource sends Last-Event-ID by itself when it reconnects; the first
+// connection can pass ?last_event_id= instead.
+pub async fn events_stream(
+    Extension(bus): Extension<EventBus>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Query(resume): Query<ResumeQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user_id = claims_user_id(&claims)?;
+    let resume_from = headers
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .or(resume.last_event_id);
+
+    let (mut subscription, backlog) = bus
+        .subscribe(user_id, resume_from)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read event log: {}", e)))?;
+    let token_ttl = token_ttl(&claims);
+
+    // The stream ends when the client goes away, which closes the channel, when the token
+    // expires or when the server shuts down. EventSource then reconnects with whatever token
+    // the browser has by then.
+    let closed = bus.closed();
+    let (sender, receiver) = mpsc::channel::<Result<SseEvent, Infallible>>(32);
+    tokio::spawn(async move {
+        for delivery in backlog {
+            if sender.send(Ok(sse_event(&delivery))).await.is_err() {
+                return;
+            }
+        }
+
+        let token_expiry = sleep(token_ttl);
+        tokio::pin!(token_expiry, closed);
+
+        loop {
+            tokio::select! {
+                _ = &mut token_expiry => break,
+                _ = &mut closed => break,
+                _ = sender.closed() => break,
+                delivery = subscription.next() => {
+                    let Some(delivery) = delivery else {
+                        break;
+                    };
+                    if sender.send(Ok(sse_event(&delivery))).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(Sse::new(ReceiverStream::new(receiver)).keep_alive(KeepAlive::default()))
+}
+
+async fn stream_events(
+    mut socket: WebSocket,
+    mut subscription: Subscription,
+    backlog: Vec<Delivery>,
+    token_ttl: Duration,
+    closed: impl Future<Output = ()>,
+) {
+    for delivery in &backlog {
+        if !send_delivery(&mut socket, delivery).await {
+            return;
+        }
+    }
+
+    let ready = ControlMessage::Ready { last_event_id: subscription.last_event_id() };
+    if !send_json(&mut socket, &ready).await {
+        return;
+    }
+
+    let mut heartbeat = interval(HEARTBEAT_INTERVAL);
+    let token_expiry = sleep(token_ttl);
+    tokio::pin!(token_expiry, closed);
+    let mut last_heard = Instant::now();
+
+    loop {
+        tokio::select! {
+            _ = heartbeat.tick() => {
+                if last_heard.elapsed() > CLIENT_TIMEOUT {
+                    break;
+                }
+                if socket.send(Message::Ping(Vec::new())).await.is_err() {
+                    break;
+                }
+            }
+            _ = &mut token_expiry => {
+                let _ = socket
+                    .send(Message::Close(Some(CloseFrame { code: 4001, reason: "Token expired".into() })))
+                    .await;
+                break;
+            }
+            _ = &mut closed => {
+                let _ = socket
+                    .send(Message::Close(Some(CloseFrame { code: close_code::AWAY, reason: "Server shutting down".into() })))
+                    .await;
+                break;
+            }
+            delivery = subscription.next() => {
+                let Some(delivery) = delivery else {
+                    break;
+                };
+                if !send_delivery(&mut socket, &delivery).await {
+                    break;
+                }
+            }
+            message = socket.recv() => {
+                let Some(Ok(message)) = message else {
+                    break;
+                };
+                last_heard = Instant::now();
+
+                match message {
+                    Message::Text(text) => {
+                        if let Ok(ClientMessage::Ping) = serde_json::from_str(&text) {
+                            if !send_json(&mut socket, &ControlMessage::Pong).await {
+                                break;
+                            }
+                        }
+                    }
+                    Message::Close(_) => break,
+                    // Pings are answered by axum itself; pongs only count as a sign of life.
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+async fn send_delivery(socket: &mut WebSocket, delivery: &Delivery) -> bool {
+    match delivery {
+        Delivery::Event(event) => send_json(socket, &**event).await,
+        Delivery::Resync { last_event_id } => send_json(socket, &ControlMessage::Resync { last_event_id: *last_event_id }).await,
+    }
+}
+
+fn sse_event(delivery: &Delivery) -> SseEvent {
+    match delivery {
+        Delivery::Event(event) => SseEvent::default()
+            .id(event.id.to_string())
+            .event(&event.kind)
+            .data(serde_json::to_string(&**event).unwrap_or_default()),
+        Delivery::Resync { last_event_id } => SseEvent::default()
+            .id(last_event_id.to_string())
+            .event("resync")
+            .data(serde_json::to_string(&ControlMessage::Resync { last_event_id: *last_event_id }).unwrap_or_default()),
+    }
+}
+
+fn claims_user_id(claims: &Claims) -> Result<i32, (StatusCode, String)> {
+    claims
+        .sub
+        .parse()
+        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token subject".to_string()))
+}
+
+// Streams outlive no token: they are closed when the access token expires.
+fn token_ttl(claims: &Claims) -> Duration {
+    Duration::from_secs((claims.exp as i64 - Utc::now().timestamp()).max(0) as u64)
+}
+
+async fn send_json(socket: &mut WebSocket, message: &impl Serialize) -> bool {
+    match serde_json::to_string(message) {
+        Ok(text) => socket.send(Message::Text(text)).await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+// Browsers send cookies along with cross-site WebSocket handshakes and CORS does not apply
+// to them, so a page on another site must not be able to open a socket as the user.
+// Clients that are not browsers send no Origin and are let through.
+fn check_origin(headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return Ok(());
+    };
+
+    let origin_host = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| Url::parse(origin).ok())
+        .and_then(|url| {
+            let host = url.host_str()?.to_string();
+            Some(match url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host,
+            })
+        });
+    let host = headers.get(header::HOST).and_then(|host| host.to_str().ok());
+
+    match (origin_host, host) {
+        (Some(origin_host), Some(host)) if origin_host.eq_ignore_ascii_case(host) => Ok(()),
+        _ => Err((StatusCode::FORBIDDEN, "Cross-origin WebSocket connections are not allowed".to_string())),
+    }
+}
@@ -0,0 +1,156 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::{Duration, Utc};
+use sqlx::MySqlPool;
+use validator::Validate;
+
+use crate::{
+    models::{
+        auth::{Actor, Claims, ResponseMessage},
+        impersonation::{ImpersonationFilter, ImpersonationResponse, ImpersonationSession, StartImpersonation},
+        user::User,
+    },
+    utils::{
+        client_ip::ClientIp,
+        input_validation::handle_validation_errors,
+        tokens::{generate_impersonation_token, IMPERSONATION_TTL_MINUTES},
+    },
+};
+
+// POST /api/admin/impersonate/:user_id
+// Starts an impersonation session and returns a short-lived bearer token for the user.
+// The admin's own session is left alone, so closing the impersonation is just dropping the token.
+pub async fn impersonation_create(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+    ClientIp(ip_address): ClientIp,
+    Path(user_id): Path<i32>,
+    Json(payload): Json<StartImpersonation>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    payload.validate().map_err(|errors| {
+        let error_message = handle_validation_errors(errors);
+        (StatusCode::BAD_REQUEST, error_message)
+    })?;
+
+    let admin_id: i32 = claims
+        .sub
+        .parse()
+        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token subject".to_string()))?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch user from database: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))?;
+
+    // Impersonating an admin would hand out admin access without the audit of a real login.
+    if user.id == admin_id || user.is_admin {
+        return Err((StatusCode::FORBIDDEN, "Admins cannot be impersonated".to_string()));
+    }
+
+    let q = "INSERT INTO impersonation_sessions (admin_id, user_id, reason, ip_address, expires_at) VALUES (?, ?, ?, ?, ?)";
+    let result = sqlx::query(q)
+        .bind(admin_id)
+        .bind(user.id)
+        .bind(payload.reason.trim())
+        .bind(&ip_address)
+        .bind(Utc::now() + Duration::minutes(IMPERSONATION_TTL_MINUTES))
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start impersonation session: {}", e)))?;
+
+    let session_id = result.last_insert_id() as i32;
+    let actor = Actor { sub: admin_id.to_string(), sid: session_id };
+    let access_token = generate_impersonation_token(&user.id, actor)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ImpersonationResponse {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: IMPERSONATION_TTL_MINUTES * 60,
+            session_id,
+            user_id: user.id,
+        }),
+    ))
+}
+
+// POST /api/impersonation/stop
+// Called with the impersonation token itself. The token stops working immediately.
+pub async fn impersonation_stop(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let actor = claims
+        .act
+        .ok_or((StatusCode::BAD_REQUEST, "Not an impersonation session".to_string()))?;
+    let admin_id: i32 = actor
+        .sub
+        .parse()
+        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token actor".to_string()))?;
+
+    end_session(&pool, actor.sid, admin_id).await?;
+
+    Ok((StatusCode::OK, Json(ResponseMessage { message: "Impersonation stopped".to_string() })))
+}
+
+// GET /api/admin/impersonations
+pub async fn impersonations_index(
+    Extension(pool): Extension<MySqlPool>,
+    Query(filter): Query<ImpersonationFilter>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let q = "SELECT * FROM impersonation_sessions \
+             WHERE (? IS NULL OR admin_id = ?) AND (? IS NULL OR user_id = ?) \
+             ORDER BY id DESC LIMIT ?";
+
+    let sessions = sqlx::query_as::<_, ImpersonationSession>(q)
+        .bind(filter.admin_id)
+        .bind(filter.admin_id)
+        .bind(filter.user_id)
+        .bind(filter.user_id)
+        .bind(filter.limit.unwrap_or(100).min(1000))
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch impersonation sessions from database: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(sessions)))
+}
+
+// DELETE /api/admin/impersonations/:id
+// Lets any admin cut an impersonation session short.
+pub async fn impersonations_delete(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let admin_id: i32 = claims
+        .sub
+        .parse()
+        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token subject".to_string()))?;
+
+    end_session(&pool, id, admin_id).await?;
+
+    Ok((StatusCode::OK, Json(ResponseMessage { message: "Impersonation stopped".to_string() })))
+}
+
+// Rows are never deleted: the session is the audit trail.
+async fn end_session(pool: &MySqlPool, id: i32, ended_by: i32) -> Result<(), (StatusCode, String)> {
+    let result = sqlx::query("UPDATE impersonation_sessions SET ended_at = ?, ended_by = ? WHERE id = ? AND ended_at IS NULL")
+        .bind(Utc::now())
+        .bind(ended_by)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to stop impersonation session: {}", e)))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "No open impersonation session".to_string()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+
+use crate::utils::jwt_keys::keyset;
+
+// Public signing keys, so other services can verify access tokens issued by this API.
+pub async fn jwks_show() -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(keyset().jwks()),
+    )
+}
@@ -0,0 +1,228 @@
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde::Serialize;
+use sqlx::{MySqlConnection, MySqlPool};
+use validator::Validate;
+
+use crate::{
+    models::{
+        auth::ResponseMessage,
+        event::{DeletedResource, Event},
+        list::{CreateList, List, UpdateList},
+        todo::Todo,
+        workspace::WorkspaceRole,
+    },
+    utils::{
+        events::{self, EventBus},
+        input_validation::handle_validation_errors,
+        webhooks,
+        workspaces::{require_list, Tenant},
+    },
+};
+
+pub async fn lists_index(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(tenant): Extension<Tenant>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let lists = sqlx::query_as::<_, List>("SELECT * FROM lists WHERE workspace_id = ? ORDER BY name")
+        .bind(tenant.workspace_id)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch lists from database: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(lists)))
+}
+
+pub async fn lists_create(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(bus): Extension<EventBus>,
+    Extension(tenant): Extension<Tenant>,
+    Json(input): Json<CreateList>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    input.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string))
+    })?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))?;
+
+    let id = sqlx::query("INSERT INTO lists (workspace_id, name, created_by) VALUES (?, ?, ?)")
+        .bind(tenant.workspace_id)
+        .bind(input.name.trim())
+        .bind(tenant.user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create list: {}", e)))?
+        .last_insert_id() as i32;
+
+    let list = sqlx::query_as::<_, List>("SELECT * FROM lists WHERE id = ?")
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch list from database: {}", e)))?;
+
+    webhooks::enqueue(&mut *tx, tenant.user_id, "list.created", &list).await?;
+    let events = record_for_members(&mut tx, &tenant, "list.created", &list).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit list: {}", e)))?;
+
+    bus.send(events);
+
+    Ok((StatusCode::CREATED, Json(list)))
+}
+
+pub async fn lists_find(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let list = fetch_list(&pool, &tenant, id).await?;
+
+    Ok((StatusCode::OK, Json(list)))
+}
+
+pub async fn lists_update(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(bus): Extension<EventBus>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i32>,
+    Json(input): Json<UpdateList>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    input.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string))
+    })?;
+
+    authorize_list(&pool, &tenant, id).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))?;
+
+    sqlx::query("UPDATE lists SET name = ? WHERE id = ?")
+        .bind(input.name.trim())
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update list: {}", e)))?;
+
+    let list = sqlx::query_as::<_, List>("SELECT * FROM lists WHERE id = ?")
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch list from database: {}", e)))?;
+
+    webhooks::enqueue(&mut *tx, tenant.user_id, "list.updated", &list).await?;
+    let events = record_for_members(&mut tx, &tenant, "list.updated", &list).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit list: {}", e)))?;
+
+    bus.send(events);
+
+    Ok((StatusCode::OK, Json(list)))
+}
+
+// The list's todos stay in the workspace, on no list.
+pub async fn lists_delete(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(bus): Extension<EventBus>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    authorize_list(&pool, &tenant, id).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))?;
+
+    sqlx::query("DELETE FROM lists WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete list: {}", e)))?;
+
+    webhooks::enqueue(&mut *tx, tenant.user_id, "list.deleted", &DeletedResource { id }).await?;
+    let events = record_for_members(&mut tx, &tenant, "list.deleted", &DeletedResource { id }).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit list deletion: {}", e)))?;
+
+    bus.send(events);
+
+    Ok((StatusCode::OK, Json(ResponseMessage { message: "List deleted".to_string() })))
+}
+
+// GET /api/lists/:id/todos
+pub async fn lists_todos(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    require_list(&pool, tenant.workspace_id, id).await?;
+
+    let todos = sqlx::query_as::<_, Todo>("SELECT * FROM todos WHERE list_id = ? AND workspace_id = ? ORDER BY id")
+        .bind(id)
+        .bind(tenant.workspace_id)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch todos from database: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(todos)))
+}
+
+// Lists are changed by whoever made them, and by workspace admins.
+pub async fn authorize_list(pool: &MySqlPool, tenant: &Tenant, id: i32) -> Result<List, (StatusCode, String)> {
+    let list = fetch_list(pool, tenant, id).await?;
+
+    if list.created_by != Some(tenant.user_id) {
+        tenant.require(WorkspaceRole::Admin)?;
+    }
+
+    Ok(list)
+}
+
+// Lists are shared by the whole workspace, so every member's clients hear about changes.
+// Webhooks get them once, as an event about the member who made the change.
+async fn record_for_members(
+    tx: &mut MySqlConnection,
+    tenant: &Tenant,
+    kind: &str,
+    data: &impl Serialize,
+) -> Result<Vec<Event>, (StatusCode, String)> {
+    let members = sqlx::query_scalar::<_, i32>("SELECT user_id FROM workspace_members WHERE workspace_id = ?")
+        .bind(tenant.workspace_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch workspace members for {} event: {}", kind, e)))?;
+
+    let mut recorded = Vec::with_capacity(members.len());
+    for user_id in members {
+        recorded.push(events::record(&mut *tx, user_id, kind, data).await?);
+    }
+
+    Ok(recorded)
+}
+
+async fn fetch_list(pool: &MySqlPool, tenant: &Tenant, id: i32) -> Result<List, (StatusCode, String)> {
+    sqlx::query_as::<_, List>("SELECT * FROM lists WHERE id = ? AND workspace_id = ?")
+        .bind(id)
+        .bind(tenant.workspace_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch list from database: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "List not found".to_string()))
+}
@@ -0,0 +1,65 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use sqlx::MySqlPool;
+
+use crate::models::{
+    auth::ResponseMessage,
+    login_attempt::{LoginAttempt, LoginAttemptFilter},
+    login_lockout::LoginLockout,
+};
+
+pub async fn login_lockouts_index(
+    Extension(pool): Extension<MySqlPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let q = "SELECT * FROM login_lockouts ORDER BY last_failed_at DESC";
+
+    let lockouts = sqlx::query_as::<_, LoginLockout>(q)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch login lockouts from database: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(lockouts)))
+}
+
+// Manual unlock. Removing the row also resets the failure counter.
+pub async fn login_lockouts_delete(
+    Extension(pool): Extension<MySqlPool>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let result = sqlx::query("DELETE FROM login_lockouts WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete login lockout from database: {}", e)))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Login lockout not found".to_string()));
+    }
+
+    Ok((StatusCode::OK, Json(ResponseMessage { message: "Login lockout removed".to_string() })))
+}
+
+pub async fn login_attempts_index(
+    Extension(pool): Extension<MySqlPool>,
+    Query(filter): Query<LoginAttemptFilter>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let q = "SELECT * FROM login_attempts \
+             WHERE (? IS NULL OR username = ?) AND (? IS NULL OR ip_address = ?) \
+             ORDER BY id DESC LIMIT ?";
+
+    let attempts = sqlx::query_as::<_, LoginAttempt>(q)
+        .bind(&filter.username)
+        .bind(&filter.username)
+        .bind(&filter.ip_address)
+        .bind(&filter.ip_address)
+        .bind(filter.limit.unwrap_or(100).min(1000))
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch login attempts from database: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(attempts)))
+}
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Query},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    Json,
+};
+use chrono::{Duration, Utc};
+use sqlx::MySqlPool;
+use tower_cookies::Cookies;
+use validator::Validate;
+
+use crate::{
+    controllers::auth_controller::{issue_tokens, set_session_cookies},
+    models::{
+        auth::ResponseMessage,
+        magic_link::{MagicLinkCallback, MagicLinkToken, RequestMagicLink},
+        user::User,
+    },
+    utils::{
+        client_ip::ClientIp,
+        input_validation::handle_validation_errors,
+        lockout::record_attempt,
+        magic_link::{magic_link_redirect, magic_link_url, verify_magic_link, MAGIC_LINK_TTL_MINUTES},
+        mailer::Mailer,
+        notifications::LoginDevices,
+        oauth::{hash_token, random_token},
+        rate_limit::{RateLimitPolicy, RateLimitStore},
+    },
+};
+
+// POST /api/auth/magic-link
+// Emails a login link if an account has this address. The answer is the same either way,
+// so the endpoint cannot be used to find out who has an account.
+pub async fn magic_links_create(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(mailer): Extension<Arc<dyn Mailer>>,
+    Extension(rate_limit_store): Extension<Arc<dyn RateLimitStore>>,
+    Json(payload): Json<RequestMagicLink>,
+) -> Result<Response, (StatusCode, String)> {
+    payload.validate().map_err(|errors| {
+        let error_message = handle_validation_errors(errors);
+        (StatusCode::BAD_REQUEST, error_message)
+    })?;
+
+    // Limit per address, on top of the per-IP limit of the route, so nobody's inbox gets flooded.
+    let email = payload.email.trim().to_lowercase();
+    let policy = RateLimitPolicy::magic_link();
+    let decision = rate_limit_store.take(&format!("{}:email:{}", policy.name, email), &policy).await;
+    if !decision.allowed {
+        return Ok(decision.limited_response());
+    }
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = ?")
+        .bind(&email)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch user from database: {}", e)))?;
+
+    if let Some(user) = user {
+        let token = random_token("", 43);
+        let expires_at = Utc::now() + Duration::minutes(MAGIC_LINK_TTL_MINUTES);
+        let link = magic_link_url(&token, expires_at.timestamp())?;
+
+        sqlx::query("INSERT INTO magic_link_tokens (token_hash, user_id, expires_at) VALUES (?, ?, ?)")
+            .bind(hash_token(&token))
+            .bind(user.id)
+            .bind(expires_at)
+            .execute(&pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store login link in database: {}", e)))?;
+
+        let body = format!(
+            "Hi {},\n\nUse this link to log in. It works once and expires in {} minutes:\n\n{}\n\nIf you did not ask for it, you can ignore this email.",
+            user.username, MAGIC_LINK_TTL_MINUTES, link
+        );
+        mailer
+            .send(&user.email, "Your login link", &body)
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Failed to send login link: {}", e)))?;
+    }
+
+    let response = (
+        StatusCode::ACCEPTED,
+        Json(ResponseMessage {
+            message: "If an account exists for this address, a login link has been sent".to_string(),
+        }),
+    )
+        .into_response();
+
+    Ok(response)
+}
+
+// GET /api/auth/magic-link/callback
+// Consumes the link and logs the user in with the same cookies as `login`.
+pub async fn magic_links_callback(
+    Extension(pool): Extension<MySqlPool>,
+    ClientIp(ip_address): ClientIp,
+    cookies: Cookies,
+    login_devices: LoginDevices,
+    Query(link): Query<MagicLinkCallback>,
+) -> Result<Response, (StatusCode, String)> {
+    let invalid = || (StatusCode::UNAUTHORIZED, "Invalid or expired login link".to_string());
+    let now = Utc::now();
+
+    // 1. Cheap checks first: a forged or expired link never reaches the database
+    if !verify_magic_link(&link.token, link.expires, &link.signature)? || link.expires < now.timestamp() {
+        return Err(invalid());
+    }
+
+    let token = sqlx::query_as::<_, MagicLinkToken>("SELECT * FROM magic_link_tokens WHERE token_hash = ?")
+        .bind(hash_token(&link.token))
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch login link from database: {}", e)))?
+        .ok_or_else(invalid)?;
+
+    // 2. Mark it used. The condition makes a second, concurrent use fail.
+    let claimed = sqlx::query("UPDATE magic_link_tokens SET used_at = ? WHERE id = ? AND used_at IS NULL AND expires_at > ?")
+        .bind(now)
+        .bind(token.id)
+        .bind(now)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to use login link in database: {}", e)))?;
+
+    if claimed.rows_affected() == 0 {
+        return Err(invalid());
+    }
+
+    // 3. Log the user in
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(token.user_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch user from database: {}", e)))?;
+
+    record_attempt(&pool, &user.username, &ip_address, true, Some("magic_link")).await?;
+
+    let (access_token, refresh_token) = issue_tokens(&pool, &user).await?;
+    set_session_cookies(&cookies, access_token, refresh_token);
+    login_devices.notice_browser_login(&user, &ip_address).await;
+
+    Ok(Redirect::to(&magic_link_redirect()).into_response())
+}
@@ -0,0 +1,172 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::Utc;
+use sqlx::MySqlPool;
+
+use crate::{
+    models::{
+        auth::{Claims, ResponseMessage},
+        notification::{Notification, NotificationFilter, NotificationPage, UnreadCount},
+    },
+    utils::{
+        events::EventBus,
+        notifications::{publish_unread_count, unread_count},
+    },
+};
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+pub async fn notifications_index(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+    Query(filter): Query<NotificationFilter>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user_id = claims_user_id(&claims)?;
+    let limit = filter.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    // One extra row tells whether there is another page.
+    let q = "SELECT * FROM notifications WHERE user_id = ? AND (? IS NULL OR id < ?) AND (NOT ? OR read_at IS NULL) \
+             ORDER BY id DESC LIMIT ?";
+
+    let mut notifications = sqlx::query_as::<_, Notification>(q)
+        .bind(user_id)
+        .bind(filter.before)
+        .bind(filter.before)
+        .bind(filter.unread.unwrap_or(false))
+        .bind(limit + 1)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch notifications from database: {}", e)))?;
+
+    let next_before = if notifications.len() > limit as usize {
+        notifications.truncate(limit as usize);
+        notifications.last().map(|notification| notification.id)
+    } else {
+        None
+    };
+
+    let unread_count = count_unread(&pool, user_id).await?;
+
+    Ok((StatusCode::OK, Json(NotificationPage { notifications, unread_count, next_before })))
+}
+
+// Cheap enough to poll for a badge.
+pub async fn notifications_unread_count(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let unread_count = count_unread(&pool, claims_user_id(&claims)?).await?;
+
+    Ok((StatusCode::OK, Json(UnreadCount { unread_count })))
+}
+
+pub async fn notifications_read(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(bus): Extension<EventBus>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    set_read(&pool, &bus, &claims, id, true).await
+}
+
+pub async fn notifications_unread(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(bus): Extension<EventBus>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    set_read(&pool, &bus, &claims, id, false).await
+}
+
+pub async fn notifications_read_all(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(bus): Extension<EventBus>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user_id = claims_user_id(&claims)?;
+
+    sqlx::query("UPDATE notifications SET read_at = ? WHERE user_id = ? AND read_at IS NULL")
+        .bind(Utc::now())
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update notifications: {}", e)))?;
+
+    publish_unread_count(&pool, &bus, user_id).await;
+
+    Ok((StatusCode::OK, Json(UnreadCount { unread_count: 0 })))
+}
+
+pub async fn notifications_delete(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(bus): Extension<EventBus>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user_id = claims_user_id(&claims)?;
+
+    let result = sqlx::query("DELETE FROM notifications WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete notification from database: {}", e)))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Notification not found".to_string()));
+    }
+
+    publish_unread_count(&pool, &bus, user_id).await;
+
+    Ok((StatusCode::OK, Json(ResponseMessage { message: "Notification deleted".to_string() })))
+}
+
+async fn set_read(
+    pool: &MySqlPool,
+    bus: &EventBus,
+    claims: &Claims,
+    id: i64,
+    read: bool,
+) -> Result<(StatusCode, Json<Notification>), (StatusCode, String)> {
+    let user_id = claims_user_id(claims)?;
+
+    // Marking an already read notification read keeps the time it was first read.
+    sqlx::query("UPDATE notifications SET read_at = IF(?, COALESCE(read_at, ?), NULL) WHERE id = ? AND user_id = ?")
+        .bind(read)
+        .bind(Utc::now())
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update notification: {}", e)))?;
+
+    let notification = sqlx::query_as::<_, Notification>("SELECT * FROM notifications WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch notification from database: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Notification not found".to_string()))?;
+
+    publish_unread_count(pool, bus, user_id).await;
+
+    Ok((StatusCode::OK, Json(notification)))
+}
+
+async fn count_unread(pool: &MySqlPool, user_id: i32) -> Result<i64, (StatusCode, String)> {
+    unread_count(pool, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to count unread notifications: {}", e)))
+}
+
+fn claims_user_id(claims: &Claims) -> Result<i32, (StatusCode, String)> {
+    claims
+        .sub
+        .parse()
+        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token subject".to_string()))
+}
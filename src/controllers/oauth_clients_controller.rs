@@ -0,0 +1,183 @@
+use sqlx::MySqlPool;
+use validator::Validate;
+
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension, Json};
+
+use crate::{
+    models::oauth_client::{CreateOAuthClient, CreatedOAuthClient, FieldValue, OAuthClient, UpdateOAuthClient},
+    utils::{
+        input_validation::handle_validation_errors,
+        oauth::{hash_token, random_token, CLIENT_ID_PREFIX, CLIENT_SECRET_PREFIX}
+    },
+};
+
+pub async fn oauth_clients_index(
+    Extension(pool): Extension<MySqlPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let q = "SELECT * FROM oauth_clients";
+
+    let clients = sqlx::query_as::<_, OAuthClient>(q)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch oauth clients from database: {}", e),
+            )
+        })?;
+
+    Ok((StatusCode::OK, Json(clients)))
+}
+
+pub async fn oauth_clients_find(
+    Extension(pool): Extension<MySqlPool>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    Ok((StatusCode::OK, Json(fetch_oauth_client(&pool, id).await?)))
+}
+
+// Helper function for fetching oauth client.
+pub async fn fetch_oauth_client(pool: &MySqlPool, id: i32) -> Result<OAuthClient, (StatusCode, String)> {
+    sqlx::query_as::<_, OAuthClient>("SELECT * FROM oauth_clients WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch oauth client from database: {}", e),
+            )
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "OAuth client not found".to_string()))
+}
+
+// Registers a third-party application. The client secret is only part of the response.
+pub async fn oauth_clients_create(
+    Extension(pool): Extension<MySqlPool>,
+    Json(input): Json<CreateOAuthClient>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    input.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Validation failed: {}", error_string),
+        )
+    })?;
+
+    let client_id = random_token(CLIENT_ID_PREFIX, 24);
+    let client_secret = input
+        .confidential
+        .then(|| random_token(CLIENT_SECRET_PREFIX, 48));
+
+    let q = "INSERT INTO oauth_clients (client_id, client_secret_hash, client_name, contact_email, redirect_uris, scopes) VALUES (?, ?, ?, ?, ?, ?)";
+
+    let result = sqlx::query(q)
+        .bind(&client_id)
+        .bind(client_secret.as_deref().map(hash_token))
+        .bind(&input.client_name)
+        .bind(&input.contact_email)
+        .bind(input.redirect_uris.join(" "))
+        .bind(input.scopes.join(" "))
+        .execute(&pool)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to create oauth client in database: {}", e),
+            )
+        })?;
+
+    let client = fetch_oauth_client(&pool, result.last_insert_id() as i32).await?;
+
+    Ok((StatusCode::CREATED, Json(CreatedOAuthClient { client, client_secret })))
+}
+
+pub async fn oauth_clients_update(
+    Extension(pool): Extension<MySqlPool>,
+    Path(id): Path<i32>,
+    Json(updates): Json<UpdateOAuthClient>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    updates.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Validation failed: {}", error_string),
+        )
+    })?;
+
+    let mut query_string = "UPDATE oauth_clients SET ".to_string();
+    let mut params: Vec<String> = vec![];
+
+    oauth_clients_update_query_builder(&mut query_string, &mut params, &updates);
+    if params.is_empty() {
+        return Ok((StatusCode::OK, Json(fetch_oauth_client(&pool, id).await?)));
+    }
+    query_string.truncate(query_string.len() - 2);
+    query_string.push_str(" WHERE id = ?");
+
+    // Values are bound rather than spliced in: redirect URIs come straight from the client.
+    let mut query = sqlx::query(&query_string);
+    for param in &params {
+        query = query.bind(param);
+    }
+
+    query
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to update oauth client in database: {}", e),
+            )
+        })?;
+
+    let client = fetch_oauth_client(&pool, id).await?;
+
+    Ok((StatusCode::OK, Json(client)))
+}
+
+// Helper function for oauth_clients_update. Adds a placeholder per field and its value to params.
+pub fn oauth_clients_update_query_builder(
+    query: &mut String,
+    params: &mut Vec<String>,
+    updates: &UpdateOAuthClient,
+) {
+    for (field, value) in updates.clone().into_iter() {
+        let value = match value {
+            FieldValue::ClientName(client_name) => client_name,
+            FieldValue::ContactEmail(contact_email) => contact_email,
+            // Redirect URIs and scopes are validated before we get here.
+            FieldValue::RedirectUris(redirect_uris) => redirect_uris.map(|uris| uris.join(" ")),
+            FieldValue::Scopes(scopes) => scopes.map(|scopes| scopes.join(" ")),
+            FieldValue::IsActive(is_active) => is_active.map(|is_active| (is_active as u8).to_string()),
+        };
+
+        if let Some(value) = value {
+            query.push_str(&format!("{} = ?, ", field));
+            params.push(value);
+        }
+    }
+}
+
+// Deleting a client also deletes its authorization codes and tokens.
+pub async fn oauth_clients_delete(
+    Extension(pool): Extension<MySqlPool>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    sqlx::query("DELETE FROM oauth_clients WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to delete oauth client in database: {}", e),
+            )
+        })?;
+
+    Ok((
+        StatusCode::OK,
+        Json("OAuth client deleted successfully".to_string()),
+    ))
+}
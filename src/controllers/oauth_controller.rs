@@ -0,0 +1,306 @@
+use axum::{
+    extract::{Extension, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Form, Json,
+};
+use chrono::{Duration, Utc};
+use sqlx::MySqlPool;
+use url::Url;
+
+use crate::{
+    models::{
+        auth::Claims,
+        oauth::{
+            AuthorizationPrompt, AuthorizationRequest, ConsentRequest, ConsentResponse, IntrospectionResponse,
+            OAuthAccess, OAuthAuthorizationCode, OAuthTokenRequest, TokenLookupRequest,
+        },
+        oauth_client::OAuthClient,
+    },
+    utils::oauth::{
+        authenticate_client, fetch_client_by_client_id, find_token, hash_token, is_active, issue_oauth_tokens,
+        oauth_error, random_token, resolve_scopes, revoke_family, revoke_token, server_error, verify_pkce,
+        OAuthErrorResponse, AUTHORIZATION_CODE_PREFIX, AUTHORIZATION_CODE_TTL_MINUTES,
+    },
+};
+
+// GET /api/oauth/authorize
+// Checks an authorization request for the logged-in user and returns what the consent
+// screen needs to show. Nothing is granted until the user answers through oauth_authorize_consent.
+pub async fn oauth_authorize_show(
+    Extension(pool): Extension<MySqlPool>,
+    oauth_access: Option<Extension<OAuthAccess>>,
+    Query(request): Query<AuthorizationRequest>,
+) -> Result<impl IntoResponse, OAuthErrorResponse> {
+    first_party_only(oauth_access)?;
+    let (client, scopes) = check_authorization_request(&pool, &request).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(AuthorizationPrompt {
+            client_id: client.client_id,
+            client_name: client.client_name,
+            redirect_uri: request.redirect_uri,
+            scopes: scopes.split_whitespace().map(str::to_string).collect(),
+            state: request.state,
+        }),
+    ))
+}
+
+// POST /api/oauth/authorize
+// The user's answer on the consent screen. Approval creates a single-use authorization code;
+// either way the response says where to send the browser.
+pub async fn oauth_authorize_consent(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+    oauth_access: Option<Extension<OAuthAccess>>,
+    Json(ConsentRequest { request, approve }): Json<ConsentRequest>,
+) -> Result<impl IntoResponse, OAuthErrorResponse> {
+    first_party_only(oauth_access)?;
+    let (client, scopes) = check_authorization_request(&pool, &request).await?;
+
+    let mut redirect_to = Url::parse(&request.redirect_uri)
+        .map_err(|_| oauth_error(StatusCode::BAD_REQUEST, "invalid_request", "Invalid redirect_uri"))?;
+
+    if approve {
+        let user_id = claims
+            .sub
+            .parse::<i32>()
+            .map_err(|_| oauth_error(StatusCode::UNAUTHORIZED, "access_denied", "Invalid user"))?;
+        let code = random_token(AUTHORIZATION_CODE_PREFIX, 40);
+        let now = Utc::now();
+
+        // Opportunistic cleanup, bounded so a single request never does much work.
+        sqlx::query("DELETE FROM oauth_authorization_codes WHERE expires_at < ? LIMIT 100")
+            .bind(now)
+            .execute(&pool)
+            .await
+            .map_err(|e| server_error("Failed to clean up authorization codes", e))?;
+
+        let q = "INSERT INTO oauth_authorization_codes (code_hash, client_id, user_id, redirect_uri, scopes, code_challenge, expires_at) VALUES (?, ?, ?, ?, ?, ?, ?)";
+        sqlx::query(q)
+            .bind(hash_token(&code))
+            .bind(client.id)
+            .bind(user_id)
+            .bind(&request.redirect_uri)
+            .bind(&scopes)
+            .bind(&request.code_challenge)
+            .bind(now + Duration::minutes(AUTHORIZATION_CODE_TTL_MINUTES))
+            .execute(&pool)
+            .await
+            .map_err(|e| server_error("Failed to store authorization code in database", e))?;
+
+        redirect_to.query_pairs_mut().append_pair("code", &code);
+    } else {
+        redirect_to.query_pairs_mut().append_pair("error", "access_denied");
+    }
+
+    if let Some(state) = &request.state {
+        redirect_to.query_pairs_mut().append_pair("state", state);
+    }
+
+    Ok((StatusCode::OK, Json(ConsentResponse { redirect_to: redirect_to.to_string() })))
+}
+
+// Shared by both authorize endpoints. Errors are returned to the consent screen rather than
+// the client's redirect_uri, which is only trusted once it has been matched.
+async fn check_authorization_request(
+    pool: &MySqlPool,
+    request: &AuthorizationRequest,
+) -> Result<(OAuthClient, String), OAuthErrorResponse> {
+    if request.response_type != "code" {
+        return Err(oauth_error(StatusCode::BAD_REQUEST, "unsupported_response_type", "Only response_type=code is supported"));
+    }
+
+    let client = fetch_client_by_client_id(pool, &request.client_id)
+        .await?
+        .ok_or_else(|| oauth_error(StatusCode::BAD_REQUEST, "invalid_request", "Unknown client_id"))?;
+
+    if !client.allows_redirect_uri(&request.redirect_uri) {
+        return Err(oauth_error(StatusCode::BAD_REQUEST, "invalid_request", "redirect_uri is not registered for this client"));
+    }
+
+    // PKCE is required for every client, confidential ones included.
+    let challenge_ok = request
+        .code_challenge
+        .as_deref()
+        .is_some_and(|challenge| (43..=128).contains(&challenge.len()));
+    if !challenge_ok {
+        return Err(oauth_error(StatusCode::BAD_REQUEST, "invalid_request", "A code_challenge is required"));
+    }
+    if request.code_challenge_method.as_deref() != Some("S256") {
+        return Err(oauth_error(StatusCode::BAD_REQUEST, "invalid_request", "code_challenge_method must be S256"));
+    }
+
+    let scopes = resolve_scopes(request.scope.as_deref(), &client.scopes)?;
+
+    Ok((client, scopes))
+}
+
+// A third-party app holding an OAuth token must not approve grants for itself.
+fn first_party_only(oauth_access: Option<Extension<OAuthAccess>>) -> Result<(), OAuthErrorResponse> {
+    match oauth_access {
+        Some(_) => Err(oauth_error(StatusCode::FORBIDDEN, "access_denied", "OAuth tokens cannot be used here")),
+        None => Ok(()),
+    }
+}
+
+// POST /api/oauth/token
+// Supports the authorization_code (with PKCE) and refresh_token grants.
+pub async fn oauth_token(
+    Extension(pool): Extension<MySqlPool>,
+    headers: HeaderMap,
+    Form(request): Form<OAuthTokenRequest>,
+) -> Result<Response, OAuthErrorResponse> {
+    let client = authenticate_client(&pool, &headers, request.client_id.as_deref(), request.client_secret.as_deref()).await?;
+    let invalid_grant = |description: &str| oauth_error(StatusCode::BAD_REQUEST, "invalid_grant", description);
+    let missing = |field: &str| oauth_error(StatusCode::BAD_REQUEST, "invalid_request", format!("{} is required", field));
+
+    let tokens = match request.grant_type.as_str() {
+        "authorization_code" => {
+            let code = request.code.as_deref().ok_or_else(|| missing("code"))?;
+            let redirect_uri = request.redirect_uri.as_deref().ok_or_else(|| missing("redirect_uri"))?;
+            let code_verifier = request.code_verifier.as_deref().ok_or_else(|| missing("code_verifier"))?;
+
+            let authorization = sqlx::query_as::<_, OAuthAuthorizationCode>("SELECT * FROM oauth_authorization_codes WHERE code_hash = ?")
+                .bind(hash_token(code))
+                .fetch_optional(&pool)
+                .await
+                .map_err(|e| server_error("Failed to fetch authorization code from database", e))?
+                .ok_or_else(|| invalid_grant("Invalid authorization code"))?;
+
+            // Codes are single use. Deleting first means a concurrent second exchange finds nothing.
+            let deleted = sqlx::query("DELETE FROM oauth_authorization_codes WHERE id = ?")
+                .bind(authorization.id)
+                .execute(&pool)
+                .await
+                .map_err(|e| server_error("Failed to delete authorization code from database", e))?;
+
+            if deleted.rows_affected() == 0 {
+                return Err(invalid_grant("Invalid authorization code"));
+            }
+            if authorization.client_id != client.id {
+                return Err(invalid_grant("Authorization code was issued to another client"));
+            }
+            if authorization.redirect_uri != redirect_uri {
+                return Err(invalid_grant("redirect_uri does not match the authorization request"));
+            }
+            if authorization.expires_at < Utc::now() {
+                return Err(invalid_grant("Authorization code expired"));
+            }
+            if !verify_pkce(code_verifier, &authorization.code_challenge) {
+                return Err(invalid_grant("Invalid code_verifier"));
+            }
+
+            // Every authorization starts a new token family.
+            let family = random_token("", 32);
+            issue_oauth_tokens(&pool, client.id, authorization.user_id, &authorization.scopes, &family).await?
+        }
+        "refresh_token" => {
+            let refresh_token = request.refresh_token.as_deref().ok_or_else(|| missing("refresh_token"))?;
+
+            let token = find_token(&pool, refresh_token)
+                .await?
+                .filter(|token| token.token_type == "refresh" && token.client_id == client.id)
+                .ok_or_else(|| invalid_grant("Invalid refresh token"))?;
+
+            if token.expires_at < Utc::now() {
+                return Err(invalid_grant("Refresh token expired"));
+            }
+
+            // Refresh tokens rotate on every use. Seeing a used one again means it leaked,
+            // so the whole grant is revoked.
+            if token.revoked_at.is_some() || !revoke_token(&pool, token.id).await? {
+                revoke_family(&pool, &token.family).await?;
+                return Err(invalid_grant("Refresh token has already been used"));
+            }
+
+            // A refresh may narrow the scopes but never widen them.
+            let scopes = resolve_scopes(request.scope.as_deref(), &token.scopes)?;
+            issue_oauth_tokens(&pool, client.id, token.user_id, &scopes, &token.family).await?
+        }
+        _ => {
+            return Err(oauth_error(StatusCode::BAD_REQUEST, "unsupported_grant_type", "grant_type must be authorization_code or refresh_token"));
+        }
+    };
+
+    let response = (
+        StatusCode::OK,
+        [(header::CACHE_CONTROL, "no-store")],
+        Json(tokens),
+    )
+        .into_response();
+
+    Ok(response)
+}
+
+// POST /api/oauth/introspect (RFC 7662)
+// Only confidential clients may introspect, and only tokens issued to themselves.
+pub async fn oauth_introspect(
+    Extension(pool): Extension<MySqlPool>,
+    headers: HeaderMap,
+    Form(request): Form<TokenLookupRequest>,
+) -> Result<impl IntoResponse, OAuthErrorResponse> {
+    let client = authenticate_client(&pool, &headers, request.client_id.as_deref(), request.client_secret.as_deref()).await?;
+    if !client.is_confidential() {
+        return Err(oauth_error(StatusCode::UNAUTHORIZED, "invalid_client", "Only confidential clients may introspect tokens"));
+    }
+
+    let token = find_token(&pool, &request.token)
+        .await?
+        .filter(|token| token.client_id == client.id && is_active(token));
+
+    let Some(token) = token else {
+        return Ok((StatusCode::OK, Json(IntrospectionResponse::default())));
+    };
+
+    let username = sqlx::query_scalar::<_, String>("SELECT username FROM users WHERE id = ?")
+        .bind(token.user_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| server_error("Failed to fetch user from database", e))?;
+
+    let token_type = match token.token_type.as_str() {
+        "access" => "Bearer",
+        _ => "refresh_token",
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(IntrospectionResponse {
+            active: true,
+            scope: Some(token.scopes),
+            client_id: Some(client.client_id),
+            username,
+            token_type: Some(token_type.to_string()),
+            exp: Some(token.expires_at.timestamp()),
+            iat: Some(token.created_at.timestamp()),
+            sub: Some(token.user_id.to_string()),
+        }),
+    ))
+}
+
+// POST /api/oauth/revoke (RFC 7009)
+// Revoking a refresh token revokes every token of its grant. Unknown tokens and tokens of
+// other clients are ignored, and the answer is 200 either way.
+pub async fn oauth_revoke(
+    Extension(pool): Extension<MySqlPool>,
+    headers: HeaderMap,
+    Form(request): Form<TokenLookupRequest>,
+) -> Result<impl IntoResponse, OAuthErrorResponse> {
+    let client = authenticate_client(&pool, &headers, request.client_id.as_deref(), request.client_secret.as_deref()).await?;
+
+    let token = find_token(&pool, &request.token)
+        .await?
+        .filter(|token| token.client_id == client.id);
+
+    if let Some(token) = token {
+        if token.token_type == "refresh" {
+            revoke_family(&pool, &token.family).await?;
+        } else {
+            revoke_token(&pool, token.id).await?;
+        }
+    }
+
+    Ok(StatusCode::OK)
+}
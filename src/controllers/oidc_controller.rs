@@ -0,0 +1,185 @@
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+};
+use chrono::{Duration, Utc};
+use sqlx::MySqlPool;
+use tower_cookies::Cookies;
+
+use crate::{
+    controllers::auth_controller::{issue_tokens, set_session_cookies},
+    models::{
+        oidc::{IdTokenClaims, OidcCallback, OidcLoginState},
+        user::User,
+    },
+    utils::{
+        client_ip::ClientIp,
+        cookies::{constant_time_eq, oidc_state_cookie, oidc_state_removal_cookie, OIDC_STATE_COOKIE},
+        lockout::record_attempt,
+        notifications::LoginDevices,
+        oauth::{pkce_challenge, random_token},
+        oidc::Oidc,
+    },
+};
+
+// How long the user has to finish logging in at the provider.
+const LOGIN_STATE_TTL_MINUTES: i64 = 10;
+
+// GET /api/auth/oidc/:provider/login
+// Sends the browser to the provider with a fresh state, nonce and PKCE challenge.
+pub async fn oidc_login(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(oidc): Extension<Oidc>,
+    cookies: Cookies,
+    Path(provider_name): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let provider = oidc.provider(&provider_name)?;
+    let metadata = oidc.metadata(provider).await?;
+
+    let state = random_token("", 43);
+    let nonce = random_token("", 43);
+    let code_verifier = random_token("", 64);
+    let now = Utc::now();
+
+    // Opportunistic cleanup, bounded so a single request never does much work.
+    sqlx::query("DELETE FROM oidc_login_states WHERE expires_at < ? LIMIT 100")
+        .bind(now)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to clean up login states: {}", e)))?;
+
+    let q = "INSERT INTO oidc_login_states (state, provider, nonce, code_verifier, expires_at) VALUES (?, ?, ?, ?, ?)";
+    sqlx::query(q)
+        .bind(&state)
+        .bind(&provider.name)
+        .bind(&nonce)
+        .bind(&code_verifier)
+        .bind(now + Duration::minutes(LOGIN_STATE_TTL_MINUTES))
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store login state in database: {}", e)))?;
+
+    let url = oidc.authorization_url(provider, &metadata, &state, &nonce, &pkce_challenge(&code_verifier))?;
+    cookies.add(oidc_state_cookie(state));
+
+    Ok(Redirect::to(&url).into_response())
+}
+
+// GET /api/auth/oidc/:provider/callback
+// Where the provider sends the browser back. Validates the ID token, finds or links the
+// local user and finishes with the same session cookies as a password login.
+pub async fn oidc_callback(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(oidc): Extension<Oidc>,
+    ClientIp(ip_address): ClientIp,
+    cookies: Cookies,
+    login_devices: LoginDevices,
+    Path(provider_name): Path<String>,
+    Query(callback): Query<OidcCallback>,
+) -> Result<Response, (StatusCode, String)> {
+    // The state cookie is single use, whatever happens next.
+    let cookie_state = cookies.get(OIDC_STATE_COOKIE).map(|c| c.value().to_string());
+    cookies.add(oidc_state_removal_cookie());
+
+    if let Some(error) = callback.error {
+        let description = callback.error_description.unwrap_or_default();
+        return Err((StatusCode::UNAUTHORIZED, format!("Identity provider returned an error: {} {}", error, description)));
+    }
+
+    let code = callback.code.ok_or((StatusCode::BAD_REQUEST, "Missing code".to_string()))?;
+    let state = callback.state.ok_or((StatusCode::BAD_REQUEST, "Missing state".to_string()))?;
+
+    // 1. The login must have been started by this browser
+    let same_browser = cookie_state.is_some_and(|cookie_state| constant_time_eq(cookie_state.as_bytes(), state.as_bytes()));
+    if !same_browser {
+        return Err((StatusCode::UNAUTHORIZED, "Login state does not match".to_string()));
+    }
+
+    // 2. Claim the stored state. Deleting it first makes it single use.
+    let login_state = sqlx::query_as::<_, OidcLoginState>("SELECT * FROM oidc_login_states WHERE state = ?")
+        .bind(&state)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch login state from database: {}", e)))?
+        .ok_or((StatusCode::UNAUTHORIZED, "Unknown or used login state".to_string()))?;
+
+    let deleted = sqlx::query("DELETE FROM oidc_login_states WHERE id = ?")
+        .bind(login_state.id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete login state from database: {}", e)))?;
+
+    if deleted.rows_affected() == 0 || login_state.provider != provider_name {
+        return Err((StatusCode::UNAUTHORIZED, "Unknown or used login state".to_string()));
+    }
+    if login_state.expires_at < Utc::now() {
+        return Err((StatusCode::UNAUTHORIZED, "Login took too long, please try again".to_string()));
+    }
+
+    // 3. Trade the code for an ID token and check it
+    let provider = oidc.provider(&provider_name)?;
+    let metadata = oidc.metadata(provider).await?;
+    let id_token = oidc.exchange_code(provider, &metadata, &code, &login_state.code_verifier).await?;
+    let claims = oidc.validate_id_token(provider, &metadata, &id_token, &login_state.nonce).await?;
+
+    // 4. Find the local account and log it in
+    let user = find_or_link_user(&pool, &provider.name, &claims).await?;
+    record_attempt(&pool, &user.username, &ip_address, true, Some(&format!("oidc:{}", provider.name))).await?;
+
+    let (token, refresh_token) = issue_tokens(&pool, &user).await?;
+    set_session_cookies(&cookies, token, refresh_token);
+    login_devices.notice_browser_login(&user, &ip_address).await;
+
+    Ok(Redirect::to(&oidc.post_login_redirect).into_response())
+}
+
+// A known identity logs in its user. A new identity is linked to the user with the same email,
+// but only if the provider says it verified that email; there is no automatic sign-up.
+async fn find_or_link_user(pool: &MySqlPool, provider: &str, claims: &IdTokenClaims) -> Result<User, (StatusCode, String)> {
+    let q = "SELECT u.* FROM users u JOIN user_identities i ON i.user_id = u.id WHERE i.provider = ? AND i.subject = ?";
+    let linked = sqlx::query_as::<_, User>(q)
+        .bind(provider)
+        .bind(&claims.sub)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch linked user from database: {}", e)))?;
+
+    if let Some(user) = linked {
+        sqlx::query("UPDATE user_identities SET last_login_at = ?, email = ? WHERE provider = ? AND subject = ?")
+            .bind(Utc::now())
+            .bind(&claims.email)
+            .bind(provider)
+            .bind(&claims.sub)
+            .execute(pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update identity in database: {}", e)))?;
+
+        return Ok(user);
+    }
+
+    let email = claims
+        .email
+        .as_deref()
+        .filter(|_| claims.email_verified)
+        .ok_or((StatusCode::FORBIDDEN, "No account is linked to this identity and the provider did not share a verified email".to_string()))?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = ?")
+        .bind(email)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch user from database: {}", e)))?
+        .ok_or((StatusCode::FORBIDDEN, "No account exists for this email address".to_string()))?;
+
+    sqlx::query("INSERT INTO user_identities (user_id, provider, subject, email, last_login_at) VALUES (?, ?, ?, ?, ?)")
+        .bind(user.id)
+        .bind(provider)
+        .bind(&claims.sub)
+        .bind(email)
+        .bind(Utc::now())
+        .execute(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to link identity in database: {}", e)))?;
+
+    Ok(user)
+}
@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use argon2::{
+    password_hash::{
+        PasswordHasher,
+        SaltString
+    },
+    Argon2, PasswordHash, PasswordVerifier,
+};
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{Duration, Utc};
+use rand::Rng;
+use sqlx::MySqlPool;
+use validator::Validate;
+
+use crate::{
+    controllers::users_controller::fetch_user,
+    models::{
+        auth::ResponseMessage,
+        event::UpdatedAccount,
+        phone_verification::{ConfirmPhoneVerification, PhoneVerification}
+    },
+    utils::{
+        events::{self, EventBus},
+        input_validation::handle_validation_errors,
+        rate_limit::{RateLimitPolicy, RateLimitStore},
+        sms::SmsSender,
+        workspaces::Tenant,
+    },
+};
+
+// How long a code stays valid once sent.
+const CODE_TTL_MINUTES: i64 = 10;
+// Wrong guesses allowed before the code is thrown away.
+const MAX_ATTEMPTS: i32 = 5;
+
+// Users only verify their own number.
+pub async fn phone_verifications_create(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(sms): Extension<Arc<dyn SmsSender>>,
+    Extension(rate_limit_store): Extension<Arc<dyn RateLimitStore>>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i32>,
+) -> Result<Response, (StatusCode, String)> {
+    tenant.require_self(id)?;
+    let user = fetch_user(&id, &pool).await?;
+
+    let phone_number = user
+        .phone_number
+        .filter(|p| !p.is_empty())
+        .ok_or((StatusCode::BAD_REQUEST, "User has no phone number".to_string()))?;
+
+    if user.phone_number_verified {
+        return Err((StatusCode::CONFLICT, "Phone number already verified".to_string()));
+    }
+
+    // Limit per number, on top of the per-user limit of the route, so that no number can be
+    // flooded with texts, whichever accounts ask for them.
+    let policy = RateLimitPolicy::phone_verification();
+    let decision = rate_limit_store.take(&format!("{}:phone:{}", policy.name, phone_number), &policy).await;
+    if !decision.allowed {
+        return Ok(decision.limited_response());
+    }
+
+    let code = format!("{:06}", rand::thread_rng().gen_range(0..1_000_000));
+
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    let code_hash = Argon2::default()
+        .hash_password(code.as_bytes(), &salt)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Error hashing verification code: {}", e)))?;
+
+    let expires_at = Utc::now() + Duration::minutes(CODE_TTL_MINUTES);
+
+    // One pending code per user. Sending again replaces the previous code, but wrong guesses
+    // keep counting until it would have expired, so resending does not buy more guesses.
+    let q = "INSERT INTO phone_verifications (user_id, phone_number, code_hash, attempts, expires_at) VALUES (?, ?, ?, 0, ?) \
+             ON DUPLICATE KEY UPDATE phone_number = VALUES(phone_number), code_hash = VALUES(code_hash), \
+             attempts = IF(expires_at > ?, attempts, 0), expires_at = VALUES(expires_at)";
+
+    sqlx::query(q)
+        .bind(id)
+        .bind(&phone_number)
+        .bind(code_hash.to_string())
+        .bind(expires_at)
+        .bind(Utc::now())
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store verification code in database: {}", e)))?;
+
+    let message = format!("Your verification code is {}. It expires in {} minutes.", code, CODE_TTL_MINUTES);
+    sms.send(&phone_number, &message)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Failed to send verification code: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(ResponseMessage { message: "Verification code sent".to_string() })).into_response())
+}
+
+pub async fn phone_verifications_confirm(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(bus): Extension<EventBus>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i32>,
+    Json(input): Json<ConfirmPhoneVerification>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    tenant.require_self(id)?;
+
+    input.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string))
+    })?;
+
+    let verification = sqlx::query_as::<_, PhoneVerification>("SELECT * FROM phone_verifications WHERE user_id = ?")
+        .bind(id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch verification code from database: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "No pending verification code".to_string()))?;
+
+    if verification.expires_at < Utc::now() {
+        delete_phone_verification(&pool, verification.id).await?;
+        return Err((StatusCode::GONE, "Verification code expired".to_string()));
+    }
+
+    let parsed_hash = PasswordHash::new(&verification.code_hash)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to parse verification code hash: {}", e)))?;
+
+    if Argon2::default().verify_password(input.code.as_bytes(), &parsed_hash).is_err() {
+        let attempts = verification.attempts + 1;
+
+        if attempts >= MAX_ATTEMPTS {
+            delete_phone_verification(&pool, verification.id).await?;
+            return Err((StatusCode::TOO_MANY_REQUESTS, "Too many attempts, request a new code".to_string()));
+        }
+
+        sqlx::query("UPDATE phone_verifications SET attempts = ? WHERE id = ?")
+            .bind(attempts)
+            .bind(verification.id)
+            .execute(&pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update verification attempts: {}", e)))?;
+
+        return Err((StatusCode::BAD_REQUEST, "Invalid verification code".to_string()));
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))?;
+
+    // Only mark the number verified if it is still the one the code was sent to.
+    let result = sqlx::query("UPDATE users SET phone_number_verified = true WHERE id = ? AND phone_number = ?")
+        .bind(id)
+        .bind(&verification.phone_number)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to verify phone number: {}", e)))?;
+
+    let verified = result.rows_affected() > 0;
+    let mut event = None;
+    if verified {
+        let fields = vec!["phone_number_verified".to_string()];
+        event = Some(events::record(&mut *tx, id, "user.updated", &UpdatedAccount { id, fields }).await?);
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit phone verification: {}", e)))?;
+
+    bus.send(event);
+    delete_phone_verification(&pool, verification.id).await?;
+
+    if !verified {
+        return Err((StatusCode::CONFLICT, "Phone number changed since the code was sent".to_string()));
+    }
+
+    let user = fetch_user(&id, &pool).await?;
+
+    Ok((StatusCode::OK, Json(user)))
+}
+
+// Helper function for removing a used or dead verification code.
+async fn delete_phone_verification(pool: &MySqlPool, id: i32) -> Result<(), (StatusCode, String)> {
+    sqlx::query("DELETE FROM phone_verifications WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete verification code from database: {}", e)))?;
+
+    Ok(())
+}
@@ -23,14 +23,14 @@ use crate::{
     }, 
     utils::{
         input_validation::handle_validation_errors, 
-        tokens::generate_refresh_token
+        tokens::{generate_refresh_token, hash_api_key}
     }
 };
 
 pub async fn refresh_tokens_index(
     Extension(pool): Extension<MySqlPool>
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let q = &format!("SELECT * FROM refresh_tokens");
+    let q = "SELECT * FROM refresh_tokens";
 
     let refresh_tokens = sqlx::query_as::<_, RefreshToken>(q)
         .fetch_all(&pool)
@@ -88,18 +88,20 @@ pub async fn create_refresh_token(
     let expires_at_formatted = expires_at.with_timezone(&Utc);
     let token = generate_refresh_token(pool).await;
 
-    let q = &format!("INSERT INTO refresh_tokens (user_id, token, expires_at) VALUES (?, ?, ?)");
+    let q = "INSERT INTO refresh_tokens (user_id, token, expires_at) VALUES (?, ?, ?)";
     let refresh_token_id = sqlx::query(q)
         .bind(user_id)
-        .bind(token)
+        .bind(hash_api_key(&token))
         .bind(expires_at_formatted)
         .execute(pool)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create refresh token in database: {}", e)))?.last_insert_id();
     
-    let refresh_token = fetch_refresh_token(pool, refresh_token_id as i32).await?;
+    // Only the hash is stored, so this is the one time the token itself is seen.
+    let mut refresh_token = fetch_refresh_token(pool, refresh_token_id as i32).await?;
+    refresh_token.token = token;
 
-    return Ok(refresh_token);
+    Ok(refresh_token)
 }
 
 pub async fn refresh_tokens_update(
@@ -133,7 +135,7 @@ pub async fn refresh_tokens_query_builder(
             FieldValue::Token(token) => {
                 if let Some(token) = token {
                     query.push_str(&format!("{} = ?, ", field));
-                    params.push(token);
+                    params.push(hash_api_key(&token));
                 }
             },
             FieldValue::ExpiresAt(expires_at) => {
@@ -0,0 +1,74 @@
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension, Json};
+use sqlx::MySqlPool;
+use validator::Validate;
+
+use crate::{
+    models::reminder::{ReminderPreferences, UpdateReminderPreferences},
+    utils::{digests::reschedule_digest, input_validation::handle_validation_errors, workspaces::Tenant},
+};
+
+pub async fn reminder_preferences_find(
+    Extension(pool): Extension<MySqlPool>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    Ok((StatusCode::OK, Json(fetch_reminder_preferences(&pool, id).await?)))
+}
+
+// PATCH /api/users/:id/reminder_preferences
+// {"time_zone": "Asia/Dhaka", "quiet_hours_start": "22:00", "quiet_hours_end": "07:00", "channels": ["email", "in_app"],
+//  "due_reminder_minutes": 60}
+pub async fn reminder_preferences_update(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i32>,
+    Json(updates): Json<UpdateReminderPreferences>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    updates.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string))
+    })?;
+
+    tenant.require_self_or_admin(id)?;
+    fetch_reminder_preferences(&pool, id).await?;
+
+    // Quiet hours are only touched when given, and then both ends together.
+    let set_quiet_hours = updates.quiet_hours_start.is_some();
+    let q = "UPDATE users SET time_zone = COALESCE(?, time_zone), \
+             quiet_hours_start = IF(?, ?, quiet_hours_start), quiet_hours_end = IF(?, ?, quiet_hours_end), \
+             reminder_channels = COALESCE(?, reminder_channels), \
+             due_reminder_minutes = IF(?, ?, due_reminder_minutes) WHERE id = ?";
+
+    sqlx::query(q)
+        .bind(&updates.time_zone)
+        .bind(set_quiet_hours)
+        .bind(updates.quiet_hours_start.flatten())
+        .bind(set_quiet_hours)
+        .bind(updates.quiet_hours_end.flatten())
+        .bind(updates.channels.as_ref().map(|channels| channels.join(" ")))
+        .bind(updates.due_reminder_minutes.is_some())
+        .bind(updates.due_reminder_minutes.flatten())
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update reminder preferences: {}", e)))?;
+
+    // Digests go out at a local hour, so they move with the time zone.
+    if updates.time_zone.is_some() {
+        reschedule_digest(&pool, id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    }
+
+    Ok((StatusCode::OK, Json(fetch_reminder_preferences(&pool, id).await?)))
+}
+
+async fn fetch_reminder_preferences(pool: &MySqlPool, id: i32) -> Result<ReminderPreferences, (StatusCode, String)> {
+    let q = "SELECT time_zone, quiet_hours_start, quiet_hours_end, reminder_channels, due_reminder_minutes FROM users WHERE id = ?";
+
+    sqlx::query_as::<_, ReminderPreferences>(q)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch reminder preferences from database: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))
+}
@@ -0,0 +1,222 @@
+use argon2::{PasswordHash, PasswordVerifier};
+use axum::{
+    extract::Path,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::{DateTime, Duration, Local, Utc};
+use sqlx::MySqlPool;
+use validator::Validate;
+
+use crate::{
+    controllers::lists_controller::authorize_list,
+    models::{
+        share_link::{CreateShareLink, CreatedShareLink, PublicTodo, ShareLink, SharedContent},
+        todo_share::TodoRole,
+        workspace::WorkspaceRole,
+    },
+    utils::{
+        input_validation::handle_validation_errors,
+        password::hash_password,
+        sharing::authorize_todo,
+        tokens::{generate_share_token, hash_api_key, SHARE_TOKEN_PREFIX_LEN},
+        workspaces::Tenant,
+    },
+};
+
+// Header viewers of a password protected link send the password in.
+const SHARE_PASSWORD_HEADER: &str = "x-share-password";
+
+// POST /api/lists/:id/share_links
+// Lists are shared by whoever may change them.
+pub async fn list_share_links_create(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i32>,
+    Json(input): Json<CreateShareLink>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    authorize_list(&pool, &tenant, id).await?;
+    let share_link = create_share_link(&pool, &tenant, ("list_id", id), input).await?;
+
+    Ok((StatusCode::CREATED, Json(share_link)))
+}
+
+// POST /api/todos/:id/share_links
+// Only todo owners publish a todo.
+pub async fn todo_share_links_create(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i32>,
+    Json(input): Json<CreateShareLink>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    authorize_todo(&pool, id, &tenant, TodoRole::Owner).await?;
+    let share_link = create_share_link(&pool, &tenant, ("todo_id", id), input).await?;
+
+    Ok((StatusCode::CREATED, Json(share_link)))
+}
+
+// The caller's links in the current workspace; admins see everyone's.
+pub async fn share_links_index(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(tenant): Extension<Tenant>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let q = "SELECT * FROM share_links WHERE workspace_id = ? AND (created_by = ? OR ?) ORDER BY id DESC";
+
+    let share_links = sqlx::query_as::<_, ShareLink>(q)
+        .bind(tenant.workspace_id)
+        .bind(tenant.user_id)
+        .bind(tenant.role >= WorkspaceRole::Admin)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch share links from database: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(share_links)))
+}
+
+// Revokes the link for good; its token stops working at once. Revoked links are kept so
+// their view counts remain visible.
+pub async fn share_links_delete(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let share_link = fetch_share_link(&pool, &tenant, id).await?;
+
+    if share_link.created_by != Some(tenant.user_id) {
+        tenant.require(WorkspaceRole::Admin)?;
+    }
+
+    sqlx::query("UPDATE share_links SET revoked_at = CURRENT_TIMESTAMP WHERE id = ? AND revoked_at IS NULL")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to revoke share link: {}", e)))?;
+
+    let share_link = fetch_share_link(&pool, &tenant, id).await?;
+
+    Ok((StatusCode::OK, Json(share_link)))
+}
+
+// GET /api/public/:token
+// Unauthenticated. Unknown and revoked tokens are not found; only views that get the
+// content are counted.
+pub async fn public_share_link(
+    Extension(pool): Extension<MySqlPool>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let share_link = sqlx::query_as::<_, ShareLink>("SELECT * FROM share_links WHERE token_hash = ? AND revoked_at IS NULL")
+        .bind(hash_api_key(&token))
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch share link from database: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Share link not found".to_string()))?;
+
+    if share_link.expires_at.is_some_and(|expires_at| expires_at <= Local::now()) {
+        return Err((StatusCode::GONE, "This share link has expired".to_string()));
+    }
+
+    if let Some(password_hash) = &share_link.password_hash {
+        let password = headers
+            .get(SHARE_PASSWORD_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .ok_or((StatusCode::UNAUTHORIZED, "This share link needs a password".to_string()))?;
+
+        let parsed_hash = PasswordHash::new(password_hash)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to parse password hash: {}", e)))?;
+
+        if argon2::Argon2::default().verify_password(password.as_bytes(), &parsed_hash).is_err() {
+            return Err((StatusCode::UNAUTHORIZED, "Wrong password".to_string()));
+        }
+    }
+
+    let content = match (share_link.list_id, share_link.todo_id) {
+        (Some(list_id), _) => {
+            let name = sqlx::query_scalar::<_, String>("SELECT name FROM lists WHERE id = ?")
+                .bind(list_id)
+                .fetch_one(&pool)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch list from database: {}", e)))?;
+
+            let q = "SELECT description, done, due_at, completed_at FROM todos WHERE list_id = ? ORDER BY id";
+            let todos = sqlx::query_as::<_, PublicTodo>(q)
+                .bind(list_id)
+                .fetch_all(&pool)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch todos from database: {}", e)))?;
+
+            SharedContent::List { name, todos }
+        }
+        (None, Some(todo_id)) => {
+            let q = "SELECT description, done, due_at, completed_at FROM todos WHERE id = ?";
+            let todo = sqlx::query_as::<_, PublicTodo>(q)
+                .bind(todo_id)
+                .fetch_one(&pool)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch todo from database: {}", e)))?;
+
+            SharedContent::Todo(todo)
+        }
+        (None, None) => return Err((StatusCode::NOT_FOUND, "Share link not found".to_string())),
+    };
+
+    sqlx::query("UPDATE share_links SET view_count = view_count + 1, last_viewed_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(share_link.id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to count share link view: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(content)))
+}
+
+// Stores a link to the list or todo given as (column, id). The full token is only part of
+// the return value; the database keeps its hash and prefix.
+async fn create_share_link(
+    pool: &MySqlPool,
+    tenant: &Tenant,
+    (column, id): (&str, i32),
+    input: CreateShareLink,
+) -> Result<CreatedShareLink, (StatusCode, String)> {
+    input.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string))
+    })?;
+
+    let token = generate_share_token();
+    let password_hash = input.password.as_deref().map(hash_password).transpose()?;
+    let expires_at: Option<DateTime<Utc>> = input.expires_in_days.map(|days| Utc::now() + Duration::days(days));
+
+    let q = format!(
+        "INSERT INTO share_links (workspace_id, {}, token_prefix, token_hash, password_hash, expires_at, created_by) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        column
+    );
+
+    let share_link_id = sqlx::query(&q)
+        .bind(tenant.workspace_id)
+        .bind(id)
+        .bind(token.chars().take(SHARE_TOKEN_PREFIX_LEN).collect::<String>())
+        .bind(hash_api_key(&token))
+        .bind(password_hash)
+        .bind(expires_at)
+        .bind(tenant.user_id)
+        .execute(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create share link: {}", e)))?
+        .last_insert_id() as i64;
+
+    let share_link = fetch_share_link(pool, tenant, share_link_id).await?;
+    let path = format!("/api/public/{}", token);
+
+    Ok(CreatedShareLink { share_link, token, path })
+}
+
+async fn fetch_share_link(pool: &MySqlPool, tenant: &Tenant, id: i64) -> Result<ShareLink, (StatusCode, String)> {
+    sqlx::query_as::<_, ShareLink>("SELECT * FROM share_links WHERE id = ? AND workspace_id = ?")
+        .bind(id)
+        .bind(tenant.workspace_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch share link from database: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Share link not found".to_string()))
+}
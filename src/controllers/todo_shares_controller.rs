@@ -0,0 +1,276 @@
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension, Json};
+use serde_json::json;
+use sqlx::MySqlPool;
+use validator::Validate;
+
+use crate::{
+    models::{
+        auth::ResponseMessage,
+        todo_share::{CreateTodoShare, SharedTodo, TodoInvitation, TodoRole, TodoShare, TodoShareDetails, UpdateTodoShare},
+        user::User,
+    },
+    utils::{
+        events::EventBus,
+        input_validation::handle_validation_errors,
+        notifications::notify,
+        sharing::authorize_todo,
+        workspaces::Tenant,
+    },
+};
+
+// Owners see every share of the todo, including pending and declined invitations; everyone
+// else only who else has access.
+pub async fn todo_shares_index(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let (_, role) = authorize_todo(&pool, id, &tenant, TodoRole::Viewer).await?;
+
+    let q = "SELECT s.*, u.username, i.username AS invited_by_username FROM todo_shares s \
+             JOIN users u ON u.id = s.user_id JOIN users i ON i.id = s.invited_by \
+             WHERE s.todo_id = ? AND (? OR s.status = 'accepted') ORDER BY s.id";
+
+    let shares = sqlx::query_as::<_, TodoShareDetails>(q)
+        .bind(id)
+        .bind(role == TodoRole::Owner)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch shares from database: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(shares)))
+}
+
+// Invites a user, by username or email, to the todo. Inviting someone who declined before
+// invites them again.
+pub async fn todo_shares_create(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(bus): Extension<EventBus>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i32>,
+    Json(input): Json<CreateTodoShare>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    input.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string))
+    })?;
+
+    let user_id = tenant.user_id;
+    let (todo, _) = authorize_todo(&pool, id, &tenant, TodoRole::Owner).await?;
+
+    let invitee = input.invitee.trim();
+    let invitee = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ? OR email = ? LIMIT 1")
+        .bind(invitee)
+        .bind(invitee)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch user from database: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "No user with that username or email".to_string()))?;
+
+    if invitee.id == todo.user_id || invitee.id == user_id {
+        return Err((StatusCode::BAD_REQUEST, "That user already has access to this todo".to_string()));
+    }
+
+    let existing = fetch_share(&pool, id, invitee.id).await?;
+    match existing.as_ref().map(|share| share.status.as_str()) {
+        Some("accepted") => return Err((StatusCode::CONFLICT, "Already shared with that user; change their role instead".to_string())),
+        Some("pending") => return Err((StatusCode::CONFLICT, "That user has already been invited".to_string())),
+        _ => {}
+    }
+
+    let q = "INSERT INTO todo_shares (todo_id, user_id, role, invited_by) VALUES (?, ?, ?, ?) \
+             ON DUPLICATE KEY UPDATE role = VALUES(role), invited_by = VALUES(invited_by), status = 'pending', responded_at = NULL";
+
+    sqlx::query(q)
+        .bind(id)
+        .bind(invitee.id)
+        .bind(input.role.as_str())
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to share todo: {}", e)))?;
+
+    let share = fetch_share(&pool, id, invitee.id)
+        .await?
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch share from database".to_string()))?;
+
+    let title = format!("You were invited to a todo as {}", input.role);
+    let data = json!({ "todo_id": id, "share_id": share.id, "role": input.role, "invited_by": user_id });
+    if let Err(e) = notify(&pool, &bus, invitee.id, "todo.invitation", &title, &todo.description, Some(data)).await {
+        println!("Failed to notify user {} of todo share invitation: {}", invitee.id, e);
+    }
+
+    Ok((StatusCode::CREATED, Json(share)))
+}
+
+pub async fn todo_shares_update(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(tenant): Extension<Tenant>,
+    Path((id, share_id)): Path<(i32, i64)>,
+    Json(input): Json<UpdateTodoShare>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    authorize_todo(&pool, id, &tenant, TodoRole::Owner).await?;
+
+    let result = sqlx::query("UPDATE todo_shares SET role = ? WHERE id = ? AND todo_id = ?")
+        .bind(input.role.as_str())
+        .bind(share_id)
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update share: {}", e)))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Share not found".to_string()));
+    }
+
+    let share = sqlx::query_as::<_, TodoShare>("SELECT * FROM todo_shares WHERE id = ?")
+        .bind(share_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch share from database: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(share)))
+}
+
+// Owners can take anyone's access away, and anyone can give up their own.
+pub async fn todo_shares_delete(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(tenant): Extension<Tenant>,
+    Path((id, share_id)): Path<(i32, i64)>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user_id = tenant.user_id;
+
+    let share = sqlx::query_as::<_, TodoShare>("SELECT * FROM todo_shares WHERE id = ? AND todo_id = ?")
+        .bind(share_id)
+        .bind(id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch share from database: {}", e)))?;
+
+    if share.as_ref().map(|share| share.user_id) != Some(user_id) {
+        authorize_todo(&pool, id, &tenant, TodoRole::Owner).await?;
+    }
+    if share.is_none() {
+        return Err((StatusCode::NOT_FOUND, "Share not found".to_string()));
+    }
+
+    sqlx::query("DELETE FROM todo_shares WHERE id = ?")
+        .bind(share_id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete share: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(ResponseMessage { message: "Share removed".to_string() })))
+}
+
+// GET /api/todos/shared
+// Todos shared with the caller, from any workspace.
+pub async fn todos_shared(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(tenant): Extension<Tenant>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let q = "SELECT t.*, s.role, u.username AS owner_username FROM todo_shares s \
+             JOIN todos t ON t.id = s.todo_id JOIN users u ON u.id = t.user_id \
+             WHERE s.user_id = ? AND s.status = 'accepted' ORDER BY t.id";
+
+    let todos = sqlx::query_as::<_, SharedTodo>(q)
+        .bind(tenant.user_id)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch shared todos from database: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(todos)))
+}
+
+// GET /api/todos/invitations
+pub async fn todo_invitations_index(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(tenant): Extension<Tenant>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let q = "SELECT s.id, s.todo_id, t.description, t.due_at, s.role, i.username AS invited_by_username, s.created_at \
+             FROM todo_shares s JOIN todos t ON t.id = s.todo_id JOIN users i ON i.id = s.invited_by \
+             WHERE s.user_id = ? AND s.status = 'pending' ORDER BY s.id DESC";
+
+    let invitations = sqlx::query_as::<_, TodoInvitation>(q)
+        .bind(tenant.user_id)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch invitations from database: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(invitations)))
+}
+
+pub async fn todo_invitations_accept(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(bus): Extension<EventBus>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let share = respond(&pool, &tenant, id, "accepted").await?;
+
+    // Lets whoever sent the invitation know.
+    let q = "SELECT u.username, t.description FROM todo_shares s \
+             JOIN users u ON u.id = s.user_id JOIN todos t ON t.id = s.todo_id WHERE s.id = ?";
+    let accepted = sqlx::query_as::<_, (String, String)>(q)
+        .bind(share.id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|e| format!("Failed to fetch invitation details: {}", e));
+    let data = json!({ "todo_id": share.todo_id, "share_id": share.id });
+
+    let notified = match accepted {
+        Ok((username, description)) => {
+            let title = format!("{} accepted your invitation", username);
+            notify(&pool, &bus, share.invited_by, "todo.invitation_accepted", &title, &description, Some(data)).await.map(|_| ())
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = notified {
+        println!("Failed to notify user {} of accepted todo share: {}", share.invited_by, e);
+    }
+
+    Ok((StatusCode::OK, Json(share)))
+}
+
+pub async fn todo_invitations_decline(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let share = respond(&pool, &tenant, id, "declined").await?;
+
+    Ok((StatusCode::OK, Json(share)))
+}
+
+// Accepts or declines one of the caller's pending invitations.
+async fn respond(pool: &MySqlPool, tenant: &Tenant, id: i64, status: &str) -> Result<TodoShare, (StatusCode, String)> {
+    let q = "UPDATE todo_shares SET status = ?, responded_at = CURRENT_TIMESTAMP \
+             WHERE id = ? AND user_id = ? AND status = 'pending'";
+
+    let result = sqlx::query(q)
+        .bind(status)
+        .bind(id)
+        .bind(tenant.user_id)
+        .execute(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update invitation: {}", e)))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Invitation not found".to_string()));
+    }
+
+    sqlx::query_as::<_, TodoShare>("SELECT * FROM todo_shares WHERE id = ?")
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch invitation from database: {}", e)))
+}
+
+async fn fetch_share(pool: &MySqlPool, todo_id: i32, user_id: i32) -> Result<Option<TodoShare>, (StatusCode, String)> {
+    sqlx::query_as::<_, TodoShare>("SELECT * FROM todo_shares WHERE todo_id = ? AND user_id = ?")
+        .bind(todo_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch share from database: {}", e)))
+}
@@ -6,26 +6,44 @@ use axum::{
 	Extension
 };
 
+use chrono::Utc;
+use serde_json::json;
 use validator::Validate;
 
 use crate::{
-	models::todo::{
-		Todo, 
-		CreateTodo,
-		UpdateTodo,
-		FieldValue
+	models::{
+		event::DeletedResource,
+		todo::{
+			Todo, 
+			CreateTodo,
+			UpdateTodo,
+			FieldValue
+		},
+		todo_share::TodoRole
 	},
-	utils::input_validation::handle_validation_errors
+	utils::{
+		events::{self, EventBus},
+		input_validation::handle_validation_errors,
+		notifications::notify,
+		sharing::authorize_todo,
+		webhooks,
+		workspaces::{require_list, require_member, Tenant}
+	}
 };
 
 use sqlx::MySqlPool;
 
+// The caller's own todos in the current workspace, and those on its lists. Todos shared
+// with them are listed by todos_shared.
 pub async fn todos_index(
-	Extension(pool): Extension<MySqlPool>
+	Extension(pool): Extension<MySqlPool>,
+	Extension(tenant): Extension<Tenant>
 ) -> Result<impl IntoResponse, (StatusCode, String)>  {
-	let q = "SELECT * FROM todos";
+	let q = "SELECT * FROM todos WHERE workspace_id = ? AND (user_id = ? OR list_id IS NOT NULL)";
 
 	let todos = sqlx::query_as::<_, Todo>(q)
+		.bind(tenant.workspace_id)
+		.bind(tenant.user_id)
 		.fetch_all(&pool)
 		.await;
 
@@ -38,19 +56,18 @@ pub async fn todos_index(
 
 pub async fn todos_find(
 	Extension(pool): Extension<MySqlPool>, 
+	Extension(tenant): Extension<Tenant>,
 	Path(id): Path<i32>
 ) -> Result<impl IntoResponse, (StatusCode, String)>  {
-	let todo = fetch_todo(&id, &pool).await;
-
-	if let Err(e) = todo {
-		return Err(e)
-	}
+	let (todo, _) = authorize_todo(&pool, id, &tenant, TodoRole::Viewer).await?;
 
-	Ok((StatusCode::OK, Json(todo.unwrap())))
+	Ok((StatusCode::OK, Json(todo)))
 }
 
 pub async fn todos_create(
 	Extension(pool): Extension<MySqlPool>,
+	Extension(bus): Extension<EventBus>,
+	Extension(tenant): Extension<Tenant>,
 	Json(input): Json<CreateTodo>
 ) -> Result<impl IntoResponse, (StatusCode, String)>  {
 	// Validation
@@ -61,13 +78,34 @@ pub async fn todos_create(
 		return Err((StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string)))
 	}
 
-	let q = "INSERT INTO todos (description, done, user_id) VALUES (?, ?, ?)";
+	// Todos go into the current workspace, for one of its members.
+	require_member(&pool, &tenant, input.user_id).await?;
+
+	if let Some(list_id) = input.list_id {
+		require_list(&pool, tenant.workspace_id, list_id).await?;
+	}
+
+	// The todo and its webhook event are committed together or not at all.
+	let tx = pool.begin().await;
+
+	if let Err(e) = tx {
+		return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))
+	}
+
+	let mut tx = tx.unwrap();
+	let q = "INSERT INTO todos (description, done, user_id, workspace_id, list_id, due_at, remind_at, completed_at) \
+		VALUES (?, ?, ?, ?, ?, ?, ?, IF(?, CURRENT_TIMESTAMP, NULL))";
 
 	let todo_id = sqlx::query(q)
 		.bind(input.description)
 		.bind(input.done)
 		.bind(input.user_id)
-		.execute(&pool)
+		.bind(tenant.workspace_id)
+		.bind(input.list_id)
+		.bind(input.due_at)
+		.bind(input.remind_at)
+		.bind(input.done)
+		.execute(&mut *tx)
 		.await;
 
 	if let Err(e) = todo_id {
@@ -75,20 +113,46 @@ pub async fn todos_create(
 	}
 
 	let id = todo_id.unwrap().last_insert_id() as i32;
-	let todo = fetch_todo(&id, &pool).await;
+	let todo = sqlx::query_as::<_, Todo>("SELECT * FROM todos WHERE id = ?")
+		.bind(id)
+		.fetch_one(&mut *tx)
+		.await;
 
 	if let Err(e) = todo {
-		return Err(e)
+		return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch todo from database: {}", e)))
 	}
 
-	Ok((StatusCode::OK, Json(todo.unwrap())))
+	let todo = todo.unwrap();
+	webhooks::enqueue(&mut *tx, todo.user_id, "todo.created", &todo).await?;
+	let event = events::record(&mut *tx, todo.user_id, "todo.created", &todo).await?;
+
+	if let Err(e) = tx.commit().await {
+		return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit todo: {}", e)))
+	}
+
+	bus.send([event]);
+	notify_owner(&pool, &bus, &tenant, todo.user_id, todo.id, "todo.created", &todo.description).await;
+
+	Ok((StatusCode::OK, Json(todo)))
 }
 
 pub async fn todos_update(
     Path(id): Path<i32>,
     Extension(pool): Extension<MySqlPool>,
+    Extension(bus): Extension<EventBus>,
+    Extension(tenant): Extension<Tenant>,
     Json(updates): Json<UpdateTodo>
 ) -> Result<impl IntoResponse, (StatusCode, String)>  {
+    // Moving a todo to another list, or off one, changes who can see it: only owners may.
+    let role = if updates.list_id.is_some() { TodoRole::Owner } else { TodoRole::Editor };
+    let (todo, _) = authorize_todo(&pool, id, &tenant, role).await?;
+
+    // The list has to be in the todo's own workspace, which for a sharee is not the one they
+    // are acting in.
+    if let Some(Some(list_id)) = updates.list_id {
+        require_list(&pool, todo.workspace_id, list_id).await?;
+    }
+
     let mut query_string = "UPDATE todos SET ".to_string();
     let mut params = Vec::new();
 
@@ -96,14 +160,28 @@ pub async fn todos_update(
     build_update_query_string(&mut query_string, &mut params, &updates);
     
     // Remove trailing comma and space if any fields were updated
-    if !params.is_empty() {
+    if query_string.ends_with(", ") {
         query_string.truncate(query_string.len() - 2);
     }
-    query_string.push_str(&format!(" WHERE id = {}", id));
+    query_string.push_str(" WHERE id = ?");
+
+	let tx = pool.begin().await;
+
+	if let Err(e) = tx {
+		return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))
+	}
 
-    // Execute the query
-    let update_query = sqlx::query(&query_string)
-        .execute(&pool)
+	let mut tx = tx.unwrap();
+
+    // Bind the values in the order their placeholders were added, then the id.
+    let mut update_query = sqlx::query(&query_string);
+    for param in params {
+        update_query = update_query.bind(param);
+    }
+
+    let update_query = update_query
+        .bind(id)
+        .execute(&mut *tx)
         .await;
 
 	if let Err(e) = update_query {
@@ -111,30 +189,65 @@ pub async fn todos_update(
 	}
 
 	// Fetch the updated todo
-	let todo = fetch_todo(&id, &pool).await;	
+	let todo = sqlx::query_as::<_, Todo>("SELECT * FROM todos WHERE id = ?")
+		.bind(id)
+		.fetch_one(&mut *tx)
+		.await;
+
 	if let Err(e) = todo {
-		return Err(e)
+		return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch todo from database: {}", e)))
 	}
 
-	Ok((StatusCode::OK, Json(todo.unwrap())))
+	let todo = todo.unwrap();
+	webhooks::enqueue(&mut *tx, todo.user_id, "todo.updated", &todo).await?;
+	let event = events::record(&mut *tx, todo.user_id, "todo.updated", &todo).await?;
+
+	if let Err(e) = tx.commit().await {
+		return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit todo: {}", e)))
+	}
+
+	bus.send([event]);
+	notify_owner(&pool, &bus, &tenant, todo.user_id, todo.id, "todo.updated", &todo.description).await;
+
+	Ok((StatusCode::OK, Json(todo)))
 	// Ok((StatusCode::OK, "test".to_string()))
 }
 
-// todos_update helper function
+// todos_update helper function. Every value gets a placeholder in the query string and is
+// added to params, to be bound in the same order.
 pub fn build_update_query_string(query: &mut String, params: &mut Vec<String>, updates: &UpdateTodo) {
 	for (field, item) in updates.clone().into_iter() {
 		match item {
 			FieldValue::Description(value) => {
 				if let Some(val) = value {
-					query.push_str(&format!("{} = '{}', ", field, val));
+					query.push_str(&format!("{} = ?, ", field));
 					params.push(val);
 				}
 			},
 			FieldValue::Done(value) => {
 				if let Some(val) = value {
-					query.push_str(&format!("{} = '{}', ", field, val as i32));
-					params.push(val.to_string());
+					query.push_str(&format!("{} = ?, ", field));
+					params.push((val as i32).to_string());
+					// Marking a done todo done again keeps when it was first completed.
+					query.push_str(&format!("completed_at = {}, ", if val { "COALESCE(completed_at, CURRENT_TIMESTAMP)" } else { "NULL" }));
 				}
+			},
+			// The connection's time zone is UTC.
+			FieldValue::Timestamp(value) => match value {
+				Some(Some(val)) => {
+					query.push_str(&format!("{} = ?, ", field));
+					params.push(val.with_timezone(&Utc).format("%Y-%m-%d %H:%M:%S").to_string());
+				},
+				Some(None) => query.push_str(&format!("{} = NULL, ", field)),
+				None => {}
+			},
+			FieldValue::Id(value) => match value {
+				Some(Some(val)) => {
+					query.push_str(&format!("{} = ?, ", field));
+					params.push(val.to_string());
+				},
+				Some(None) => query.push_str(&format!("{} = NULL, ", field)),
+				None => {}
 			}
 		}
 	}
@@ -156,19 +269,61 @@ pub async fn fetch_todo(id: &i32, pool: &MySqlPool) -> Result<Todo, (StatusCode,
 
 pub async fn todos_delete(
 	Extension(pool): Extension<MySqlPool>,
+	Extension(bus): Extension<EventBus>,
+	Extension(tenant): Extension<Tenant>,
 	Path(id): Path<i32>
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
+	authorize_todo(&pool, id, &tenant, TodoRole::Owner).await?;
+
+	let tx = pool.begin().await;
+
+	if let Err(e) = tx {
+		return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))
+	}
+
+	let mut tx = tx.unwrap();
+
+	// The owner is needed to route the event, and is gone after the delete.
+	let owner = sqlx::query_as::<_, (i32, String)>("SELECT user_id, description FROM todos WHERE id = ? FOR UPDATE")
+		.bind(id)
+		.fetch_optional(&mut *tx)
+		.await;
+
+	if let Err(e) = owner {
+		return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch todo from database: {}", e)))
+	}
+
 	let q = "DELETE FROM todos WHERE id = ?";
 
 	let delete = sqlx::query(q)
 		.bind(id)
-		.execute(&pool)
+		.execute(&mut *tx)
 		.await;
 
 	if let Err(e) = delete {
 		return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete todo from database: {}", e)))
 	}
 
+	let deleted_from = match (owner.unwrap(), delete.unwrap().rows_affected() > 0) {
+		(Some(owner), true) => Some(owner),
+		_ => None,
+	};
+
+	let mut event = None;
+	if let Some((user_id, _)) = &deleted_from {
+		webhooks::enqueue(&mut *tx, *user_id, "todo.deleted", &DeletedResource { id }).await?;
+		event = Some(events::record(&mut *tx, *user_id, "todo.deleted", &DeletedResource { id }).await?);
+	}
+
+	if let Err(e) = tx.commit().await {
+		return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit todo deletion: {}", e)))
+	}
+
+	bus.send(event);
+	if let Some((user_id, description)) = deleted_from {
+		notify_owner(&pool, &bus, &tenant, user_id, id, "todo.deleted", &description).await;
+	}
+
 	Ok((StatusCode::OK, "Todo deleted".to_string()))
 }
 
@@ -186,3 +341,30 @@ pub async fn fetch_user_todo(user_id: &i32, todo_id: &i32, pool: &MySqlPool) ->
 	Ok(todo.unwrap())
 }
 
+// Tells the owner when somebody else, such as an editor it is shared with, adds, changes or
+// removes one of their todos. Their own changes are not worth a notification.
+async fn notify_owner(
+	pool: &MySqlPool,
+	bus: &EventBus,
+	tenant: &Tenant,
+	owner_id: i32,
+	todo_id: i32,
+	kind: &str,
+	description: &str,
+) {
+	let actor = tenant.user_id;
+	if actor == owner_id {
+		return;
+	}
+
+	let title = match kind {
+		"todo.created" => "New todo on your list",
+		"todo.deleted" => "A todo of yours was removed",
+		_ => "A todo of yours was changed",
+	};
+
+	let data = json!({ "todo_id": todo_id, "actor_id": actor });
+	if let Err(e) = notify(pool, bus, owner_id, kind, title, description, Some(data)).await {
+		println!("Failed to notify user {} of {} on todo {}: {}", owner_id, kind, todo_id, e);
+	}
+}
@@ -1,11 +1,3 @@
-use argon2::{
-    password_hash::{
-        PasswordHasher, 
-        SaltString
-    },
-    Argon2,
-};
-
 use axum::{
 	extract::Path, 
     http::StatusCode, 
@@ -17,24 +9,39 @@ use axum::{
 use validator::Validate;
 
 use crate::{
-    models::user::{
-        CreateUser, 
-        CreateUserFromInput, 
-        FieldValue, 
-        UpdateUser, 
-        User
+    models::{
+        auth::Claims,
+        event::{CreatedAccount, DeletedResource, UpdatedAccount},
+        user::{
+            CreateUser, 
+            CreateUserFromInput, 
+            FieldValue, 
+            UpdateUser, 
+            User
+        }
     }, 
-    utils::input_validation::handle_validation_errors
+    utils::{
+        events::{self, EventBus},
+        input_validation::handle_validation_errors,
+        password::{hash_password, PasswordPolicy},
+        webhooks,
+        workspaces::Tenant
+    }
 };
 
 use sqlx::MySqlPool;
 
+// Members of the caller's workspace. API keys are not tied to a workspace and see everyone.
 pub async fn users_index(
-    Extension(pool): Extension<MySqlPool>
+    Extension(pool): Extension<MySqlPool>,
+    tenant: Option<Extension<Tenant>>
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-	let q = "SELECT * FROM users";
+	let q = "SELECT * FROM users WHERE ? IS NULL OR id IN (SELECT user_id FROM workspace_members WHERE workspace_id = ?)";
+	let workspace_id = tenant.map(|Extension(tenant)| tenant.workspace_id);
 
 	let users = sqlx::query_as::<_, User>(q)
+		.bind(workspace_id)
+		.bind(workspace_id)
 		.fetch_all(&pool)
 		.await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch users from database: {}", e)))?;
@@ -51,6 +58,7 @@ pub async fn users_find(
 
 pub async fn users_create(
     Extension(pool): Extension<MySqlPool>, 
+    Extension(password_policy): Extension<PasswordPolicy>,
     Json(input): Json<CreateUserFromInput>
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     input.validate().map_err(|e| {
@@ -58,34 +66,52 @@ pub async fn users_create(
         (StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string))
     })?;
 
+    password_policy.validate(&input.password, &[&input.username, &input.email]).map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string))
+    })?;
+
     let q = "INSERT INTO users (username, password_hash, email, phone_number, phone_number_verified) VALUES (?, ?, ?, ?, ?)";
 
-    let salt = SaltString::generate(&mut rand::thread_rng());
-    let argon2 = Argon2::default(); 
-    let password_hash = argon2.hash_password(input.password.as_bytes(), &salt)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Error hashing password: {}", e)))?;
+    let password_hash = hash_password(&input.password)?;
 
     let new_user = CreateUser {
         username: input.username,
-        password: password_hash.to_string(),
+        password: password_hash,
         email: input.email,
         phone_number: input.phone_number,
         phone_number_verified: false,
     };
 
+    // The user and its webhook event are committed together or not at all.
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))?;
+
 	let user_id = sqlx::query(q)
         .bind(new_user.username)
         .bind(new_user.password)
         .bind(new_user.email)
         .bind(new_user.phone_number)
         .bind(new_user.phone_number_verified)
-		.execute(&pool)
+		.execute(&mut *tx)
 		.await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Error creating user: {}", e)))?;
 
     let user_id = user_id.last_insert_id() as i32;
 
-    let user = fetch_user(&user_id, &pool).await?;
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch user from database: {}", e)))?;
+
+    webhooks::enqueue(&mut *tx, user.id, "user.created", &CreatedAccount::from(&user)).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit user: {}", e)))?;
 
     Ok((StatusCode::CREATED, Json(user)))
 }
@@ -93,13 +119,39 @@ pub async fn users_create(
 pub async fn users_update(
     Path(id): Path<i32>,
     Extension(pool): Extension<MySqlPool>,
+    Extension(password_policy): Extension<PasswordPolicy>,
+    Extension(claims): Extension<Claims>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(bus): Extension<EventBus>,
     Json(updates): Json<UpdateUser>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
+    if updates.password.is_some() || updates.email.is_some() || updates.phone_number.is_some() {
+        tenant.require_self(id)?;
+    } else {
+        tenant.require_self_or_admin(id)?;
+    }
+
+    // Either one is enough to take the account over.
+    if (updates.password.is_some() || updates.email.is_some()) && claims.is_impersonated() {
+        return Err((StatusCode::FORBIDDEN, "Passwords and emails cannot be changed while impersonating a user".to_string()));
+    }
+
+    if let Some(password) = &updates.password {
+        let user = fetch_user(&id, &pool).await?;
+        let username = updates.username.as_deref().unwrap_or(&user.username);
+        let email = updates.email.as_deref().unwrap_or(&user.email);
+
+        password_policy.validate(password, &[username, email]).map_err(|e| {
+            let error_string = handle_validation_errors(e);
+            (StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string))
+        })?;
+    }
+
     let mut query_string = "UPDATE users SET ".to_string();
     let mut params = Vec::new();
 
     // Use a helper function for query string building
-    users_update_query_builder(&mut query_string, &mut params, &updates);
+    users_update_query_builder(&mut query_string, &mut params, &updates)?;
     
     // Remove trailing comma and space if any fields were updated
     if !params.is_empty() {
@@ -107,14 +159,46 @@ pub async fn users_update(
     }
     query_string.push_str(&format!(" WHERE id = {}", id));
 
-    // Execute the query
-    sqlx::query(&query_string)
-        .execute(&pool)
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))?;
+
+    // Execute the query with the values bound in the order the builder added them
+    let mut query = sqlx::query(&query_string);
+    for param in &params {
+        query = query.bind(param);
+    }
+
+    query
+        .execute(&mut *tx)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update todos: {} {}", e, query_string)))?;
 
 	// Fetch the updated todo
-	let user = fetch_user(&id, &pool).await?;	
+	let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+		.bind(id)
+		.fetch_one(&mut *tx)
+		.await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch user from database: {}", e)))?;
+
+	let fields = [
+		("username", updates.username.is_some()),
+		("password", updates.password.is_some()),
+		("email", updates.email.is_some()),
+		("phone_number", updates.phone_number.is_some()),
+	];
+	let fields: Vec<String> = fields.iter().filter(|(_, changed)| *changed).map(|(field, _)| field.to_string()).collect();
+	let updated = UpdatedAccount { id: user.id, fields };
+
+	webhooks::enqueue(&mut *tx, user.id, "user.updated", &updated).await?;
+	let event = events::record(&mut *tx, user.id, "user.updated", &updated).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit user: {}", e)))?;
+
+	bus.send([event]);
 
 	Ok((StatusCode::OK, Json(user)))
 }
@@ -136,61 +220,76 @@ pub fn users_update_query_builder(
     query: &mut String, 
     params: &mut Vec<String>, 
     updates: &UpdateUser
-) {
+) -> Result<(), (StatusCode, String)> {
     // Go through all fields provided in the JSON request body. <UpdateUser>.
-    // If there is a value for it, add that value to the query string that sqlx will execute.
+    // If there is a value for it, add a placeholder to the query string that sqlx will execute
+    // and the value to params, to be bound in the same order.
     for (field, item) in updates.clone().into_iter() {
         match item {
             FieldValue::Username(val) => {
                 if let Some(username) = val {
-                    query.push_str(&format!("{} = '{}', ", field, username));
-                    params.push(field.to_string());
+                    query.push_str(&format!("{} = ?, ", field));
+                    params.push(username);
                 }
             },
             FieldValue::Password(val) => {
                 if let Some(password) = val {
-                    // Hash the password before storing
-                    let salt = SaltString::generate(&mut rand::thread_rng());
-                    let password_hash = Argon2::default()
-                        .hash_password(password.as_bytes(), &salt).unwrap();
-                    query.push_str(&format!("password_hash = '{}', ", password_hash.to_string()));
-                    query.push_str(&format!("{} = '{}', ", field, password));
-                    params.push(field.to_string());
+                    // Hash the password before storing. Only the hash has a column.
+                    let password_hash = hash_password(&password)?;
+                    query.push_str("password_hash = ?, ");
+                    params.push(password_hash);
                 }
             },
             FieldValue::Email(val) => {
                 if let Some(value) = val {
-                    query.push_str(&format!("{} = '{}', ", field, value));
-                    params.push(field.to_string());
+                    query.push_str(&format!("{} = ?, ", field));
+                    params.push(value);
                 }
             },
             FieldValue::PhoneNumber(val) => {
                 if let Some(value) = val {
-                    query.push_str(&format!("{} = '{}', ", field, value));
-                    params.push(field.to_string());
-                }
-            },
-            FieldValue::PhoneNumberVerified(val) => {
-                if let Some(value) = val {
-                    query.push_str(&format!("{} = '{}', ", field, value));
-                    params.push(field.to_string());
+                    // A new number has to be verified again. MySQL assigns left to right,
+                    // so this must come before phone_number itself is overwritten.
+                    query.push_str("phone_number_verified = IF(phone_number <=> ?, phone_number_verified, false), ");
+                    params.push(value.clone());
+                    query.push_str(&format!("{} = ?, ", field));
+                    params.push(value);
                 }
             }
         }
     }
+
+    Ok(())
 }
 
 pub async fn users_delete(
 	Extension(pool): Extension<MySqlPool>,
+	Extension(tenant): Extension<Tenant>,
 	Path(id): Path<i32>
 ) -> Result<impl IntoResponse, (StatusCode, String)>  {
+	tenant.require_self(id)?;
+
 	let q = "DELETE FROM users WHERE id = ?";
 
-	sqlx::query(q)
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))?;
+
+	let deleted = sqlx::query(q)
 		.bind(id)
-		.execute(&pool)
+		.execute(&mut *tx)
 		.await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Error deleting user from database: {}", e)))?;
 
+    // The user's own webhooks are deleted with them, so only API key webhooks get this one.
+    if deleted.rows_affected() > 0 {
+        webhooks::enqueue(&mut *tx, id, "user.deleted", &DeletedResource { id }).await?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit user deletion: {}", e)))?;
+
 	Ok((StatusCode::OK, "User deleted successfully".to_string()))
 }
@@ -0,0 +1,423 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Duration, Utc};
+use sqlx::{MySqlConnection, MySqlPool};
+use tower_cookies::Cookies;
+
+use crate::{
+    controllers::auth_controller::{issue_tokens, set_session_cookies},
+    models::{
+        auth::{Claims, ResponseMessage},
+        event::DeletedResource,
+        user::User,
+        webauthn::{
+            AuthenticatorSelection, CreationOptions, CredentialDescriptor, CredentialParameters, LoginCredential,
+            LoginOptionsRequest, RegisterCredential, RelyingParty, RequestOptions, WebAuthnChallenge,
+            WebAuthnCredential, WebAuthnUser,
+        },
+    },
+    utils::{
+        client_ip::ClientIp,
+        events::{self, EventBus},
+        lockout::record_attempt,
+        notifications::LoginDevices,
+        webauthn::{
+            check_client_data, cose_algorithm, decode, encode, generate_challenge, parse_attestation_object,
+            parse_authenticator_data, sign_count_advanced, signed_message, verify_signature, WebAuthnConfig, CEREMONY_TIMEOUT_MS,
+            CHALLENGE_TTL_MINUTES, SUPPORTED_ALGORITHMS,
+        },
+    },
+};
+
+// POST /api/webauthn/register/options
+// Starts registering a new passkey for the logged-in user.
+pub async fn webauthn_register_options(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(config): Extension<WebAuthnConfig>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = fetch_claims_user(&pool, &claims).await?;
+    let challenge = store_challenge(&pool, "registration", Some(user.id)).await?;
+
+    // Stops the authenticator from registering a second credential for the same account.
+    let exclude_credentials = fetch_credentials(&pool, user.id)
+        .await?
+        .into_iter()
+        .map(descriptor)
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(CreationOptions {
+            challenge,
+            rp: RelyingParty { id: config.rp_id.clone(), name: config.rp_name.clone() },
+            user: WebAuthnUser {
+                id: user_handle(user.id),
+                name: user.username.clone(),
+                display_name: user.username,
+            },
+            pub_key_cred_params: SUPPORTED_ALGORITHMS
+                .iter()
+                .map(|alg| CredentialParameters { kind: "public-key".to_string(), alg: *alg })
+                .collect(),
+            timeout: CEREMONY_TIMEOUT_MS,
+            attestation: "none".to_string(),
+            exclude_credentials,
+            authenticator_selection: AuthenticatorSelection {
+                resident_key: "preferred".to_string(),
+                user_verification: "preferred".to_string(),
+            },
+        }),
+    ))
+}
+
+// POST /api/webauthn/register
+// Verifies the authenticator's response and stores the new credential.
+pub async fn webauthn_register(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(bus): Extension<EventBus>,
+    Extension(config): Extension<WebAuthnConfig>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<RegisterCredential>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = fetch_claims_user(&pool, &claims).await?;
+    let credential = payload.credential;
+
+    if credential.kind != "public-key" {
+        return Err((StatusCode::BAD_REQUEST, "Unsupported credential type".to_string()));
+    }
+
+    // 1. The response has to answer a challenge we gave this user
+    let client_data_json = decode(&credential.response.client_data_json, "clientDataJSON")?;
+    let challenge = check_client_data(&config, &client_data_json, "webauthn.create")?;
+    let stored = take_challenge(&pool, &challenge, "registration").await?;
+    if stored.user_id != Some(user.id) {
+        return Err((StatusCode::BAD_REQUEST, "Unknown or expired challenge".to_string()));
+    }
+
+    // 2. Pull the new credential out of the attestation object
+    let attestation_object = decode(&credential.response.attestation_object, "attestationObject")?;
+    let auth_data = parse_attestation_object(&attestation_object)?;
+    let auth_data = parse_authenticator_data(&config, &auth_data)?;
+
+    let (Some(credential_id), Some(public_key)) = (auth_data.credential_id, auth_data.public_key) else {
+        return Err((StatusCode::BAD_REQUEST, "No attested credential data".to_string()));
+    };
+    if encode(&credential_id) != credential.raw_id.trim_end_matches('=') {
+        return Err((StatusCode::BAD_REQUEST, "Credential id does not match".to_string()));
+    }
+    let algorithm = cose_algorithm(&public_key)?;
+
+    // 3. Store it
+    let name = payload
+        .name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "Passkey".to_string());
+    let transports = Some(credential.response.transports.join(" ")).filter(|t| !t.is_empty());
+
+    // The credential and its event are committed together or not at all.
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))?;
+
+    let q = "INSERT INTO webauthn_credentials (user_id, credential_id, public_key, algorithm, sign_count, name, transports) VALUES (?, ?, ?, ?, ?, ?, ?)";
+    let result = sqlx::query(q)
+        .bind(user.id)
+        .bind(encode(&credential_id))
+        .bind(encode(&public_key))
+        .bind(algorithm)
+        .bind(auth_data.sign_count as i64)
+        .bind(name)
+        .bind(transports)
+        .execute(&mut *tx)
+        .await;
+
+    let result = match result {
+        Ok(result) => result,
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err((StatusCode::CONFLICT, "Credential is already registered".to_string()));
+        }
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store credential in database: {}", e))),
+    };
+
+    let credential = fetch_credential(&mut tx, result.last_insert_id() as i32).await?;
+    let event = events::record(&mut *tx, user.id, "passkey.created", &credential).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit credential: {}", e)))?;
+
+    bus.send([event]);
+
+    Ok((StatusCode::CREATED, Json(credential)))
+}
+
+// POST /api/webauthn/login/options
+// Starts a passkey login. With a username the user's credentials are listed; without one
+// the authenticator offers any discoverable credential it has for this site.
+pub async fn webauthn_login_options(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(config): Extension<WebAuthnConfig>,
+    payload: Option<Json<LoginOptionsRequest>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+
+    let user = match payload.username.as_deref().filter(|u| !u.is_empty()) {
+        Some(username) => sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
+            .bind(username)
+            .fetch_optional(&pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch user from database: {}", e)))?,
+        None => None,
+    };
+
+    // An unknown username gets an empty list, the same as a user without passkeys.
+    let allow_credentials = match &user {
+        Some(user) => fetch_credentials(&pool, user.id).await?.into_iter().map(descriptor).collect(),
+        None => vec![],
+    };
+    let challenge = store_challenge(&pool, "authentication", user.map(|user| user.id)).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(RequestOptions {
+            challenge,
+            rp_id: config.rp_id,
+            timeout: CEREMONY_TIMEOUT_MS,
+            user_verification: "preferred".to_string(),
+            allow_credentials,
+        }),
+    ))
+}
+
+// POST /api/webauthn/login
+// Verifies the assertion and logs the user in with the same cookies as `login`.
+pub async fn webauthn_login(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(config): Extension<WebAuthnConfig>,
+    ClientIp(ip_address): ClientIp,
+    cookies: Cookies,
+    login_devices: LoginDevices,
+    Json(payload): Json<LoginCredential>,
+) -> Result<Response, (StatusCode, String)> {
+    let credential = payload.credential;
+    let invalid = || (StatusCode::UNAUTHORIZED, "Invalid credential".to_string());
+
+    // 1. The response has to answer one of our challenges
+    let client_data_json = decode(&credential.response.client_data_json, "clientDataJSON")?;
+    let challenge = check_client_data(&config, &client_data_json, "webauthn.get")?;
+    let stored_challenge = take_challenge(&pool, &challenge, "authentication").await?;
+
+    // 2. Find the credential and its user
+    let stored = sqlx::query_as::<_, WebAuthnCredential>("SELECT * FROM webauthn_credentials WHERE credential_id = ?")
+        .bind(credential.raw_id.trim_end_matches('='))
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch credential from database: {}", e)))?
+        .ok_or_else(invalid)?;
+
+    if stored_challenge.user_id.is_some_and(|user_id| user_id != stored.user_id) {
+        return Err(invalid());
+    }
+    if let Some(handle) = &credential.response.user_handle {
+        if handle.trim_end_matches('=') != user_handle(stored.user_id) {
+            return Err(invalid());
+        }
+    }
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(stored.user_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch user from database: {}", e)))?;
+
+    // 3. Check the signature
+    let authenticator_data = decode(&credential.response.authenticator_data, "authenticatorData")?;
+    let signature = decode(&credential.response.signature, "signature")?;
+    let auth_data = parse_authenticator_data(&config, &authenticator_data)?;
+    let public_key = decode(&stored.public_key, "public key")?;
+
+    if !verify_signature(&public_key, &signed_message(&authenticator_data, &client_data_json), &signature)? {
+        record_attempt(&pool, &user.username, &ip_address, false, Some("webauthn_invalid_signature")).await?;
+        return Err(invalid());
+    }
+
+    // 4. The signature counter must move forward
+    let sign_count = auth_data.sign_count as i64;
+    if !sign_count_advanced(stored.sign_count, auth_data.sign_count) {
+        record_attempt(&pool, &user.username, &ip_address, false, Some("webauthn_sign_count")).await?;
+        return Err((StatusCode::UNAUTHORIZED, "Credential signature counter went backwards".to_string()));
+    }
+
+    // Compare-and-set so two concurrent logins cannot both pass the counter check.
+    let updated = sqlx::query("UPDATE webauthn_credentials SET sign_count = ?, last_used_at = ? WHERE id = ? AND sign_count = ?")
+        .bind(sign_count)
+        .bind(Utc::now())
+        .bind(stored.id)
+        .bind(stored.sign_count)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update credential in database: {}", e)))?;
+
+    if updated.rows_affected() == 0 {
+        return Err(invalid());
+    }
+
+    // 5. Log the user in
+    record_attempt(&pool, &user.username, &ip_address, true, Some("webauthn")).await?;
+
+    let (access_token, refresh_token) = issue_tokens(&pool, &user).await?;
+    set_session_cookies(&cookies, access_token, refresh_token);
+    login_devices.notice_browser_login(&user, &ip_address).await;
+
+    let response = (
+        StatusCode::OK,
+        Json(ResponseMessage {
+            message: "Login Successful".to_string(),
+        }),
+    )
+        .into_response();
+
+    Ok(response)
+}
+
+// GET /api/webauthn/credentials
+pub async fn webauthn_credentials_index(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = fetch_claims_user(&pool, &claims).await?;
+
+    Ok((StatusCode::OK, Json(fetch_credentials(&pool, user.id).await?)))
+}
+
+// DELETE /api/webauthn/credentials/:id
+pub async fn webauthn_credentials_delete(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(bus): Extension<EventBus>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = fetch_claims_user(&pool, &claims).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))?;
+
+    let result = sqlx::query("DELETE FROM webauthn_credentials WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete credential in database: {}", e)))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Credential not found".to_string()));
+    }
+
+    let event = events::record(&mut *tx, user.id, "passkey.deleted", &DeletedResource { id }).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit credential deletion: {}", e)))?;
+
+    bus.send([event]);
+
+    Ok((StatusCode::OK, Json("Credential deleted successfully".to_string())))
+}
+
+// Opaque user handle stored on the authenticator. Deliberately not the username.
+fn user_handle(user_id: i32) -> String {
+    encode(user_id.to_string().as_bytes())
+}
+
+fn descriptor(credential: WebAuthnCredential) -> CredentialDescriptor {
+    CredentialDescriptor {
+        kind: "public-key".to_string(),
+        id: credential.credential_id,
+        transports: credential
+            .transports
+            .map(|t| t.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default(),
+    }
+}
+
+async fn fetch_claims_user(pool: &MySqlPool, claims: &Claims) -> Result<User, (StatusCode, String)> {
+    sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(&claims.sub)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch user from database: {}", e)))?
+        .ok_or((StatusCode::UNAUTHORIZED, "User not found".to_string()))
+}
+
+async fn fetch_credential(conn: &mut MySqlConnection, id: i32) -> Result<WebAuthnCredential, (StatusCode, String)> {
+    sqlx::query_as::<_, WebAuthnCredential>("SELECT * FROM webauthn_credentials WHERE id = ?")
+        .bind(id)
+        .fetch_one(conn)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch credential from database: {}", e)))
+}
+
+async fn fetch_credentials(pool: &MySqlPool, user_id: i32) -> Result<Vec<WebAuthnCredential>, (StatusCode, String)> {
+    sqlx::query_as::<_, WebAuthnCredential>("SELECT * FROM webauthn_credentials WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch credentials from database: {}", e)))
+}
+
+async fn store_challenge(pool: &MySqlPool, ceremony: &str, user_id: Option<i32>) -> Result<String, (StatusCode, String)> {
+    let challenge = generate_challenge();
+    let now = Utc::now();
+
+    // Opportunistic cleanup, bounded so a single request never does much work.
+    sqlx::query("DELETE FROM webauthn_challenges WHERE expires_at < ? LIMIT 100")
+        .bind(now)
+        .execute(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to clean up challenges: {}", e)))?;
+
+    sqlx::query("INSERT INTO webauthn_challenges (challenge, ceremony, user_id, expires_at) VALUES (?, ?, ?, ?)")
+        .bind(&challenge)
+        .bind(ceremony)
+        .bind(user_id)
+        .bind(now + Duration::minutes(CHALLENGE_TTL_MINUTES))
+        .execute(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store challenge in database: {}", e)))?;
+
+    Ok(challenge)
+}
+
+// Challenges are single use: whoever deletes the row owns it.
+async fn take_challenge(pool: &MySqlPool, challenge: &str, ceremony: &str) -> Result<WebAuthnChallenge, (StatusCode, String)> {
+    let unknown = || (StatusCode::BAD_REQUEST, "Unknown or expired challenge".to_string());
+
+    let stored = sqlx::query_as::<_, WebAuthnChallenge>("SELECT * FROM webauthn_challenges WHERE challenge = ? AND ceremony = ?")
+        .bind(challenge)
+        .bind(ceremony)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch challenge from database: {}", e)))?
+        .ok_or_else(unknown)?;
+
+    let deleted = sqlx::query("DELETE FROM webauthn_challenges WHERE id = ?")
+        .bind(stored.id)
+        .execute(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete challenge from database: {}", e)))?;
+
+    if deleted.rows_affected() == 0 || stored.expires_at < Utc::now() {
+        return Err(unknown());
+    }
+
+    Ok(stored)
+}
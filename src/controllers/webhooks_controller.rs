@@ -0,0 +1,241 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::Utc;
+use sqlx::MySqlPool;
+use validator::Validate;
+
+use crate::{
+    models::{
+        auth::ResponseMessage,
+        webhook::{
+            CreateWebhook, CreatedWebhook, UpdateWebhook, Webhook, WebhookDelivery, WebhookDeliveryAttempt,
+            WebhookDeliveryDetails, WebhookDeliveryFilter
+        },
+    },
+    utils::{
+        input_validation::handle_validation_errors,
+        webhooks::{check_destination, generate_webhook_secret, WebhookOwner},
+    },
+};
+
+// Webhooks a user or an API key may have.
+const WEBHOOKS_PER_OWNER: i64 = 10;
+const DELIVERY_STATUSES: [&str; 3] = ["pending", "delivered", "dead"];
+
+pub async fn webhooks_index(
+    Extension(pool): Extension<MySqlPool>,
+    owner: WebhookOwner,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let q = format!("SELECT * FROM webhooks WHERE {} = ? ORDER BY id", owner.column());
+
+    let webhooks = sqlx::query_as::<_, Webhook>(&q)
+        .bind(owner.id())
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch webhooks from database: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(webhooks)))
+}
+
+pub async fn webhooks_find(
+    Extension(pool): Extension<MySqlPool>,
+    owner: WebhookOwner,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    Ok((StatusCode::OK, Json(fetch_webhook(&pool, &owner, id).await?)))
+}
+
+// The secret is only returned here. Receivers use it to check X-Webhook-Signature.
+pub async fn webhooks_create(
+    Extension(pool): Extension<MySqlPool>,
+    owner: WebhookOwner,
+    Json(input): Json<CreateWebhook>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    input.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string))
+    })?;
+    check_destination(&input.url).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let q = format!("SELECT COUNT(*) FROM webhooks WHERE {} = ?", owner.column());
+    let count = sqlx::query_scalar::<_, i64>(&q)
+        .bind(owner.id())
+        .fetch_one(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to count webhooks: {}", e)))?;
+
+    if count >= WEBHOOKS_PER_OWNER {
+        return Err((StatusCode::CONFLICT, format!("At most {} webhooks are allowed", WEBHOOKS_PER_OWNER)));
+    }
+
+    let secret = generate_webhook_secret();
+    let q = format!("INSERT INTO webhooks ({}, url, event_types, secret) VALUES (?, ?, ?, ?)", owner.column());
+
+    let result = sqlx::query(&q)
+        .bind(owner.id())
+        .bind(&input.url)
+        .bind(input.event_types.join(" "))
+        .bind(&secret)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to insert webhook into database: {}", e)))?;
+
+    let webhook = fetch_webhook(&pool, &owner, result.last_insert_id() as i32).await?;
+
+    Ok((StatusCode::CREATED, Json(CreatedWebhook { webhook, secret })))
+}
+
+pub async fn webhooks_update(
+    Extension(pool): Extension<MySqlPool>,
+    owner: WebhookOwner,
+    Path(id): Path<i32>,
+    Json(updates): Json<UpdateWebhook>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    updates.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string))
+    })?;
+    if let Some(url) = &updates.url {
+        check_destination(url).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+
+    fetch_webhook(&pool, &owner, id).await?;
+
+    let q = format!(
+        "UPDATE webhooks SET url = COALESCE(?, url), event_types = COALESCE(?, event_types), is_active = COALESCE(?, is_active) \
+         WHERE id = ? AND {} = ?",
+        owner.column()
+    );
+
+    sqlx::query(&q)
+        .bind(&updates.url)
+        .bind(updates.event_types.as_ref().map(|types| types.join(" ")))
+        .bind(updates.is_active)
+        .bind(id)
+        .bind(owner.id())
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update webhook: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(fetch_webhook(&pool, &owner, id).await?)))
+}
+
+pub async fn webhooks_delete(
+    Extension(pool): Extension<MySqlPool>,
+    owner: WebhookOwner,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let q = format!("DELETE FROM webhooks WHERE id = ? AND {} = ?", owner.column());
+
+    let result = sqlx::query(&q)
+        .bind(id)
+        .bind(owner.id())
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete webhook from database: {}", e)))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Webhook not found".to_string()));
+    }
+
+    Ok((StatusCode::OK, Json(ResponseMessage { message: "Webhook deleted".to_string() })))
+}
+
+// GET /api/webhooks/:id/deliveries?status=dead&limit=50
+// The delivery log, newest first.
+pub async fn webhook_deliveries_index(
+    Extension(pool): Extension<MySqlPool>,
+    owner: WebhookOwner,
+    Path(id): Path<i32>,
+    Query(filter): Query<WebhookDeliveryFilter>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    fetch_webhook(&pool, &owner, id).await?;
+
+    if let Some(status) = &filter.status {
+        if !DELIVERY_STATUSES.contains(&status.as_str()) {
+            return Err((StatusCode::BAD_REQUEST, format!("Unknown delivery status '{}'", status)));
+        }
+    }
+
+    let q = "SELECT * FROM webhook_deliveries WHERE webhook_id = ? AND (? IS NULL OR status = ?) ORDER BY id DESC LIMIT ?";
+
+    let deliveries = sqlx::query_as::<_, WebhookDelivery>(q)
+        .bind(id)
+        .bind(&filter.status)
+        .bind(&filter.status)
+        .bind(filter.limit.unwrap_or(50).min(200))
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch webhook deliveries from database: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(deliveries)))
+}
+
+// One delivery with its payload and every attempt made to send it.
+pub async fn webhook_deliveries_find(
+    Extension(pool): Extension<MySqlPool>,
+    owner: WebhookOwner,
+    Path((id, delivery_id)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let delivery = fetch_delivery(&pool, &owner, id, delivery_id).await?;
+
+    let attempts_log = sqlx::query_as::<_, WebhookDeliveryAttempt>("SELECT * FROM webhook_delivery_attempts WHERE delivery_id = ? ORDER BY id")
+        .bind(delivery.id)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch delivery attempts from database: {}", e)))?;
+
+    let payload = serde_json::from_str(&delivery.payload).unwrap_or_default();
+
+    Ok((StatusCode::OK, Json(WebhookDeliveryDetails { delivery, payload, attempts_log })))
+}
+
+// Sends a dead delivery once more. If that attempt fails too it is dead again.
+pub async fn webhook_deliveries_retry(
+    Extension(pool): Extension<MySqlPool>,
+    owner: WebhookOwner,
+    Path((id, delivery_id)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let delivery = fetch_delivery(&pool, &owner, id, delivery_id).await?;
+
+    if delivery.status != "dead" {
+        return Err((StatusCode::CONFLICT, "Only dead deliveries can be retried".to_string()));
+    }
+
+    sqlx::query("UPDATE webhook_deliveries SET status = 'pending', next_attempt_at = ?, locked_until = NULL WHERE id = ? AND status = 'dead'")
+        .bind(Utc::now())
+        .bind(delivery.id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to requeue webhook delivery: {}", e)))?;
+
+    Ok((StatusCode::ACCEPTED, Json(fetch_delivery(&pool, &owner, id, delivery_id).await?)))
+}
+
+async fn fetch_webhook(pool: &MySqlPool, owner: &WebhookOwner, id: i32) -> Result<Webhook, (StatusCode, String)> {
+    let q = format!("SELECT * FROM webhooks WHERE id = ? AND {} = ?", owner.column());
+
+    sqlx::query_as::<_, Webhook>(&q)
+        .bind(id)
+        .bind(owner.id())
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch webhook from database: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Webhook not found".to_string()))
+}
+
+async fn fetch_delivery(pool: &MySqlPool, owner: &WebhookOwner, id: i32, delivery_id: i32) -> Result<WebhookDelivery, (StatusCode, String)> {
+    fetch_webhook(pool, owner, id).await?;
+
+    sqlx::query_as::<_, WebhookDelivery>("SELECT * FROM webhook_deliveries WHERE id = ? AND webhook_id = ?")
+        .bind(delivery_id)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch webhook delivery from database: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Delivery not found".to_string()))
+}
@@ -0,0 +1,495 @@
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde_json::json;
+use sqlx::MySqlPool;
+use tower_cookies::Cookies;
+use validator::Validate;
+
+use crate::{
+    controllers::auth_controller::{issue_workspace_tokens, set_session_cookies},
+    models::{
+        auth::{Claims, ResponseMessage, TokenResponse},
+        user::User,
+        workspace::{
+            CreateWorkspace, CreateWorkspaceInvitation, PendingWorkspaceInvitation, UpdateWorkspace, UpdateWorkspaceMember,
+            Workspace, WorkspaceInvitation, WorkspaceMember, WorkspaceMembership, WorkspaceRole,
+        },
+    },
+    utils::{
+        events::EventBus,
+        input_validation::handle_validation_errors,
+        notifications::notify,
+        tokens::ACCESS_TOKEN_TTL_MINUTES,
+        workspaces::{personal_workspace, workspace_role},
+    },
+};
+
+// The caller's workspaces, their personal one first.
+pub async fn workspaces_index(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user_id = claims_user_id(&claims)?;
+    personal_workspace(&pool, user_id).await?;
+
+    let q = "SELECT w.*, m.role FROM workspaces w JOIN workspace_members m ON m.workspace_id = w.id \
+             WHERE m.user_id = ? ORDER BY w.personal_user_id IS NULL, w.name";
+
+    let workspaces = sqlx::query_as::<_, WorkspaceMembership>(q)
+        .bind(user_id)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch workspaces from database: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(workspaces)))
+}
+
+// The caller owns the new workspace.
+pub async fn workspaces_create(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+    Json(input): Json<CreateWorkspace>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    input.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string))
+    })?;
+
+    let user_id = claims_user_id(&claims)?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))?;
+
+    let workspace_id = sqlx::query("INSERT INTO workspaces (name, created_by) VALUES (?, ?)")
+        .bind(input.name.trim())
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create workspace: {}", e)))?
+        .last_insert_id() as i32;
+
+    sqlx::query("INSERT INTO workspace_members (workspace_id, user_id, role) VALUES (?, ?, 'owner')")
+        .bind(workspace_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to add workspace member: {}", e)))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit workspace: {}", e)))?;
+
+    let workspace = fetch_workspace(&pool, workspace_id).await?;
+
+    Ok((StatusCode::CREATED, Json(WorkspaceMembership { workspace, role: WorkspaceRole::Owner.to_string() })))
+}
+
+pub async fn workspaces_find(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let role = membership(&pool, id, claims_user_id(&claims)?, WorkspaceRole::Member).await?;
+    let workspace = fetch_workspace(&pool, id).await?;
+
+    Ok((StatusCode::OK, Json(WorkspaceMembership { workspace, role: role.to_string() })))
+}
+
+pub async fn workspaces_update(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(input): Json<UpdateWorkspace>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    input.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string))
+    })?;
+
+    let role = membership(&pool, id, claims_user_id(&claims)?, WorkspaceRole::Owner).await?;
+
+    sqlx::query("UPDATE workspaces SET name = ? WHERE id = ?")
+        .bind(input.name.trim())
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update workspace: {}", e)))?;
+
+    let workspace = fetch_workspace(&pool, id).await?;
+
+    Ok((StatusCode::OK, Json(WorkspaceMembership { workspace, role: role.to_string() })))
+}
+
+// Takes the workspace's lists and todos with it.
+pub async fn workspaces_delete(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    membership(&pool, id, claims_user_id(&claims)?, WorkspaceRole::Owner).await?;
+
+    if fetch_workspace(&pool, id).await?.personal_user_id.is_some() {
+        return Err((StatusCode::BAD_REQUEST, "Personal workspaces cannot be deleted".to_string()));
+    }
+
+    sqlx::query("DELETE FROM workspaces WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete workspace: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(ResponseMessage { message: "Workspace deleted".to_string() })))
+}
+
+// POST /api/workspaces/:id/switch
+// New tokens acting in the workspace. Browsers get them as cookies as well.
+pub async fn workspaces_switch(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+    cookies: Cookies,
+    Path(id): Path<i32>,
+) -> Result<Response, (StatusCode, String)> {
+    let user_id = claims_user_id(&claims)?;
+    membership(&pool, id, user_id, WorkspaceRole::Member).await?;
+
+    let (access_token, refresh_token) = issue_workspace_tokens(&pool, user_id, Some(id)).await?;
+    set_session_cookies(&cookies, access_token.clone(), refresh_token.clone());
+
+    let response = (
+        StatusCode::OK,
+        Json(TokenResponse {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: ACCESS_TOKEN_TTL_MINUTES * 60,
+            refresh_token,
+        }),
+    )
+        .into_response();
+
+    Ok(response)
+}
+
+pub async fn workspace_members_index(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    membership(&pool, id, claims_user_id(&claims)?, WorkspaceRole::Member).await?;
+
+    let q = "SELECT m.user_id, u.username, u.email, m.role, m.created_at AS joined_at FROM workspace_members m \
+             JOIN users u ON u.id = m.user_id WHERE m.workspace_id = ? ORDER BY u.username";
+
+    let members = sqlx::query_as::<_, WorkspaceMember>(q)
+        .bind(id)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch workspace members from database: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(members)))
+}
+
+// Admins manage members; only owners make or unmake owners, and there is always one left.
+pub async fn workspace_members_update(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+    Path((id, user_id)): Path<(i32, i32)>,
+    Json(input): Json<UpdateWorkspaceMember>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let role = membership(&pool, id, claims_user_id(&claims)?, WorkspaceRole::Admin).await?;
+    let current = workspace_role(&pool, id, user_id)
+        .await?
+        .ok_or((StatusCode::NOT_FOUND, "Member not found".to_string()))?;
+
+    if (current == WorkspaceRole::Owner || input.role == WorkspaceRole::Owner) && role < WorkspaceRole::Owner {
+        return Err((StatusCode::FORBIDDEN, "Only owners can make or unmake owners".to_string()));
+    }
+    if current == WorkspaceRole::Owner && input.role != WorkspaceRole::Owner {
+        check_other_owner(&pool, id, user_id).await?;
+    }
+
+    sqlx::query("UPDATE workspace_members SET role = ? WHERE workspace_id = ? AND user_id = ?")
+        .bind(input.role.as_str())
+        .bind(id)
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update workspace member: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(ResponseMessage { message: format!("Member is now {}", input.role) })))
+}
+
+// Admins remove members, and anyone can leave. Personal workspaces cannot be left, and
+// there is always an owner left. The member's todos stay with the workspace.
+pub async fn workspace_members_delete(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+    Path((id, user_id)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let caller_id = claims_user_id(&claims)?;
+    let needed = if user_id == caller_id { WorkspaceRole::Member } else { WorkspaceRole::Admin };
+    let role = membership(&pool, id, caller_id, needed).await?;
+
+    let current = workspace_role(&pool, id, user_id)
+        .await?
+        .ok_or((StatusCode::NOT_FOUND, "Member not found".to_string()))?;
+
+    if fetch_workspace(&pool, id).await?.personal_user_id == Some(user_id) {
+        return Err((StatusCode::BAD_REQUEST, "Personal workspaces cannot be left".to_string()));
+    }
+    if current == WorkspaceRole::Owner {
+        if user_id != caller_id && role < WorkspaceRole::Owner {
+            return Err((StatusCode::FORBIDDEN, "Only owners can remove owners".to_string()));
+        }
+        check_other_owner(&pool, id, user_id).await?;
+    }
+
+    sqlx::query("DELETE FROM workspace_members WHERE workspace_id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to remove workspace member: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(ResponseMessage { message: "Member removed".to_string() })))
+}
+
+// Invites a user, by username or email. Inviting someone who declined before invites them
+// again.
+pub async fn workspace_invitations_create(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(bus): Extension<EventBus>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(input): Json<CreateWorkspaceInvitation>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    input.validate().map_err(|e| {
+        let error_string = handle_validation_errors(e);
+        (StatusCode::BAD_REQUEST, format!("Validation failed: {}", error_string))
+    })?;
+
+    let user_id = claims_user_id(&claims)?;
+    let role = membership(&pool, id, user_id, WorkspaceRole::Admin).await?;
+
+    if input.role == WorkspaceRole::Owner && role < WorkspaceRole::Owner {
+        return Err((StatusCode::FORBIDDEN, "Only owners can invite owners".to_string()));
+    }
+
+    let workspace = fetch_workspace(&pool, id).await?;
+    if workspace.personal_user_id.is_some() {
+        return Err((StatusCode::BAD_REQUEST, "Personal workspaces cannot be shared".to_string()));
+    }
+
+    let invitee = input.invitee.trim();
+    let invitee = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ? OR email = ? LIMIT 1")
+        .bind(invitee)
+        .bind(invitee)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch user from database: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "No user with that username or email".to_string()))?;
+
+    if workspace_role(&pool, id, invitee.id).await?.is_some() {
+        return Err((StatusCode::CONFLICT, "That user is already a member".to_string()));
+    }
+
+    let existing = fetch_invitation(&pool, id, invitee.id).await?;
+    if existing.as_ref().is_some_and(|invitation| invitation.status == "pending") {
+        return Err((StatusCode::CONFLICT, "That user has already been invited".to_string()));
+    }
+
+    // Accepted invitations of members who have since left are reused as well.
+    let q = "INSERT INTO workspace_invitations (workspace_id, user_id, role, invited_by) VALUES (?, ?, ?, ?) \
+             ON DUPLICATE KEY UPDATE role = VALUES(role), invited_by = VALUES(invited_by), status = 'pending', responded_at = NULL";
+
+    sqlx::query(q)
+        .bind(id)
+        .bind(invitee.id)
+        .bind(input.role.as_str())
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to invite user: {}", e)))?;
+
+    let invitation = fetch_invitation(&pool, id, invitee.id)
+        .await?
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch invitation from database".to_string()))?;
+
+    let title = format!("You were invited to the {} workspace", workspace.name);
+    let body = format!("Join as {}.", input.role);
+    let data = json!({ "workspace_id": id, "invitation_id": invitation.id, "role": input.role, "invited_by": user_id });
+    if let Err(e) = notify(&pool, &bus, invitee.id, "workspace.invitation", &title, &body, Some(data)).await {
+        println!("Failed to notify user {} of workspace invitation: {}", invitee.id, e);
+    }
+
+    Ok((StatusCode::CREATED, Json(invitation)))
+}
+
+// GET /api/workspaces/invitations
+pub async fn workspace_invitations_index(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let q = "SELECT i.id, i.workspace_id, w.name AS workspace_name, i.role, u.username AS invited_by_username, i.created_at \
+             FROM workspace_invitations i JOIN workspaces w ON w.id = i.workspace_id JOIN users u ON u.id = i.invited_by \
+             WHERE i.user_id = ? AND i.status = 'pending' ORDER BY i.id DESC";
+
+    let invitations = sqlx::query_as::<_, PendingWorkspaceInvitation>(q)
+        .bind(claims_user_id(&claims)?)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch invitations from database: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(invitations)))
+}
+
+// Makes the caller a member with the role they were invited as.
+pub async fn workspace_invitations_accept(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(bus): Extension<EventBus>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user_id = claims_user_id(&claims)?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))?;
+
+    let invitation = respond(&mut tx, user_id, id, "accepted").await?;
+
+    sqlx::query("INSERT IGNORE INTO workspace_members (workspace_id, user_id, role) VALUES (?, ?, ?)")
+        .bind(invitation.workspace_id)
+        .bind(user_id)
+        .bind(&invitation.role)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to add workspace member: {}", e)))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit invitation: {}", e)))?;
+
+    // Lets whoever sent the invitation know.
+    let q = "SELECT u.username, w.name FROM users u JOIN workspaces w ON w.id = ? WHERE u.id = ?";
+    let accepted = sqlx::query_as::<_, (String, String)>(q)
+        .bind(invitation.workspace_id)
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|e| format!("Failed to fetch invitation details: {}", e));
+    let data = json!({ "workspace_id": invitation.workspace_id, "invitation_id": invitation.id });
+
+    let notified = match accepted {
+        Ok((username, workspace_name)) => {
+            let title = format!("{} joined {}", username, workspace_name);
+            notify(&pool, &bus, invitation.invited_by, "workspace.invitation_accepted", &title, "", Some(data)).await.map(|_| ())
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = notified {
+        println!("Failed to notify user {} of accepted workspace invitation: {}", invitation.invited_by, e);
+    }
+
+    Ok((StatusCode::OK, Json(invitation)))
+}
+
+pub async fn workspace_invitations_decline(
+    Extension(pool): Extension<MySqlPool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))?;
+
+    let invitation = respond(&mut tx, claims_user_id(&claims)?, id, "declined").await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit invitation: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(invitation)))
+}
+
+// Accepts or declines one of the user's pending invitations.
+async fn respond(
+    tx: &mut sqlx::Transaction<'_, sqlx::MySql>,
+    user_id: i32,
+    id: i64,
+    status: &str,
+) -> Result<WorkspaceInvitation, (StatusCode, String)> {
+    let result = sqlx::query("UPDATE workspace_invitations SET status = ?, responded_at = CURRENT_TIMESTAMP WHERE id = ? AND user_id = ? AND status = 'pending'")
+        .bind(status)
+        .bind(id)
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update invitation: {}", e)))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Invitation not found".to_string()));
+    }
+
+    sqlx::query_as::<_, WorkspaceInvitation>("SELECT * FROM workspace_invitations WHERE id = ?")
+        .bind(id)
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch invitation from database: {}", e)))
+}
+
+// The user's role in the workspace, if it is at least `needed`. Workspaces the user is not
+// a member of are not found.
+async fn membership(pool: &MySqlPool, workspace_id: i32, user_id: i32, needed: WorkspaceRole) -> Result<WorkspaceRole, (StatusCode, String)> {
+    match workspace_role(pool, workspace_id, user_id).await? {
+        Some(role) if role >= needed => Ok(role),
+        Some(role) => Err((StatusCode::FORBIDDEN, format!("A workspace {} cannot do that; it needs {} access", role, needed))),
+        None => Err((StatusCode::NOT_FOUND, "Workspace not found".to_string())),
+    }
+}
+
+// Fails if `user_id` is the workspace's last owner.
+async fn check_other_owner(pool: &MySqlPool, workspace_id: i32, user_id: i32) -> Result<(), (StatusCode, String)> {
+    let others = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM workspace_members WHERE workspace_id = ? AND role = 'owner' AND user_id <> ?")
+        .bind(workspace_id)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to count workspace owners: {}", e)))?;
+
+    if others == 0 {
+        return Err((StatusCode::BAD_REQUEST, "A workspace needs an owner; make someone else owner first".to_string()));
+    }
+
+    Ok(())
+}
+
+async fn fetch_workspace(pool: &MySqlPool, id: i32) -> Result<Workspace, (StatusCode, String)> {
+    sqlx::query_as::<_, Workspace>("SELECT * FROM workspaces WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch workspace from database: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Workspace not found".to_string()))
+}
+
+async fn fetch_invitation(pool: &MySqlPool, workspace_id: i32, user_id: i32) -> Result<Option<WorkspaceInvitation>, (StatusCode, String)> {
+    sqlx::query_as::<_, WorkspaceInvitation>("SELECT * FROM workspace_invitations WHERE workspace_id = ? AND user_id = ?")
+        .bind(workspace_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch invitation from database: {}", e)))
+}
+
+fn claims_user_id(claims: &Claims) -> Result<i32, (StatusCode, String)> {
+    claims
+        .sub
+        .parse()
+        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token subject".to_string()))
+}
@@ -0,0 +1,132 @@
+// Email digests.
+//
+// "digests.scan" runs every minute and picks up the users whose next_digest_at has passed.
+// Moving next_digest_at on to the following digest claims it, so each digest is sent once
+// however many instances scan; the digest itself goes out as a "digests.send" job.
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, MySqlPool};
+
+use crate::{
+    models::{
+        digest::{DigestPreferences, SendDigest},
+        job::Job,
+        user::User,
+    },
+    utils::{
+        digests::compose_digest,
+        jobs::{enqueue, JobHandler, JobOptions},
+        mailer::Mailer,
+    },
+};
+
+const SCAN_BATCH: i64 = 500;
+
+#[derive(FromRow)]
+struct DueDigest {
+    user_id: i32,
+    #[sqlx(flatten)]
+    preferences: DigestPreferences,
+}
+
+pub struct ScanDigests {
+    pub pool: MySqlPool,
+}
+
+#[async_trait]
+impl JobHandler for ScanDigests {
+    async fn run(&self, _job: &Job) -> Result<(), String> {
+        let now = Utc::now();
+        let q = "SELECT id AS user_id, time_zone, digest_frequency, digest_hour, digest_weekday, next_digest_at FROM users \
+                 WHERE digest_frequency <> 'off' AND next_digest_at <= ? ORDER BY next_digest_at LIMIT ?";
+
+        let due = sqlx::query_as::<_, DueDigest>(q)
+            .bind(now)
+            .bind(SCAN_BATCH)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch due digests: {}", e))?;
+
+        for digest in due {
+            let Some(due_at) = digest.preferences.next_digest_at else {
+                continue;
+            };
+            let due_at: DateTime<Utc> = due_at.with_timezone(&Utc);
+            let mut tx = self.pool.begin().await.map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+            let claimed = sqlx::query("UPDATE users SET next_digest_at = ? WHERE id = ? AND next_digest_at = ?")
+                .bind(digest.preferences.next_digest_at(now))
+                .bind(digest.user_id)
+                .bind(due_at)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to claim digest: {}", e))?;
+
+            // Another instance got there first.
+            if claimed.rows_affected() == 0 {
+                continue;
+            }
+
+            // A digest missed while the server was down covers the time since it was due.
+            let payload = SendDigest {
+                user_id: digest.user_id,
+                period_start: digest.preferences.period_start(due_at),
+                period_end: now,
+            };
+            let options = JobOptions {
+                unique_key: Some(format!("digest:{}:{}", digest.user_id, due_at.timestamp())),
+                ..JobOptions::default()
+            };
+            enqueue(&mut *tx, "digests.send", &payload, options)
+                .await
+                .map_err(|(_, e)| e)?;
+
+            tx.commit().await.map_err(|e| format!("Failed to commit digest: {}", e))?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct SendDigests {
+    pub pool: MySqlPool,
+    pub mailer: Arc<dyn Mailer>,
+}
+
+#[async_trait]
+impl JobHandler for SendDigests {
+    async fn run(&self, job: &Job) -> Result<(), String> {
+        let SendDigest { user_id, period_start, period_end } = job.payload()?;
+
+        // Gone since the digest was claimed.
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch user: {}", e))?;
+        let Some(user) = user else {
+            return Ok(());
+        };
+
+        let q = "SELECT time_zone, digest_frequency, digest_hour, digest_weekday, next_digest_at FROM users WHERE id = ?";
+        let preferences = sqlx::query_as::<_, DigestPreferences>(q)
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch digest preferences: {}", e))?;
+
+        // Turned off while the job waited.
+        if preferences.digest_frequency == "off" {
+            return Ok(());
+        }
+
+        // Nothing to report is not worth an email.
+        let Some(digest) = compose_digest(&self.pool, &user, &preferences, period_start, period_end).await? else {
+            return Ok(());
+        };
+
+        self.mailer.send_html(&user.email, &digest.subject, &digest.text, &digest.html).await
+    }
+}
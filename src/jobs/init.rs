@@ -0,0 +1,56 @@
+use dotenv::dotenv;
+use sqlx::MySqlPool;
+
+use crate::utils::{
+    env::env_or,
+    events::EventBus,
+    jobs::{JobRegistry, JobRunner, JobRunnerHandle},
+    mailer::mailer_from_env,
+    reminders::reminder_channels,
+    webhooks::WebhookWorker
+};
+
+use super::{
+    digests::{ScanDigests, SendDigests},
+    maintenance::{PruneEventLog, PruneJobs, PruneNotifications, PurgeExpiredTokens},
+    reminders::{DeliverReminders, ScanReminders},
+    webhooks::DeliverWebhooks
+};
+
+// Registers every job kind and its schedule, and starts the workers.
+//   EVENT_LOG_RETENTION_DAYS     how long change events are kept for resuming clients (default 7)
+//   JOB_RETENTION_DAYS           how long finished jobs are kept (default 7)
+//   NOTIFICATION_RETENTION_DAYS  how long read notifications are kept (default 90)
+pub async fn run(pool: MySqlPool, event_bus: EventBus) -> Result<JobRunnerHandle, Box<dyn std::error::Error>> {
+    dotenv().ok();
+    let event_retention_days = env_or("EVENT_LOG_RETENTION_DAYS", 7);
+    let job_retention_days = env_or("JOB_RETENTION_DAYS", 7);
+    let notification_retention_days = env_or("NOTIFICATION_RETENTION_DAYS", 90);
+
+    let mailer = mailer_from_env();
+
+    let registry = JobRegistry::default()
+        .register("tokens.purge_expired", PurgeExpiredTokens { pool: pool.clone() })
+        .register("events.prune", PruneEventLog { pool: pool.clone(), retention_days: event_retention_days })
+        .register("jobs.prune", PruneJobs { pool: pool.clone(), retention_days: job_retention_days })
+        .register("notifications.prune", PruneNotifications { pool: pool.clone(), retention_days: notification_retention_days })
+        .register("reminders.scan", ScanReminders { pool: pool.clone() })
+        .register("reminders.deliver", DeliverReminders {
+            pool: pool.clone(),
+            channels: reminder_channels(pool.clone(), mailer.clone(), event_bus),
+        })
+        .register("digests.scan", ScanDigests { pool: pool.clone() })
+        .register("digests.send", SendDigests { pool: pool.clone(), mailer })
+        .register("webhooks.deliver", DeliverWebhooks { worker: WebhookWorker::from_env(pool.clone())? })
+        .schedule("tokens.purge_expired", "0 15 * * * *")?
+        .schedule("events.prune", "0 30 * * * *")?
+        .schedule("jobs.prune", "0 45 3 * * *")?
+        .schedule("notifications.prune", "0 50 3 * * *")?
+        .schedule("reminders.scan", "0 * * * * *")?
+        .schedule("digests.scan", "30 * * * * *")?
+        .schedule("webhooks.deliver", &WebhookWorker::schedule())?;
+
+    let runner = JobRunner::from_env(pool, registry).spawn().await?;
+
+    Ok(runner)
+}
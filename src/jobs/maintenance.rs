@@ -0,0 +1,95 @@
+// Recurring clean-up of rows that are of no use once they have expired.
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::MySqlPool;
+
+use crate::{models::job::Job, utils::jobs::JobHandler};
+
+// Rows deleted per statement, so a large backlog does not hold locks for long.
+const DELETE_BATCH: u64 = 5000;
+
+// Deletes from `table` the rows matched by `condition` with its one bound value, in batches.
+async fn delete_in_batches(pool: &MySqlPool, table: &str, condition: &str, cutoff: chrono::DateTime<Utc>) -> Result<u64, String> {
+    let q = format!("DELETE FROM {} WHERE {} LIMIT {}", table, condition, DELETE_BATCH);
+    let mut deleted = 0;
+
+    loop {
+        let result = sqlx::query(&q)
+            .bind(cutoff)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Failed to delete from {}: {}", table, e))?;
+
+        deleted += result.rows_affected();
+        if result.rows_affected() < DELETE_BATCH {
+            return Ok(deleted);
+        }
+    }
+}
+
+// "tokens.purge_expired": expired refresh and access tokens.
+pub struct PurgeExpiredTokens {
+    pub pool: MySqlPool,
+}
+
+#[async_trait]
+impl JobHandler for PurgeExpiredTokens {
+    async fn run(&self, _job: &Job) -> Result<(), String> {
+        let now = Utc::now();
+
+        delete_in_batches(&self.pool, "refresh_tokens", "expires_at < ?", now).await?;
+        delete_in_batches(&self.pool, "access_tokens", "expires_at < ?", now).await?;
+
+        Ok(())
+    }
+}
+
+// "events.prune": change events older than the retention period. Clients that were away
+// for longer are told to resync.
+pub struct PruneEventLog {
+    pub pool: MySqlPool,
+    pub retention_days: i64,
+}
+
+#[async_trait]
+impl JobHandler for PruneEventLog {
+    async fn run(&self, _job: &Job) -> Result<(), String> {
+        let cutoff = Utc::now() - chrono::Duration::days(self.retention_days);
+        delete_in_batches(&self.pool, "events", "created_at < ?", cutoff).await?;
+
+        Ok(())
+    }
+}
+
+// "jobs.prune": finished jobs older than the retention period.
+pub struct PruneJobs {
+    pub pool: MySqlPool,
+    pub retention_days: i64,
+}
+
+#[async_trait]
+impl JobHandler for PruneJobs {
+    async fn run(&self, _job: &Job) -> Result<(), String> {
+        let cutoff = Utc::now() - chrono::Duration::days(self.retention_days);
+        delete_in_batches(&self.pool, "jobs", "status IN ('completed', 'dead') AND finished_at < ?", cutoff).await?;
+
+        Ok(())
+    }
+}
+
+// "notifications.prune": notifications read longer ago than the retention period. Unread
+// ones are kept however old they are.
+pub struct PruneNotifications {
+    pub pool: MySqlPool,
+    pub retention_days: i64,
+}
+
+#[async_trait]
+impl JobHandler for PruneNotifications {
+    async fn run(&self, _job: &Job) -> Result<(), String> {
+        let cutoff = Utc::now() - chrono::Duration::days(self.retention_days);
+        delete_in_batches(&self.pool, "notifications", "read_at < ?", cutoff).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+// Due-date reminders.
+//
+// "reminders.scan" runs every minute and claims each todo whose reminder time has passed by
+// inserting a todo_reminders row; its unique key on (todo_id, remind_at) is what makes every
+// reminder fire once, however many instances scan. The reminder time is the todo's
+// remind_at or, failing that, the user's due_reminder_minutes before its due_at. Each claimed reminder becomes a
+// "reminders.deliver" job, held back until the user's quiet hours are over.
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Local, Utc};
+use sqlx::{FromRow, MySqlPool};
+
+use crate::{
+    models::{
+        job::Job,
+        reminder::{DeliverReminder, ReminderPreferences, TodoReminder},
+        todo::Todo,
+        user::User,
+    },
+    utils::{
+        jobs::{enqueue, JobHandler, JobOptions},
+        reminders::{DueReminder, ReminderChannel},
+    },
+};
+
+// Reminders that were due longer ago than this when first seen, e.g. because the server
+// was down, are dropped rather than sent late.
+const LOOKBACK_HOURS: i64 = 24;
+const SCAN_BATCH: i64 = 500;
+
+#[derive(FromRow)]
+struct DueTodo {
+    todo_id: i32,
+    user_id: i32,
+    remind_at: DateTime<Local>,
+    #[sqlx(flatten)]
+    preferences: ReminderPreferences,
+}
+
+pub struct ScanReminders {
+    pub pool: MySqlPool,
+}
+
+#[async_trait]
+impl JobHandler for ScanReminders {
+    async fn run(&self, _job: &Job) -> Result<(), String> {
+        let now = Utc::now();
+        let lookback = now - chrono::Duration::hours(LOOKBACK_HOURS);
+        let q = "SELECT d.* FROM ( \
+                     SELECT t.id AS todo_id, t.user_id, \
+                     COALESCE(t.remind_at, t.due_at - INTERVAL u.due_reminder_minutes MINUTE) AS remind_at, \
+                     u.time_zone, u.quiet_hours_start, u.quiet_hours_end, u.reminder_channels, u.due_reminder_minutes \
+                     FROM todos t JOIN users u ON u.id = t.user_id \
+                     WHERE NOT t.done AND (t.remind_at > ? OR (t.remind_at IS NULL AND t.due_at > ?)) \
+                 ) d LEFT JOIN todo_reminders r ON r.todo_id = d.todo_id AND r.remind_at = d.remind_at \
+                 WHERE d.remind_at <= ? AND d.remind_at > ? AND r.id IS NULL \
+                 ORDER BY d.remind_at LIMIT ?";
+
+        let due = sqlx::query_as::<_, DueTodo>(q)
+            .bind(lookback)
+            .bind(lookback)
+            .bind(now)
+            .bind(lookback)
+            .bind(SCAN_BATCH)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch due reminders: {}", e))?;
+
+        for todo in due {
+            let deliver_at = todo.preferences.deliverable_at(now);
+            let mut tx = self.pool.begin().await.map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+            let claimed = sqlx::query("INSERT IGNORE INTO todo_reminders (todo_id, user_id, remind_at, deliver_at) VALUES (?, ?, ?, ?)")
+                .bind(todo.todo_id)
+                .bind(todo.user_id)
+                .bind(todo.remind_at)
+                .bind(deliver_at)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to claim reminder: {}", e))?;
+
+            // Another instance got there first.
+            if claimed.rows_affected() == 0 {
+                continue;
+            }
+
+            let reminder_id = claimed.last_insert_id() as i64;
+            let options = JobOptions {
+                unique_key: Some(format!("reminder:{}", reminder_id)),
+                run_at: Some(deliver_at),
+                ..JobOptions::default()
+            };
+            enqueue(&mut *tx, "reminders.deliver", &DeliverReminder { reminder_id }, options)
+                .await
+                .map_err(|(_, e)| e)?;
+
+            tx.commit().await.map_err(|e| format!("Failed to commit reminder: {}", e))?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct DeliverReminders {
+    pub pool: MySqlPool,
+    pub channels: HashMap<&'static str, Arc<dyn ReminderChannel>>,
+}
+
+#[async_trait]
+impl JobHandler for DeliverReminders {
+    async fn run(&self, job: &Job) -> Result<(), String> {
+        let DeliverReminder { reminder_id } = job.payload()?;
+
+        // Gone with its todo, or already delivered.
+        let reminder = sqlx::query_as::<_, TodoReminder>("SELECT * FROM todo_reminders WHERE id = ?")
+            .bind(reminder_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch reminder: {}", e))?;
+        let Some(reminder) = reminder.filter(|r| r.status == "pending") else {
+            return Ok(());
+        };
+
+        let todo = sqlx::query_as::<_, Todo>("SELECT * FROM todos WHERE id = ?")
+            .bind(reminder.todo_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch todo: {}", e))?;
+
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+            .bind(reminder.user_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch user: {}", e))?;
+        let preferences = sqlx::query_as::<_, ReminderPreferences>(
+            "SELECT time_zone, quiet_hours_start, quiet_hours_end, reminder_channels, due_reminder_minutes FROM users WHERE id = ?",
+        )
+        .bind(reminder.user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch reminder preferences: {}", e))?;
+
+        // Done or rescheduled while the reminder waited for quiet hours to end.
+        let todo = match todo {
+            Some(todo) if !todo.done && preferences.reminder_time(&todo) == Some(reminder.remind_at) => todo,
+            _ => return self.finish(reminder.id, "skipped").await,
+        };
+
+        let due = DueReminder { id: reminder.id, user, todo, time_zone: preferences.time_zone() };
+        let sent: Vec<&str> = reminder.sent_channels.split_whitespace().collect();
+        let mut errors = Vec::new();
+
+        for name in preferences.channels().filter(|name| !sent.contains(name)) {
+            let Some(channel) = self.channels.get(name) else {
+                continue;
+            };
+
+            match channel.send(&due).await {
+                Ok(()) => {
+                    sqlx::query("UPDATE todo_reminders SET sent_channels = TRIM(CONCAT(sent_channels, ' ', ?)) WHERE id = ?")
+                        .bind(name)
+                        .bind(reminder.id)
+                        .execute(&self.pool)
+                        .await
+                        .map_err(|e| format!("Failed to record {} reminder: {}", name, e))?;
+                }
+                Err(e) => errors.push(format!("{}: {}", name, e)),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors.join("; "));
+        }
+
+        self.finish(reminder.id, "sent").await
+    }
+}
+
+impl DeliverReminders {
+    async fn finish(&self, reminder_id: i64, status: &str) -> Result<(), String> {
+        sqlx::query("UPDATE todo_reminders SET status = ?, sent_at = IF(? = 'sent', ?, NULL) WHERE id = ?")
+            .bind(status)
+            .bind(status)
+            .bind(Utc::now())
+            .bind(reminder_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to update reminder: {}", e))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+// Webhook delivery, retries included.
+use async_trait::async_trait;
+
+use crate::{
+    models::job::Job,
+    utils::{jobs::JobHandler, webhooks::WebhookWorker},
+};
+
+// "webhooks.deliver": fans new outbox events out to the subscribed webhooks, then sends
+// every delivery that is due, first attempts and retries alike. Deliveries lock themselves,
+// so overlapping runs on several instances do not send anything twice.
+pub struct DeliverWebhooks {
+    pub worker: WebhookWorker,
+}
+
+#[async_trait]
+impl JobHandler for DeliverWebhooks {
+    async fn run(&self, _job: &Job) -> Result<(), String> {
+        self.worker
+            .dispatch_outbox()
+            .await
+            .map_err(|e| format!("Failed to dispatch webhook events: {}", e))?;
+
+        self.worker
+            .deliver_due()
+            .await
+            .map_err(|e| format!("Failed to deliver webhooks: {}", e))
+    }
+}
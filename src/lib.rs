@@ -5,6 +5,24 @@ pub mod controllers {
     pub mod refresh_tokens_controller;
     pub mod access_tokens_controller;
     pub mod api_keys_controller;
+    pub mod phone_verifications_controller;
+    pub mod login_lockouts_controller;
+    pub mod jwks_controller;
+    pub mod oauth_controller;
+    pub mod oauth_clients_controller;
+    pub mod oidc_controller;
+    pub mod magic_links_controller;
+    pub mod webauthn_controller;
+    pub mod impersonation_controller;
+    pub mod events_controller;
+    pub mod webhooks_controller;
+    pub mod reminder_preferences_controller;
+    pub mod notifications_controller;
+    pub mod digest_preferences_controller;
+    pub mod todo_shares_controller;
+    pub mod workspaces_controller;
+    pub mod lists_controller;
+    pub mod share_links_controller;
 }
 
 pub mod models {
@@ -14,12 +32,54 @@ pub mod models {
     pub mod access_token;
     pub mod api_key;
     pub mod auth;
+    pub mod phone_verification;
+    pub mod login_attempt;
+    pub mod login_lockout;
+    pub mod oauth;
+    pub mod oauth_client;
+    pub mod oidc;
+    pub mod magic_link;
+    pub mod webauthn;
+    pub mod impersonation;
+    pub mod event;
+    pub mod webhook;
+    pub mod job;
+    pub mod notification;
+    pub mod reminder;
+    pub mod digest;
+    pub mod todo_share;
+    pub mod workspace;
+    pub mod list;
+    pub mod share_link;
 }
 
 pub mod utils {
     pub mod input_validation;
+    pub mod env;
     pub mod error;
     pub mod tokens;
+    pub mod sms;
+    pub mod client_ip;
+    pub mod lockout;
+    pub mod password;
+    pub mod jwt_keys;
+    pub mod cookies;
+    pub mod api_key_usage;
+    pub mod request_signing;
+    pub mod rate_limit;
+    pub mod oauth;
+    pub mod oidc;
+    pub mod mailer;
+    pub mod magic_link;
+    pub mod webauthn;
+    pub mod events;
+    pub mod webhooks;
+    pub mod jobs;
+    pub mod reminders;
+    pub mod notifications;
+    pub mod digests;
+    pub mod sharing;
+    pub mod workspaces;
 }
 
 pub mod routes {
@@ -30,6 +90,24 @@ pub mod routes {
     pub mod refresh_tokens;
     pub mod access_tokens;
     pub mod api_keys;
+    pub mod admin;
+    pub mod well_known;
+    pub mod oauth;
+    pub mod webauthn;
+    pub mod events;
+    pub mod webhooks;
+    pub mod notifications;
+    pub mod workspaces;
+    pub mod lists;
+    pub mod share_links;
+}
+
+pub mod jobs {
+    pub mod init;
+    pub mod maintenance;
+    pub mod reminders;
+    pub mod digests;
+    pub mod webhooks;
 }
 
 pub mod database {
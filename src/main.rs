@@ -1,16 +1,69 @@
-use todos_web_api::routes;
+use std::net::SocketAddr;
+
+use tokio::sync::watch;
+
+use todos_web_api::{database, jobs, routes, utils::{api_key_usage::ApiKeyUsage, events::EventBus}};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let app = routes::init::run().await?;
+    let pool = database::init::run().await?;
+    // Shared so that events published by jobs reach the clients connected to this server.
+    let event_bus = EventBus::new(pool.clone());
+    // Kept here so that usage still buffered when the server stops can be saved.
+    let api_key_usage = ApiKeyUsage::default();
+    let app = routes::init::run(pool.clone(), event_bus.clone(), api_key_usage.clone()).await?;
+    let job_runner = jobs::init::run(pool.clone(), event_bus.clone()).await?;
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8000")
         .await
         .unwrap();
 
-    axum::serve(listener, app)
-        .await
-        .unwrap();
+    // One signal stops everything at once: the server stops taking connections, live event
+    // streams are closed rather than waited for, and the jobs in flight finish alongside the
+    // last requests.
+    let (shutdown, mut shutdown_requested) = watch::channel(false);
+
+    // Connect info is needed to know the client IP for login lockouts.
+    let serve = async {
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(async move {
+                let _ = shutdown_requested.wait_for(|requested| *requested).await;
+            })
+            .await
+            .unwrap();
+    };
+    let stop = async {
+        shutdown_signal().await;
+        shutdown.send_replace(true);
+        event_bus.close();
+        job_runner.shutdown().await;
+    };
+    tokio::join!(serve, stop);
+
+    // Both requests and jobs are done; nothing records usage any more.
+    api_key_usage.flush(&pool).await;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// Ctrl+C, or SIGTERM from docker stop and most process managers.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("Failed to listen for Ctrl+C");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
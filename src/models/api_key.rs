@@ -4,37 +4,101 @@ use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 use validator::{ValidationError, ValidationErrors};
 
+// Everything an API key can be allowed to do.
+pub const API_KEY_SCOPES: [&str; 5] = [
+    "users:read",
+    "users:write",
+    "todos:read",
+    "todos:write",
+    // Subscribe to webhooks. Events are still limited by the read scopes above.
+    "webhooks:manage",
+];
 
-#[derive(Debug, FromRow, Serialize, Deserialize)]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct ApiKey {
     pub id: i32,
-    pub api_key: String,
+    // Visible start of the key, e.g. "tdk_live_ab12", so clients can tell keys apart.
+    pub key_prefix: String,
+    // SHA-256 of the full key. The key itself is never stored.
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    // Shared secret for HMAC request signing. Only ever returned when it is (re)generated.
+    #[serde(skip_serializing)]
+    pub signing_secret: Option<String>,
     pub client_name: String,
     pub contact_email: String,
+    // Space separated, e.g. "users:read todos:write".
+    pub scopes: String,
     pub is_active: bool,
+    // NULL means the key never expires.
+    pub expires_at: Option<DateTime<Local>>,
+    // Usage is flushed in batches, so these can lag behind by a minute or so.
+    pub last_used_at: Option<DateTime<Local>>,
+    pub usage_count: i64,
+    // Set once the key has been rotated; it then only lives until expires_at.
+    pub replaced_by_id: Option<i32>,
     pub created_at: DateTime<Local>,
     pub updated_at: DateTime<Local>
 }
 
+impl ApiKey {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.split_whitespace().any(|s| s == scope)
+    }
+}
+
+// Returned once from api_keys_create and api_keys_rotate. The secrets cannot be retrieved again.
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub secret: String,
+    pub signing_secret: String,
+}
+
+// Returned once from api_keys_signing_secret.
+#[derive(Debug, Serialize)]
+pub struct SigningSecret {
+    pub api_key_id: i32,
+    pub signing_secret: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateApiKey {
     pub client_name: String,
-    pub contact_email: String
+    pub contact_email: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    // Leave out for a key that never expires.
+    pub expires_in_days: Option<i64>
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RotateApiKey {
+    // How long the old key keeps working. Defaults to API_KEY_ROTATION_GRACE_HOURS.
+    pub grace_period_hours: Option<i64>,
+    // Lifetime of the new key. Defaults to the lifetime of the old key.
+    pub expires_in_days: Option<i64>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExpiringApiKeysFilter {
+    pub within_days: Option<i64>
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UpdateApiKey {
-    pub api_key: Option<String>,
     pub client_name: Option<String>,
     pub contact_email: Option<String>,
+    pub scopes: Option<Vec<String>>,
     pub is_active: Option<bool>
 }
 
 #[derive(Debug)]
 pub enum FieldValue {
-    ApiKey(Option<String>),
     ClientName(Option<String>),
     ContactEmail(Option<String>),
+    Scopes(Option<Vec<String>>),
     IsActive(Option<bool>),
 }
 
@@ -44,14 +108,29 @@ impl IntoIterator for UpdateApiKey {
 
     fn into_iter(self) -> Self::IntoIter {
         vec![
-            ("api_key", FieldValue::ApiKey(self.api_key)),
             ("client_name", FieldValue::ClientName(self.client_name)),
             ("contact_email", FieldValue::ContactEmail(self.contact_email)),
+            ("scopes", FieldValue::Scopes(self.scopes)),
             ("is_active", FieldValue::IsActive(self.is_active))
         ].into_iter()
     }
 }
 
+// Shared by create and update: every requested scope has to be a known one.
+pub fn validate_scopes(scopes: &[String], errors: &mut ValidationErrors) {
+    for scope in scopes {
+        if !API_KEY_SCOPES.contains(&scope.as_str()) {
+            errors.add(
+                "scopes",
+                ValidationError::new(
+                    "unknown scope")
+                    .with_message(Cow::Owned(format!("Unknown scope '{}'.", scope))
+                )
+            );
+        }
+    }
+}
+
 impl validator::Validate for CreateApiKey {
     fn validate(&self) -> Result<(), ValidationErrors> {
         let mut errors = ValidationErrors::new();
@@ -74,6 +153,17 @@ impl validator::Validate for CreateApiKey {
                 )
             );
         }
+        if self.scopes.is_empty() {
+            errors.add(
+                "scopes",
+                ValidationError::new(
+                    "scopes are required")
+                    .with_message(Cow::Borrowed("At least one scope is required.")
+                )
+            );
+        }
+        validate_scopes(&self.scopes, &mut errors);
+        validate_positive("expires_in_days", self.expires_in_days, &mut errors);
 
         if errors.is_empty() {
             Ok(())
@@ -81,4 +171,55 @@ impl validator::Validate for CreateApiKey {
             Err(errors)
         }
     }
-}
\ No newline at end of file
+}
+
+impl validator::Validate for RotateApiKey {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if self.grace_period_hours.is_some_and(|hours| hours < 0) {
+            errors.add(
+                "grace_period_hours",
+                ValidationError::new(
+                    "grace period cannot be negative")
+                    .with_message(Cow::Borrowed("Grace period cannot be negative.")
+                )
+            );
+        }
+        validate_positive("expires_in_days", self.expires_in_days, &mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn validate_positive(field: &'static str, value: Option<i64>, errors: &mut ValidationErrors) {
+    if value.is_some_and(|v| v <= 0) {
+        errors.add(
+            field,
+            ValidationError::new(
+                "must be positive")
+                .with_message(Cow::Borrowed("Must be greater than 0.")
+            )
+        );
+    }
+}
+
+impl validator::Validate for UpdateApiKey {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if let Some(scopes) = &self.scopes {
+            validate_scopes(scopes, &mut errors);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
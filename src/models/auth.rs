@@ -3,10 +3,29 @@ use std::borrow::Cow;
 use serde::{Deserialize, Serialize};
 use validator::{ValidationError, ValidationErrors};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,  
+    // Set while an admin is impersonating `sub` (RFC 8693 "act" claim).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+    // The workspace the token acts in. Without one it acts in the user's personal workspace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wid: Option<i32>,
+}
+
+impl Claims {
+    pub fn is_impersonated(&self) -> bool {
+        self.act.is_some()
+    }
+}
+
+// The admin acting on behalf of the user, and the impersonation session that allows it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Actor {
+    pub sub: String,
+    pub sid: i32,
 }
 
 #[derive(Deserialize)]
@@ -15,19 +34,26 @@ pub struct LoginUser {
     pub password: String,
 }
 
-#[derive(Deserialize)]
-pub struct LogoutUser {
-    pub user_id: String,
+#[derive(Serialize)]
+pub struct ResponseMessage {
+    pub message: String,
 }
 
+// Body of POST /api/auth/token, selected by "grant_type".
 #[derive(Deserialize)]
-pub struct RefreshUser {
-    pub user_id: String,
+#[serde(tag = "grant_type", rename_all = "snake_case")]
+pub enum TokenRequest {
+    Password { username: String, password: String },
+    RefreshToken { refresh_token: String },
 }
 
 #[derive(Serialize)]
-pub struct ResponseMessage {
-    pub message: String,
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    // Seconds until the access token expires.
+    pub expires_in: i64,
+    pub refresh_token: String,
 }
 
 impl validator::Validate for LoginUser {
@@ -60,24 +86,3 @@ impl validator::Validate for LoginUser {
         }
     }
 }
-
-impl validator::Validate for LogoutUser {
-    fn validate(&self) -> Result<(), ValidationErrors> {
-        let mut errors = ValidationErrors::new();
-        if self.user_id.is_empty() {
-            errors.add(
-                "user id is empty", 
-                ValidationError::new(
-                    "User ID cannot be empty")
-                        .with_message(Cow::Borrowed("User ID cannot be empty.")
-                )
-            );
-        }
-
-        if errors.is_empty() {
-            Ok(())
-        } else {
-            Err(errors)
-        }
-    }
-}
\ No newline at end of file
@@ -0,0 +1,125 @@
+use std::borrow::Cow;
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::{ValidationError, ValidationErrors};
+
+pub const DIGEST_FREQUENCIES: [&str; 3] = ["off", "daily", "weekly"];
+
+// Columns of users that control digests.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct DigestPreferences {
+    // Shared with reminders, and changed through the reminder preferences.
+    pub time_zone: String,
+    pub digest_frequency: String,
+    // Local hour, 0 to 23.
+    pub digest_hour: u8,
+    // 1 = Monday to 7 = Sunday. Only used by weekly digests.
+    pub digest_weekday: u8,
+    pub next_digest_at: Option<DateTime<Local>>,
+}
+
+impl DigestPreferences {
+    pub fn time_zone(&self) -> Tz {
+        self.time_zone.parse().unwrap_or(Tz::UTC)
+    }
+
+    // How far back a digest sent at `at` looks for completed todos.
+    pub fn period_start(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        match self.digest_frequency.as_str() {
+            "weekly" => at - chrono::Duration::days(7),
+            _ => at - chrono::Duration::days(1),
+        }
+    }
+
+    // The first digest time after `after`, or None when digests are off.
+    pub fn next_digest_at(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let weekly = match self.digest_frequency.as_str() {
+            "daily" => false,
+            "weekly" => true,
+            _ => return None,
+        };
+
+        let tz = self.time_zone();
+        let today = after.with_timezone(&tz).date_naive();
+
+        (0..=8)
+            .filter_map(|days| today.checked_add_days(Days::new(days)))
+            .filter(|date| !weekly || date.weekday().number_from_monday() == u32::from(self.digest_weekday))
+            .filter_map(|date| local_time(&tz, date, u32::from(self.digest_hour)))
+            .find(|at| *at > after)
+    }
+}
+
+// The given local hour on `date` in UTC. An hour skipped by daylight saving becomes the
+// next one; one that happens twice is the first.
+pub fn local_time(tz: &Tz, date: NaiveDate, hour: u32) -> Option<DateTime<Utc>> {
+    let local = date.and_hms_opt(hour, 0, 0)?;
+
+    tz.from_local_datetime(&local)
+        .earliest()
+        .or_else(|| tz.from_local_datetime(&(local + chrono::Duration::hours(1))).earliest())
+        .map(|at| at.with_timezone(&Utc))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateDigestPreferences {
+    pub frequency: Option<String>,
+    pub hour: Option<u8>,
+    pub weekday: Option<u8>,
+}
+
+impl validator::Validate for UpdateDigestPreferences {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if let Some(frequency) = &self.frequency {
+            if !DIGEST_FREQUENCIES.contains(&frequency.as_str()) {
+                errors.add(
+                    "frequency",
+                    ValidationError::new(
+                        "unknown frequency")
+                        .with_message(Cow::Owned(format!("Unknown frequency '{}'. Use off, daily or weekly.", frequency))
+                    )
+                );
+            }
+        }
+
+        if self.hour.is_some_and(|hour| hour > 23) {
+            errors.add(
+                "hour",
+                ValidationError::new(
+                    "invalid hour")
+                    .with_message(Cow::Borrowed("hour must be between 0 and 23.")
+                )
+            );
+        }
+
+        if self.weekday.is_some_and(|weekday| !(1..=7).contains(&weekday)) {
+            errors.add(
+                "weekday",
+                ValidationError::new(
+                    "invalid weekday")
+                    .with_message(Cow::Borrowed("weekday must be between 1 (Monday) and 7 (Sunday).")
+                )
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+// Payload of digests.send jobs. Todos completed between the two are "recently completed";
+// overdue and due today are relative to the end.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SendDigest {
+    pub user_id: i32,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+}
@@ -0,0 +1,97 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::models::user::User;
+
+// A change pushed to the user's connected clients, e.g. "todo.created" with the todo as data.
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    // Increases with every event; clients pass the last one they saw to resume.
+    pub id: i64,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(skip)]
+    pub user_id: i32,
+    pub data: serde_json::Value,
+    pub occurred_at: DateTime<Local>,
+}
+
+// Row of the events table. The data column holds the event's JSON.
+#[derive(Debug, FromRow)]
+pub struct EventRecord {
+    pub id: i64,
+    pub user_id: i32,
+    #[sqlx(rename = "type")]
+    pub kind: String,
+    pub data: String,
+    pub created_at: DateTime<Local>,
+}
+
+impl From<EventRecord> for Event {
+    fn from(record: EventRecord) -> Self {
+        Self {
+            id: record.id,
+            kind: record.kind,
+            user_id: record.user_id,
+            data: serde_json::from_str(&record.data).unwrap_or_default(),
+            occurred_at: record.created_at,
+        }
+    }
+}
+
+// Data of "*.deleted" events.
+#[derive(Debug, Serialize)]
+pub struct DeletedResource {
+    pub id: i32,
+}
+
+// Data of "user.created" events. Never the password hash.
+#[derive(Debug, Serialize)]
+pub struct CreatedAccount {
+    pub id: i32,
+    pub username: String,
+    pub email: String,
+    pub created_at: DateTime<Local>,
+}
+
+impl From<&User> for CreatedAccount {
+    fn from(user: &User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username.clone(),
+            email: user.email.clone(),
+            created_at: user.created_at,
+        }
+    }
+}
+
+// Data of "user.updated" events. Only the names of the changed fields, never their values.
+#[derive(Debug, Serialize)]
+pub struct UpdatedAccount {
+    pub id: i32,
+    pub fields: Vec<String>,
+}
+
+// Messages of the WebSocket protocol other than events.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlMessage {
+    // Sent once after connecting. Resume from last_event_id after a disconnect.
+    Ready { last_event_id: i64 },
+    // Events were missed and cannot be replayed: fetch the todos again.
+    Resync { last_event_id: i64 },
+    Pong,
+}
+
+// Messages clients may send. Browsers cannot send ping frames, so this is the heartbeat for them.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Ping,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResumeQuery {
+    pub last_event_id: Option<i64>,
+}
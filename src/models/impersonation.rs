@@ -0,0 +1,72 @@
+use std::borrow::Cow;
+
+use chrono::{
+    DateTime,
+    Local
+};
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::{ValidationError, ValidationErrors};
+
+// Audit record of an admin acting as another user. Started at created_at, stopped at
+// ended_at, by the admin themselves or another admin (ended_by). A session that was never
+// stopped ends at expires_at.
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct ImpersonationSession {
+    pub id: i32,
+    pub admin_id: i32,
+    pub user_id: i32,
+    pub reason: String,
+    pub ip_address: String,
+    pub expires_at: DateTime<Local>,
+    pub ended_at: Option<DateTime<Local>>,
+    pub ended_by: Option<i32>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartImpersonation {
+    // Why support needs to see the account, e.g. a ticket number.
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImpersonationResponse {
+    pub access_token: String,
+    pub token_type: String,
+    // Seconds until the access token expires. There is no refresh token.
+    pub expires_in: i64,
+    pub session_id: i32,
+    pub user_id: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImpersonationFilter {
+    pub admin_id: Option<i32>,
+    pub user_id: Option<i32>,
+    pub limit: Option<u32>,
+}
+
+impl validator::Validate for StartImpersonation {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if self.reason.trim().is_empty() || self.reason.len() > 255 {
+            errors.add(
+                "reason",
+                ValidationError::new(
+                    "invalid reason")
+                    .with_message(Cow::Borrowed("A reason of at most 255 characters is required.")
+                )
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
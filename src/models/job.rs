@@ -0,0 +1,41 @@
+use chrono::{DateTime, Local};
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::FromRow;
+
+// Status is "pending" until a worker picks the job up, "running" while it does, and then
+// "completed", or "dead" once every attempt has failed.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Job {
+    pub id: i64,
+    pub kind: String,
+    // JSON, read with `Job::payload`.
+    pub payload: String,
+    pub unique_key: Option<String>,
+    pub status: String,
+    // Including the one in progress.
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: DateTime<Local>,
+    pub locked_by: Option<String>,
+    pub locked_until: Option<DateTime<Local>>,
+    pub last_error: Option<String>,
+    pub finished_at: Option<DateTime<Local>>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+impl Job {
+    pub fn payload<T: DeserializeOwned>(&self) -> Result<T, String> {
+        serde_json::from_str(&self.payload).map_err(|e| format!("Invalid {} payload: {}", self.kind, e))
+    }
+}
+
+#[derive(Debug, FromRow)]
+pub struct JobSchedule {
+    pub name: String,
+    pub cron: String,
+    pub next_run_at: DateTime<Local>,
+    pub last_run_at: Option<DateTime<Local>>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
@@ -0,0 +1,38 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::ValidationErrors;
+
+use super::workspace::validate_name;
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct List {
+    pub id: i32,
+    pub workspace_id: i32,
+    pub name: String,
+    pub created_by: Option<i32>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateList {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateList {
+    pub name: String,
+}
+
+impl validator::Validate for CreateList {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        validate_name(&self.name)
+    }
+}
+
+impl validator::Validate for UpdateList {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        validate_name(&self.name)
+    }
+}
@@ -0,0 +1,24 @@
+use chrono::{
+    DateTime,
+    Local
+};
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct LoginAttempt {
+    pub id: i32,
+    pub username: String,
+    pub ip_address: String,
+    pub succeeded: bool,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Local>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginAttemptFilter {
+    pub username: Option<String>,
+    pub ip_address: Option<String>,
+    pub limit: Option<u32>,
+}
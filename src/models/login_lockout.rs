@@ -0,0 +1,19 @@
+use chrono::{
+    DateTime,
+    Local
+};
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct LoginLockout {
+    pub id: i32,
+    // "user:<username>" or "ip:<address>"
+    pub lock_key: String,
+    pub failed_attempts: i32,
+    pub locked_until: Option<DateTime<Local>>,
+    pub last_failed_at: DateTime<Local>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
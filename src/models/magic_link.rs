@@ -0,0 +1,58 @@
+use std::borrow::Cow;
+
+use chrono::{
+    DateTime,
+    Local
+};
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::{ValidationError, ValidationErrors};
+
+// Stored like a refresh token, except that only the hash of the token is kept.
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct MagicLinkToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub token_hash: String,
+    pub expires_at: DateTime<Local>,
+    pub used_at: Option<DateTime<Local>>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RequestMagicLink {
+    pub email: String,
+}
+
+// Query of the link in the email.
+#[derive(Debug, Deserialize)]
+pub struct MagicLinkCallback {
+    pub token: String,
+    // Unix time in seconds.
+    pub expires: i64,
+    pub signature: String,
+}
+
+impl validator::Validate for RequestMagicLink {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if self.email.is_empty() || !self.email.contains('@') {
+            errors.add(
+                "email",
+                ValidationError::new(
+                    "invalid email")
+                    .with_message(Cow::Borrowed("A valid email address is required.")
+                )
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
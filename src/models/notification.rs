@@ -0,0 +1,49 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize, Serializer};
+use sqlx::FromRow;
+
+// Something the user is told about in the app, e.g. a reminder that fired.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Notification {
+    pub id: i64,
+    pub user_id: i32,
+    // What it is about, e.g. "todo.reminder" or "auth.new_device".
+    pub kind: String,
+    pub title: String,
+    pub body: String,
+    // JSON with the ids a client needs to link to the subject, if any.
+    #[serde(serialize_with = "as_json")]
+    pub data: Option<String>,
+    pub read_at: Option<DateTime<Local>>,
+    pub created_at: DateTime<Local>
+}
+
+// Sends the stored JSON as JSON rather than as a string.
+fn as_json<S: Serializer>(data: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    data.as_deref()
+        .and_then(|data| serde_json::from_str::<serde_json::Value>(data).ok())
+        .serialize(serializer)
+}
+
+// GET /api/notifications?unread=true&limit=20&before=<id>
+// Newest first. Pass the previous page's next_before to get the one after it.
+#[derive(Debug, Deserialize)]
+pub struct NotificationFilter {
+    pub unread: Option<bool>,
+    pub limit: Option<u32>,
+    pub before: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationPage {
+    pub notifications: Vec<Notification>,
+    pub unread_count: i64,
+    // None on the last page.
+    pub next_before: Option<i64>,
+}
+
+// Also pushed as "notification.unread_count" whenever it changes, so every open tab agrees.
+#[derive(Debug, Serialize)]
+pub struct UnreadCount {
+    pub unread_count: i64,
+}
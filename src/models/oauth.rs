@@ -0,0 +1,148 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct OAuthAuthorizationCode {
+    pub id: i32,
+    pub code_hash: String,
+    // oauth_clients.id, not the public client_id.
+    pub client_id: i32,
+    pub user_id: i32,
+    pub redirect_uri: String,
+    pub scopes: String,
+    // BASE64URL(SHA-256(code_verifier)). Only S256 is supported.
+    pub code_challenge: String,
+    pub expires_at: DateTime<Local>,
+    pub created_at: DateTime<Local>
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct OAuthToken {
+    pub id: i32,
+    pub token_hash: String,
+    // "access" or "refresh"
+    pub token_type: String,
+    pub family: String,
+    // oauth_clients.id, not the public client_id.
+    pub client_id: i32,
+    pub user_id: i32,
+    pub scopes: String,
+    pub expires_at: DateTime<Local>,
+    pub revoked_at: Option<DateTime<Local>>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+// Added to the request extensions by check_token_auth when the caller used an OAuth access
+// token, next to the usual Claims. Its absence means a first-party session.
+#[derive(Debug, Clone)]
+pub struct OAuthAccess {
+    // oauth_clients.id
+    pub client_id: i32,
+    pub scopes: String,
+}
+
+impl OAuthAccess {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.split_whitespace().any(|s| s == scope)
+    }
+}
+
+// Query of GET /api/oauth/authorize, and repeated in the body of the consent POST.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizationRequest {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    // Space separated. Defaults to everything the client is allowed.
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+// What the consent screen shows the user.
+#[derive(Debug, Serialize)]
+pub struct AuthorizationPrompt {
+    pub client_id: String,
+    pub client_name: String,
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    pub state: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConsentRequest {
+    #[serde(flatten)]
+    pub request: AuthorizationRequest,
+    pub approve: bool,
+}
+
+// Where the consent screen should send the browser next: the client's redirect_uri with
+// either a code or an error attached.
+#[derive(Debug, Serialize)]
+pub struct ConsentResponse {
+    pub redirect_to: String,
+}
+
+// Form body of POST /api/oauth/token. Which fields are needed depends on grant_type.
+// Client credentials can also come in an HTTP Basic Authorization header.
+#[derive(Debug, Deserialize)]
+pub struct OAuthTokenRequest {
+    pub grant_type: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+    pub scope: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OAuthTokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    // Seconds until the access token expires.
+    pub expires_in: i64,
+    pub refresh_token: String,
+    pub scope: String,
+}
+
+// Form body of POST /api/oauth/introspect (RFC 7662) and POST /api/oauth/revoke (RFC 7009).
+#[derive(Debug, Deserialize)]
+pub struct TokenLookupRequest {
+    pub token: String,
+    // "access_token" or "refresh_token". Only a hint: every token is looked up by its hash anyway.
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+// RFC 7662 response. Inactive tokens are answered with {"active": false} and nothing else.
+#[derive(Debug, Default, Serialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+}
+
+// Error body defined by RFC 6749 section 5.2, e.g. {"error": "invalid_grant", ...}.
+#[derive(Debug, Serialize)]
+pub struct OAuthError {
+    pub error: String,
+    pub error_description: String,
+}
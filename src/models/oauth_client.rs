@@ -0,0 +1,193 @@
+use std::borrow::Cow;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use url::Url;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::models::api_key::validate_scopes;
+
+// A third-party application that may act on behalf of users through OAuth.
+// Scopes are the same ones API keys use (see API_KEY_SCOPES).
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct OAuthClient {
+    pub id: i32,
+    // Public identifier, e.g. "tdc_ab12...". Sent by the client in every OAuth request.
+    pub client_id: String,
+    // SHA-256 of the client secret. NULL for public clients (mobile and single-page apps),
+    // which cannot keep a secret and rely on PKCE alone.
+    #[serde(skip_serializing)]
+    pub client_secret_hash: Option<String>,
+    pub client_name: String,
+    pub contact_email: String,
+    // Space separated. The redirect_uri of an authorization request must match one exactly.
+    pub redirect_uris: String,
+    // Space separated. The most a user can grant this client.
+    pub scopes: String,
+    pub is_active: bool,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+impl OAuthClient {
+    pub fn is_confidential(&self) -> bool {
+        self.client_secret_hash.is_some()
+    }
+
+    pub fn allows_redirect_uri(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris.split_whitespace().any(|uri| uri == redirect_uri)
+    }
+
+    pub fn allows_scope(&self, scope: &str) -> bool {
+        self.scopes.split_whitespace().any(|s| s == scope)
+    }
+}
+
+// Returned once from oauth_clients_create. The secret cannot be retrieved again.
+#[derive(Debug, Serialize)]
+pub struct CreatedOAuthClient {
+    #[serde(flatten)]
+    pub client: OAuthClient,
+    // None for public clients.
+    pub client_secret: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateOAuthClient {
+    pub client_name: String,
+    pub contact_email: String,
+    pub redirect_uris: Vec<String>,
+    pub scopes: Vec<String>,
+    // Public clients get no secret. Defaults to a confidential client.
+    #[serde(default = "default_confidential")]
+    pub confidential: bool
+}
+
+fn default_confidential() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateOAuthClient {
+    pub client_name: Option<String>,
+    pub contact_email: Option<String>,
+    pub redirect_uris: Option<Vec<String>>,
+    pub scopes: Option<Vec<String>>,
+    pub is_active: Option<bool>
+}
+
+#[derive(Debug)]
+pub enum FieldValue {
+    ClientName(Option<String>),
+    ContactEmail(Option<String>),
+    RedirectUris(Option<Vec<String>>),
+    Scopes(Option<Vec<String>>),
+    IsActive(Option<bool>),
+}
+
+impl IntoIterator for UpdateOAuthClient {
+    type Item = (&'static str, FieldValue);
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        vec![
+            ("client_name", FieldValue::ClientName(self.client_name)),
+            ("contact_email", FieldValue::ContactEmail(self.contact_email)),
+            ("redirect_uris", FieldValue::RedirectUris(self.redirect_uris)),
+            ("scopes", FieldValue::Scopes(self.scopes)),
+            ("is_active", FieldValue::IsActive(self.is_active))
+        ].into_iter()
+    }
+}
+
+// Redirect URIs must be absolute, without a fragment, and use https unless they point
+// at the local machine (native apps and development).
+pub fn validate_redirect_uris(redirect_uris: &[String], errors: &mut ValidationErrors) {
+    if redirect_uris.is_empty() {
+        errors.add(
+            "redirect_uris",
+            ValidationError::new(
+                "redirect uris are required")
+                .with_message(Cow::Borrowed("At least one redirect URI is required.")
+            )
+        );
+    }
+
+    for redirect_uri in redirect_uris {
+        let valid = Url::parse(redirect_uri).is_ok_and(|url| {
+            let local = matches!(url.host_str(), Some("localhost") | Some("127.0.0.1") | Some("[::1]"));
+            url.fragment().is_none() && (url.scheme() == "https" || (url.scheme() == "http" && local))
+        });
+
+        if !valid {
+            errors.add(
+                "redirect_uris",
+                ValidationError::new(
+                    "invalid redirect uri")
+                    .with_message(Cow::Owned(format!("Invalid redirect URI '{}'.", redirect_uri))
+                )
+            );
+        }
+    }
+}
+
+impl validator::Validate for CreateOAuthClient {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if self.client_name.is_empty() {
+            errors.add(
+                "client_name",
+                ValidationError::new(
+                    "client name is required")
+                    .with_message(Cow::Borrowed("Client Name is required.")
+                )
+            );
+        }
+        if self.contact_email.is_empty() {
+            errors.add(
+                "contact_email",
+                ValidationError::new(
+                    "client email is required")
+                    .with_message(Cow::Borrowed("Client Email is required.")
+                )
+            );
+        }
+        if self.scopes.is_empty() {
+            errors.add(
+                "scopes",
+                ValidationError::new(
+                    "scopes are required")
+                    .with_message(Cow::Borrowed("At least one scope is required.")
+                )
+            );
+        }
+        validate_scopes(&self.scopes, &mut errors);
+        validate_redirect_uris(&self.redirect_uris, &mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl validator::Validate for UpdateOAuthClient {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if let Some(scopes) = &self.scopes {
+            validate_scopes(scopes, &mut errors);
+        }
+        if let Some(redirect_uris) = &self.redirect_uris {
+            validate_redirect_uris(redirect_uris, &mut errors);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
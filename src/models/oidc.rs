@@ -0,0 +1,62 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+// Links a local user to an account at an external OpenID Connect provider.
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct UserIdentity {
+    pub id: i32,
+    pub user_id: i32,
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub last_login_at: Option<DateTime<Local>>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+// What we remember between sending the browser to the provider and it coming back.
+#[derive(Debug, FromRow)]
+pub struct OidcLoginState {
+    pub id: i32,
+    pub state: String,
+    pub provider: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub expires_at: DateTime<Local>,
+    pub created_at: DateTime<Local>
+}
+
+// Query the provider redirects back with. Either code or error is set.
+#[derive(Debug, Deserialize)]
+pub struct OidcCallback {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+// The parts of the provider's /.well-known/openid-configuration we use.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+// Response of the provider's token endpoint. Only the ID token matters to us.
+#[derive(Debug, Deserialize)]
+pub struct ProviderTokenResponse {
+    pub id_token: Option<String>,
+}
+
+// ID token claims we rely on. iss, aud and exp are checked while decoding.
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+}
@@ -0,0 +1,50 @@
+use std::borrow::Cow;
+
+use chrono::{
+    DateTime,
+    Local
+};
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::{ValidationError, ValidationErrors};
+
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct PhoneVerification {
+    pub id: i32,
+    pub user_id: i32,
+    pub phone_number: String,
+    #[serde(skip_serializing)]
+    pub code_hash: String,
+    pub attempts: i32,
+    pub expires_at: DateTime<Local>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfirmPhoneVerification {
+    pub code: String,
+}
+
+impl validator::Validate for ConfirmPhoneVerification {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if self.code.len() != 6 || !self.code.chars().all(|c| c.is_ascii_digit()) {
+            errors.add(
+                "code",
+                ValidationError::new(
+                    "Code must be 6 digits")
+                    .with_message(Cow::Borrowed("Code must be 6 digits.")
+                )
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
@@ -15,6 +15,8 @@ pub struct RefreshToken {
     pub user_id: i32,
     pub token: String,
     pub expires_at: DateTime<Local>,
+    // The workspace the session was switched to; None is the user's personal one.
+    pub workspace_id: Option<i32>,
     pub created_at: DateTime<Local>,
     pub updated_at: DateTime<Local>
 }
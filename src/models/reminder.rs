@@ -0,0 +1,181 @@
+use std::borrow::Cow;
+
+use chrono::{DateTime, Days, Local, LocalResult, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::{models::todo::Todo, utils::input_validation::nullable};
+
+// Longest lead time for due-date reminders: a week.
+const MAX_DUE_REMINDER_MINUTES: i32 = 7 * 24 * 60;
+
+// Ways a reminder can reach the user.
+pub const REMINDER_CHANNELS: [&str; 3] = ["email", "webhook", "in_app"];
+
+// Columns of users that control reminders.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ReminderPreferences {
+    // IANA name, e.g. "Asia/Dhaka".
+    pub time_zone: String,
+    // Local times. A window may wrap past midnight, e.g. 22:00 to 07:00.
+    pub quiet_hours_start: Option<NaiveTime>,
+    pub quiet_hours_end: Option<NaiveTime>,
+    // Space separated.
+    pub reminder_channels: String,
+    // Lead time for todos that only have a due date; None means they are not reminded.
+    pub due_reminder_minutes: Option<i32>,
+}
+
+impl ReminderPreferences {
+    // When the todo is due to be reminded: its own remind_at, or else the lead time before
+    // its due date.
+    pub fn reminder_time(&self, todo: &Todo) -> Option<DateTime<Local>> {
+        todo.remind_at.or_else(|| {
+            let minutes = self.due_reminder_minutes?;
+            todo.due_at.map(|due_at| due_at - chrono::Duration::minutes(minutes.into()))
+        })
+    }
+
+    pub fn time_zone(&self) -> Tz {
+        self.time_zone.parse().unwrap_or(Tz::UTC)
+    }
+
+    pub fn channels(&self) -> impl Iterator<Item = &str> {
+        self.reminder_channels.split_whitespace()
+    }
+
+    // The first moment at or after `at` that is outside the user's quiet hours.
+    pub fn deliverable_at(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let (Some(start), Some(end)) = (self.quiet_hours_start, self.quiet_hours_end) else {
+            return at;
+        };
+        if start == end {
+            return at;
+        }
+
+        let tz = self.time_zone();
+        let local = at.with_timezone(&tz);
+        let time = local.time();
+        let quiet = if start < end { time >= start && time < end } else { time >= start || time < end };
+        if !quiet {
+            return at;
+        }
+
+        // Quiet hours end later today, or tomorrow if the window wrapped past midnight.
+        let date = if time < end { local.date_naive() } else { local.date_naive() + Days::new(1) };
+        let end_local = date.and_time(end);
+
+        match tz.from_local_datetime(&end_local) {
+            LocalResult::Single(end) | LocalResult::Ambiguous(end, _) => end.with_timezone(&Utc),
+            // The end falls in a daylight saving gap: the clocks skip to an hour later.
+            LocalResult::None => tz
+                .from_local_datetime(&(end_local + chrono::Duration::hours(1)))
+                .earliest()
+                .map_or(at, |end| end.with_timezone(&Utc)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateReminderPreferences {
+    pub time_zone: Option<String>,
+    // "22:00", or null to turn quiet hours off. Start and end are set together.
+    #[serde(default, deserialize_with = "nullable")]
+    pub quiet_hours_start: Option<Option<NaiveTime>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub quiet_hours_end: Option<Option<NaiveTime>>,
+    pub channels: Option<Vec<String>>,
+    // Minutes before the due date, or null to only remind at remind_at.
+    #[serde(default, deserialize_with = "nullable")]
+    pub due_reminder_minutes: Option<Option<i32>>,
+}
+
+impl validator::Validate for UpdateReminderPreferences {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if let Some(time_zone) = &self.time_zone {
+            if time_zone.parse::<Tz>().is_err() {
+                errors.add(
+                    "time_zone",
+                    ValidationError::new(
+                        "unknown time zone")
+                        .with_message(Cow::Owned(format!("Unknown time zone '{}'. Use an IANA name such as Europe/London.", time_zone))
+                    )
+                );
+            }
+        }
+
+        if self.quiet_hours_start.map(|t| t.is_some()) != self.quiet_hours_end.map(|t| t.is_some()) {
+            errors.add(
+                "quiet_hours",
+                ValidationError::new(
+                    "incomplete quiet hours")
+                    .with_message(Cow::Borrowed("quiet_hours_start and quiet_hours_end must be set or cleared together.")
+                )
+            );
+        }
+
+        if self.due_reminder_minutes.flatten().is_some_and(|minutes| !(0..=MAX_DUE_REMINDER_MINUTES).contains(&minutes)) {
+            errors.add(
+                "due_reminder_minutes",
+                ValidationError::new(
+                    "invalid lead time")
+                    .with_message(Cow::Owned(format!("due_reminder_minutes must be between 0 and {}.", MAX_DUE_REMINDER_MINUTES))
+                )
+            );
+        }
+
+        if let Some(channels) = &self.channels {
+            for channel in channels {
+                if !REMINDER_CHANNELS.contains(&channel.as_str()) {
+                    errors.add(
+                        "channels",
+                        ValidationError::new(
+                            "unknown channel")
+                            .with_message(Cow::Owned(format!("Unknown channel '{}'.", channel))
+                        )
+                    );
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+// Row of todo_reminders. Status is "pending" until every channel has it ("sent"), or
+// "skipped" if the todo was done or rescheduled before the reminder went out.
+#[derive(Debug, FromRow)]
+pub struct TodoReminder {
+    pub id: i64,
+    pub todo_id: i32,
+    pub user_id: i32,
+    pub remind_at: DateTime<Local>,
+    pub status: String,
+    // Space separated.
+    pub sent_channels: String,
+    pub deliver_at: DateTime<Local>,
+    pub sent_at: Option<DateTime<Local>>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+// Payload of reminders.deliver jobs.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeliverReminder {
+    pub reminder_id: i64,
+}
+
+// Data of "todo.reminder" webhook events.
+#[derive(Debug, Serialize)]
+pub struct ReminderEvent<'a, T: Serialize> {
+    pub reminder_id: i64,
+    pub todo: &'a T,
+}
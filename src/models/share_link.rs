@@ -0,0 +1,104 @@
+use std::borrow::Cow;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize, Serializer};
+use sqlx::FromRow;
+use validator::{ValidationError, ValidationErrors};
+
+// A public, read-only link to a list or a single todo; exactly one of list_id and todo_id
+// is set. Anyone with the token can view it until it expires or is revoked.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ShareLink {
+    pub id: i64,
+    pub workspace_id: i32,
+    pub list_id: Option<i32>,
+    pub todo_id: Option<i32>,
+    // Visible start of the token, e.g. "shr_ab12cd34", so links can be told apart.
+    pub token_prefix: String,
+    // SHA-256 of the full token. The token itself is never stored.
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    // Only whether there is a password is ever shown.
+    #[serde(rename = "password_protected", serialize_with = "serialize_is_some")]
+    pub password_hash: Option<String>,
+    // NULL means the link never expires.
+    pub expires_at: Option<DateTime<Local>>,
+    pub revoked_at: Option<DateTime<Local>>,
+    pub view_count: i64,
+    pub last_viewed_at: Option<DateTime<Local>>,
+    pub created_by: Option<i32>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+fn serialize_is_some<S: Serializer>(value: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bool(value.is_some())
+}
+
+// Returned once, on creation. The token cannot be retrieved again.
+#[derive(Debug, Serialize)]
+pub struct CreatedShareLink {
+    #[serde(flatten)]
+    pub share_link: ShareLink,
+    pub token: String,
+    // Where the link is served, e.g. "/api/public/shr_...".
+    pub path: String,
+}
+
+// POST /api/lists/:id/share_links or /api/todos/:id/share_links
+// {"password": "optional", "expires_in_days": 7}
+#[derive(Debug, Default, Deserialize)]
+pub struct CreateShareLink {
+    // Viewers then send it in the X-Share-Password header.
+    pub password: Option<String>,
+    // Leave out for a link that never expires.
+    pub expires_in_days: Option<i64>
+}
+
+// A todo as the public sees it, without any user or workspace ids.
+#[derive(Debug, FromRow, Serialize)]
+pub struct PublicTodo {
+    pub description: String,
+    pub done: bool,
+    pub due_at: Option<DateTime<Local>>,
+    pub completed_at: Option<DateTime<Local>>
+}
+
+// GET /api/public/:token
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SharedContent {
+    List { name: String, todos: Vec<PublicTodo> },
+    Todo(PublicTodo),
+}
+
+impl validator::Validate for CreateShareLink {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if self.password.as_ref().is_some_and(|password| password.is_empty() || password.chars().count() > 128) {
+            errors.add(
+                "password",
+                ValidationError::new(
+                    "invalid password")
+                    .with_message(Cow::Borrowed("password must be between 1 and 128 characters.")
+                )
+            );
+        }
+        if self.expires_in_days.is_some_and(|days| days <= 0) {
+            errors.add(
+                "expires_in_days",
+                ValidationError::new(
+                    "must be positive")
+                    .with_message(Cow::Borrowed("Must be greater than 0.")
+                )
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
@@ -9,12 +9,21 @@ use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 use validator::{ValidationError, ValidationErrors};
 
+use crate::utils::input_validation::nullable;
+
 #[derive(FromRow, Debug, Serialize, Deserialize)]
 pub struct Todo {
 	pub id: i32,
     pub user_id: i32,
+	pub workspace_id: i32,
+	pub list_id: Option<i32>,
 	pub description: String,
 	pub done: bool,
+	pub due_at: Option<DateTime<Local>>,
+	// When the owner is reminded. Each value fires once; set a new one to be reminded again.
+	pub remind_at: Option<DateTime<Local>>,
+	// When it was last marked done; cleared when it is undone.
+	pub completed_at: Option<DateTime<Local>>,
 	pub created_at: DateTime<Local>,
     pub updated_at: DateTime<Local>
 }
@@ -22,20 +31,34 @@ pub struct Todo {
 #[derive(FromRow, Debug, Serialize, Deserialize)]
 pub struct CreateTodo {
     pub user_id: i32,
+	// Must be a list of the current workspace.
+	pub list_id: Option<i32>,
 	pub description: String,
-	pub done: bool
+	pub done: bool,
+	pub due_at: Option<DateTime<Local>>,
+	pub remind_at: Option<DateTime<Local>>
 }
 
 #[derive(FromRow, Debug, Serialize, Deserialize, Clone)]
 pub struct UpdateTodo {
     pub description: Option<String>,
-    pub done: Option<bool>
+    pub done: Option<bool>,
+    // Absent leaves the date alone, null clears it.
+    #[serde(default, deserialize_with = "nullable")]
+    pub due_at: Option<Option<DateTime<Local>>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub remind_at: Option<Option<DateTime<Local>>>,
+    // Null takes the todo off its list.
+    #[serde(default, deserialize_with = "nullable")]
+    pub list_id: Option<Option<i32>>
 }
 
 #[derive(Debug)]
 pub enum FieldValue {
 	Description(Option<String>),
 	Done(Option<bool>),
+	Timestamp(Option<Option<DateTime<Local>>>),
+	Id(Option<Option<i32>>),
 }
 
 impl IntoIterator for UpdateTodo {
@@ -46,6 +69,9 @@ impl IntoIterator for UpdateTodo {
 		vec![
 			("description", FieldValue::Description(self.description)),
 			("done", FieldValue::Done(self.done)),
+			("due_at", FieldValue::Timestamp(self.due_at)),
+			("remind_at", FieldValue::Timestamp(self.remind_at)),
+			("list_id", FieldValue::Id(self.list_id)),
 		].into_iter()
 	}
 }
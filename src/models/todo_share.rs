@@ -0,0 +1,130 @@
+use std::{borrow::Cow, fmt, str::FromStr};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::{ValidationError, ValidationErrors};
+
+use super::todo::Todo;
+
+// What a user may do with a todo. Each level includes the ones below it: viewers read,
+// editors also change it, and owners also delete it and manage who it is shared with.
+// The user a todo belongs to is always its owner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TodoRole {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+impl TodoRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoRole::Viewer => "viewer",
+            TodoRole::Editor => "editor",
+            TodoRole::Owner => "owner",
+        }
+    }
+}
+
+impl FromStr for TodoRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(TodoRole::Viewer),
+            "editor" => Ok(TodoRole::Editor),
+            "owner" => Ok(TodoRole::Owner),
+            _ => Err(format!("Unknown role '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for TodoRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Status is "pending" until the invitee accepts or declines. Only accepted shares grant
+// access.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct TodoShare {
+    pub id: i64,
+    pub todo_id: i32,
+    pub user_id: i32,
+    pub role: String,
+    pub status: String,
+    pub invited_by: i32,
+    pub responded_at: Option<DateTime<Local>>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+// A share with the usernames of the invitee and the inviter, for listings.
+#[derive(Debug, FromRow, Serialize)]
+pub struct TodoShareDetails {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub share: TodoShare,
+    pub username: String,
+    pub invited_by_username: String,
+}
+
+// An invitation as the invitee sees it.
+#[derive(Debug, FromRow, Serialize)]
+pub struct TodoInvitation {
+    pub id: i64,
+    pub todo_id: i32,
+    pub description: String,
+    pub due_at: Option<DateTime<Local>>,
+    pub role: String,
+    pub invited_by_username: String,
+    pub created_at: DateTime<Local>
+}
+
+// A todo shared with the caller, and what they may do with it.
+#[derive(Debug, FromRow, Serialize)]
+pub struct SharedTodo {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub todo: Todo,
+    pub role: String,
+    pub owner_username: String,
+}
+
+// POST /api/todos/:id/shares
+// {"invitee": "alice" or "alice@example.com", "role": "editor"}
+#[derive(Debug, Deserialize)]
+pub struct CreateTodoShare {
+    pub invitee: String,
+    pub role: TodoRole,
+}
+
+impl validator::Validate for CreateTodoShare {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if self.invitee.trim().is_empty() {
+            errors.add(
+                "invitee",
+                ValidationError::new(
+                    "invitee required")
+                    .with_message(Cow::Borrowed("invitee must be a username or an email address.")
+                )
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateTodoShare {
+    pub role: TodoRole,
+}
@@ -16,6 +16,7 @@ pub struct User {
     pub email: String,
     pub phone_number: Option<String>,
     pub phone_number_verified: bool,
+    pub is_admin: bool,
     pub created_at: chrono::DateTime<Local>,
     pub updated_at: chrono::DateTime<Local>
 }
@@ -43,7 +44,7 @@ pub struct UpdateUser {
     pub password: Option<String>, // Allow password update (handle hashing separately)
     pub email: Option<String>,
     pub phone_number: Option<String>,
-    pub phone_number_verified: Option<bool>,
+    // phone_number_verified is deliberately absent: it is only set through phone verification.
 }
 
 #[derive(Debug)]
@@ -52,8 +53,6 @@ pub enum FieldValue {
     Password(Option<String>),
     Email(Option<String>),
     PhoneNumber(Option<String>),
-    PhoneNumberVerified(Option<bool>),
-
 }
 
 impl IntoIterator for UpdateUser {
@@ -65,8 +64,7 @@ impl IntoIterator for UpdateUser {
             ("username", FieldValue::Username(self.username)),
             ("password", FieldValue::Password(self.password)),
             ("email", FieldValue::Email(self.email)),
-            ("phone_number", FieldValue::PhoneNumber(self.phone_number)),
-            ("phone_number_verified", FieldValue::PhoneNumberVerified(self.phone_number_verified))
+            ("phone_number", FieldValue::PhoneNumber(self.phone_number))
         ].into_iter()
     }
 }
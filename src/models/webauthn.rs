@@ -0,0 +1,143 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct WebAuthnCredential {
+    pub id: i32,
+    pub user_id: i32,
+    pub credential_id: String,
+    #[serde(skip_serializing)]
+    pub public_key: String,
+    pub algorithm: i32,
+    pub sign_count: i64,
+    pub name: String,
+    pub transports: Option<String>,
+    pub last_used_at: Option<DateTime<Local>>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+#[derive(Debug, FromRow)]
+pub struct WebAuthnChallenge {
+    pub id: i32,
+    pub challenge: String,
+    pub ceremony: String,
+    pub user_id: Option<i32>,
+    pub expires_at: DateTime<Local>,
+    pub created_at: DateTime<Local>
+}
+
+// The following mirror the WebAuthn JSON the browser API works with. Binary values are
+// base64url without padding.
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebAuthnUser {
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CredentialParameters {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub alg: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub transports: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: String,
+    pub user_verification: String,
+}
+
+// Input for navigator.credentials.create({ publicKey }).
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreationOptions {
+    pub challenge: String,
+    pub rp: RelyingParty,
+    pub user: WebAuthnUser,
+    pub pub_key_cred_params: Vec<CredentialParameters>,
+    pub timeout: u32,
+    pub attestation: String,
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+    pub authenticator_selection: AuthenticatorSelection,
+}
+
+// Input for navigator.credentials.get({ publicKey }).
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub timeout: u32,
+    pub user_verification: String,
+    // Empty lets the authenticator offer any discoverable credential (passkey) for this site.
+    pub allow_credentials: Vec<CredentialDescriptor>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationResponse {
+    pub client_data_json: String,
+    pub attestation_object: String,
+    #[serde(default)]
+    pub transports: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResponse {
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+    pub user_handle: Option<String>,
+}
+
+// A PublicKeyCredential as serialised by the browser (or its toJSON()).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicKeyCredential<R> {
+    pub id: String,
+    pub raw_id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub response: R,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterCredential {
+    // Shown in the credential list, e.g. "YubiKey" or "MacBook". Defaults to "Passkey".
+    pub name: Option<String>,
+    pub credential: PublicKeyCredential<AttestationResponse>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LoginOptionsRequest {
+    // Leave out to log in with a discoverable credential.
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginCredential {
+    pub credential: PublicKeyCredential<AssertionResponse>,
+}
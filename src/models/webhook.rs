@@ -0,0 +1,219 @@
+use std::borrow::Cow;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::{ValidationError, ValidationErrors};
+
+// Events a webhook can subscribe to. "todo.*" or "*" subscribe to a group or to everything.
+pub const WEBHOOK_EVENT_TYPES: [&str; 10] = [
+    "list.created",
+    "list.updated",
+    "list.deleted",
+    "todo.created",
+    "todo.updated",
+    "todo.deleted",
+    "todo.reminder",
+    "user.created",
+    "user.updated",
+    "user.deleted",
+];
+
+// Owned by a user, who gets their own events, or by an API key, which gets every event its
+// read scopes cover (todos:read for todo.* and list.*, users:read for user.*).
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: i32,
+    pub user_id: Option<i32>,
+    pub api_key_id: Option<i32>,
+    pub url: String,
+    // Space separated.
+    pub event_types: String,
+    // Signs every delivery. Only shown when the webhook is created.
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub is_active: bool,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+impl Webhook {
+    pub fn subscribes_to(&self, event_type: &str) -> bool {
+        self.event_types.split_whitespace().any(|pattern| event_type_matches(pattern, event_type))
+    }
+}
+
+pub fn event_type_matches(pattern: &str, event_type: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => event_type.starts_with(prefix),
+        None => pattern == event_type,
+    }
+}
+
+// Returned once from webhooks_create. The secret cannot be retrieved again.
+#[derive(Debug, Serialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhook {
+    pub url: String,
+    pub event_types: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWebhook {
+    pub url: Option<String>,
+    pub event_types: Option<Vec<String>>,
+    pub is_active: Option<bool>,
+}
+
+// One event for one webhook. Status is "pending" until it is "delivered", or "dead" once
+// every attempt has failed.
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event_id: String,
+    pub event_type: String,
+    #[serde(skip_serializing)]
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Local>,
+    #[serde(skip_serializing)]
+    pub locked_until: Option<DateTime<Local>>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Local>>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct WebhookDeliveryAttempt {
+    pub id: i32,
+    pub delivery_id: i32,
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i32,
+    pub created_at: DateTime<Local>
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookDeliveryDetails {
+    #[serde(flatten)]
+    pub delivery: WebhookDelivery,
+    pub payload: serde_json::Value,
+    pub attempts_log: Vec<WebhookDeliveryAttempt>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookDeliveryFilter {
+    pub status: Option<String>,
+    pub limit: Option<u32>,
+}
+
+// Row of webhook_outbox.
+#[derive(Debug, FromRow)]
+pub struct WebhookOutboxEvent {
+    pub id: i64,
+    pub event_id: String,
+    pub event_type: String,
+    pub user_id: i32,
+    pub payload: String,
+    pub dispatched_at: Option<DateTime<Local>>,
+    pub created_at: DateTime<Local>
+}
+
+// The body of every delivery.
+#[derive(Debug, Serialize)]
+pub struct WebhookPayload<'a, T: Serialize> {
+    pub id: &'a str,
+    #[serde(rename = "type")]
+    pub event_type: &'a str,
+    pub created_at: DateTime<Local>,
+    pub data: &'a T,
+}
+
+fn validate_event_types(event_types: &[String], errors: &mut ValidationErrors) {
+    if event_types.is_empty() {
+        errors.add(
+            "event_types",
+            ValidationError::new(
+                "no event types")
+                .with_message(Cow::Borrowed("At least one event type is required.")
+            )
+        );
+    }
+
+    for event_type in event_types {
+        let known = WEBHOOK_EVENT_TYPES.iter().any(|known| event_type_matches(event_type, known));
+        let valid = known && (!event_type.contains('*') || event_type == "*" || event_type.ends_with(".*"));
+
+        if !valid {
+            errors.add(
+                "event_types",
+                ValidationError::new(
+                    "unknown event type")
+                    .with_message(Cow::Owned(format!("Unknown event type '{}'.", event_type))
+                )
+            );
+        }
+    }
+}
+
+impl validator::Validate for CreateWebhook {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if self.url.is_empty() || self.url.len() > 2048 {
+            errors.add(
+                "url",
+                ValidationError::new(
+                    "invalid url")
+                    .with_message(Cow::Borrowed("A URL of at most 2048 characters is required.")
+                )
+            );
+        }
+
+        validate_event_types(&self.event_types, &mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl validator::Validate for UpdateWebhook {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if let Some(url) = &self.url {
+            if url.is_empty() || url.len() > 2048 {
+                errors.add(
+                    "url",
+                    ValidationError::new(
+                        "invalid url")
+                        .with_message(Cow::Borrowed("A URL of at most 2048 characters is required.")
+                    )
+                );
+            }
+        }
+
+        if let Some(event_types) = &self.event_types {
+            validate_event_types(event_types, &mut errors);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
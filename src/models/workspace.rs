@@ -0,0 +1,179 @@
+use std::{borrow::Cow, fmt, str::FromStr};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::{ValidationError, ValidationErrors};
+
+// A member's standing in a workspace. Each level includes the ones below it: members work
+// with its lists and todos, admins also manage its members and everyone's todos, and owners
+// also rename or delete it and make other owners.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceRole {
+    Member,
+    Admin,
+    Owner,
+}
+
+impl WorkspaceRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkspaceRole::Member => "member",
+            WorkspaceRole::Admin => "admin",
+            WorkspaceRole::Owner => "owner",
+        }
+    }
+}
+
+impl FromStr for WorkspaceRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "member" => Ok(WorkspaceRole::Member),
+            "admin" => Ok(WorkspaceRole::Admin),
+            "owner" => Ok(WorkspaceRole::Owner),
+            _ => Err(format!("Unknown role '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for WorkspaceRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Workspace {
+    pub id: i32,
+    pub name: String,
+    // Set on the user's own workspace, which cannot be shared, left or deleted.
+    pub personal_user_id: Option<i32>,
+    pub created_by: Option<i32>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+// A workspace as one of its members sees it.
+#[derive(Debug, FromRow, Serialize)]
+pub struct WorkspaceMembership {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub workspace: Workspace,
+    pub role: String,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct WorkspaceMember {
+    pub user_id: i32,
+    pub username: String,
+    pub email: String,
+    pub role: String,
+    pub joined_at: DateTime<Local>
+}
+
+// Status is "pending" until the invitee accepts or declines.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct WorkspaceInvitation {
+    pub id: i64,
+    pub workspace_id: i32,
+    pub user_id: i32,
+    pub role: String,
+    pub status: String,
+    pub invited_by: i32,
+    pub responded_at: Option<DateTime<Local>>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>
+}
+
+// An invitation as the invitee sees it.
+#[derive(Debug, FromRow, Serialize)]
+pub struct PendingWorkspaceInvitation {
+    pub id: i64,
+    pub workspace_id: i32,
+    pub workspace_name: String,
+    pub role: String,
+    pub invited_by_username: String,
+    pub created_at: DateTime<Local>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWorkspace {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWorkspace {
+    pub name: String,
+}
+
+// POST /api/workspaces/:id/invitations
+// {"invitee": "alice" or "alice@example.com", "role": "member"}
+#[derive(Debug, Deserialize)]
+pub struct CreateWorkspaceInvitation {
+    pub invitee: String,
+    pub role: WorkspaceRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWorkspaceMember {
+    pub role: WorkspaceRole,
+}
+
+impl validator::Validate for CreateWorkspace {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        validate_name(&self.name)
+    }
+}
+
+impl validator::Validate for UpdateWorkspace {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        validate_name(&self.name)
+    }
+}
+
+impl validator::Validate for CreateWorkspaceInvitation {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if self.invitee.trim().is_empty() {
+            errors.add(
+                "invitee",
+                ValidationError::new(
+                    "invitee required")
+                    .with_message(Cow::Borrowed("invitee must be a username or an email address.")
+                )
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+// Workspace and list names.
+pub fn validate_name(name: &str) -> Result<(), ValidationErrors> {
+    let mut errors = ValidationErrors::new();
+    let length = name.trim().chars().count();
+
+    if length == 0 || length > 100 {
+        errors.add(
+            "name",
+            ValidationError::new(
+                "invalid name")
+                .with_message(Cow::Borrowed("name must be between 1 and 100 characters.")
+            )
+        );
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
@@ -1,4 +1,4 @@
-use axum::{routing::get, Router};
+use axum::{middleware, routing::get, Router};
 
 use crate::controllers::access_tokens_controller::{
     access_tokens_create, 
@@ -8,18 +8,29 @@ use crate::controllers::access_tokens_controller::{
     access_tokens_update
 };
 
-// Create user routes
+use crate::utils::rate_limit::RateLimitPolicy;
+
+use super::middlewares::{check_token_auth, rate_limit, require_admin};
+
+// Create access token routes. Tokens are issued through /api/auth, so these are for admins only.
 pub fn routes() -> Router {
     Router::new()
-        .route(
-            "/api/access_tokens",
-            get(access_tokens_index)
-            .post(access_tokens_create)
-        )
-        .route(
-            "/api/access_tokens/:id",
-            get(access_tokens_find)
-            .patch(access_tokens_update)
-            .delete(access_tokens_delete)
+        .nest(
+        "/api/access_tokens",
+        Router::new()
+            .route(
+                "/",
+                get(access_tokens_index)
+                .post(access_tokens_create)
+            )
+            .route(
+                "/:id",
+                get(access_tokens_find)
+                .patch(access_tokens_update)
+                .delete(access_tokens_delete)
+            )
+            .route_layer(middleware::from_fn_with_state(RateLimitPolicy::api(), rate_limit))
+            .route_layer(middleware::from_fn(require_admin))
+            .route_layer(middleware::from_fn(check_token_auth))
         )
-}
\ No newline at end of file
+}
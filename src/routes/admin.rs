@@ -0,0 +1,42 @@
+use axum::{middleware, routing::{delete, get, post}, Router};
+
+use crate::controllers::{
+    impersonation_controller::{
+        impersonation_create,
+        impersonation_stop,
+        impersonations_delete,
+        impersonations_index
+    },
+    login_lockouts_controller::{
+        login_attempts_index,
+        login_lockouts_delete,
+        login_lockouts_index
+    }
+};
+
+use crate::utils::rate_limit::RateLimitPolicy;
+
+use super::middlewares::{check_token_auth, rate_limit, require_admin};
+
+// Create admin routes
+pub fn routes() -> Router {
+    Router::new()
+        .nest(
+        "/api/admin",
+        Router::new()
+            .route("/lockouts", get(login_lockouts_index))
+            .route("/lockouts/:id", delete(login_lockouts_delete))
+            .route("/login_attempts", get(login_attempts_index))
+            .route("/impersonate/:user_id", post(impersonation_create))
+            .route("/impersonations", get(impersonations_index))
+            .route("/impersonations/:id", delete(impersonations_delete))
+            .route_layer(middleware::from_fn_with_state(RateLimitPolicy::api(), rate_limit))
+            .route_layer(middleware::from_fn(require_admin))
+            .route_layer(middleware::from_fn(check_token_auth))
+        )
+        // Called with the impersonation token, which require_admin turns away.
+        .route(
+            "/api/impersonation/stop",
+            post(impersonation_stop).route_layer(middleware::from_fn(check_token_auth))
+        )
+}
@@ -1,25 +1,42 @@
-use axum::{routing::get, Router};
+use axum::{middleware, routing::{get, post}, Router};
 
 use crate::controllers::api_keys_controller::{
     api_keys_create, 
     api_keys_delete, 
+    api_keys_expiring, 
     api_keys_find, 
     api_keys_index, 
+    api_keys_rotate, 
+    api_keys_signing_secret, 
     api_keys_update
 };
 
-// Create user routes
+use crate::utils::rate_limit::RateLimitPolicy;
+
+use super::middlewares::{check_token_auth, rate_limit, require_admin};
+
+// Create api key routes. Keys are managed by admins only.
 pub fn routes() -> Router {
     Router::new()
-        .route(
-            "/api/api_keys",
-            get(api_keys_index)
-            .post(api_keys_create)
-        )
-        .route(
-            "/api/api_keys/:id",
-            get(api_keys_find)
-            .patch(api_keys_update)
-            .delete(api_keys_delete)
+        .nest(
+        "/api/api_keys",
+        Router::new()
+            .route(
+                "/",
+                get(api_keys_index)
+                .post(api_keys_create)
+            )
+            .route(
+                "/:id",
+                get(api_keys_find)
+                .patch(api_keys_update)
+                .delete(api_keys_delete)
+            )
+            .route("/expiring", get(api_keys_expiring))
+            .route("/:id/rotate", post(api_keys_rotate))
+            .route("/:id/signing_secret", post(api_keys_signing_secret))
+            .route_layer(middleware::from_fn_with_state(RateLimitPolicy::api(), rate_limit))
+            .route_layer(middleware::from_fn(require_admin))
+            .route_layer(middleware::from_fn(check_token_auth))
         )
-}
\ No newline at end of file
+}
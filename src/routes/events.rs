@@ -0,0 +1,16 @@
+use axum::{middleware, routing::get, Router};
+
+use crate::controllers::events_controller::{events_stream, events_ws};
+use crate::utils::rate_limit::RateLimitPolicy;
+
+use super::middlewares::{check_token_auth, rate_limit, require_scope};
+
+// Create event routes
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/ws", get(events_ws))
+        .route("/api/events", get(events_stream))
+        .route_layer(middleware::from_fn_with_state("todos", require_scope))
+        .route_layer(middleware::from_fn_with_state(RateLimitPolicy::api(), rate_limit))
+        .route_layer(middleware::from_fn(check_token_auth))
+}
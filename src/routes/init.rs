@@ -1,6 +1,7 @@
 use axum::{
     middleware, routing::get, Extension, Router
 };
+use sqlx::MySqlPool;
 use tower_cookies::CookieManagerLayer;
 
 use crate::routes::{
@@ -8,14 +9,39 @@ use crate::routes::{
     todos,
     refresh_tokens,
     access_tokens,
-    api_keys
+    api_keys,
+    admin,
+    well_known,
+    oauth,
+    webauthn,
+    events,
+    webhooks,
+    notifications,
+    workspaces,
+    lists,
+    share_links
 };
 
-use super::middlewares::main_response_mapper;
+use crate::utils::{
+    api_key_usage::ApiKeyUsage,
+    events::EventBus,
+    lockout::LockoutPolicy,
+    mailer::mailer_from_env,
+    oidc::Oidc,
+    password::PasswordPolicy,
+    rate_limit::rate_limit_store,
+    sms::sms_sender_from_env,
+    webauthn::WebAuthnConfig
+};
+
+use super::middlewares::{csrf_protect, main_response_mapper};
+
+pub async fn run(pool: MySqlPool, event_bus: EventBus, api_key_usage: ApiKeyUsage) -> Result<Router, Box<dyn std::error::Error>> {
+    // Fail at startup rather than on the first login if the signing keys are misconfigured.
+    crate::utils::jwt_keys::KeySet::from_env()?;
+    let oidc = Oidc::from_env()?;
 
-pub async fn run() -> Result<Router, Box<dyn std::error::Error>> {
-    // Database Init
-    let pool = crate::database::init::run().await?;
+    api_key_usage.spawn_flusher(pool.clone());
     // Web Server Routes Init
     let app = Router::new()
         .route("/api", get(|| async { "Hello" }))
@@ -24,8 +50,28 @@ pub async fn run() -> Result<Router, Box<dyn std::error::Error>> {
         .merge(refresh_tokens::routes())
         .merge(access_tokens::routes())
         .merge(api_keys::routes())
+        .merge(admin::routes())
+        .merge(well_known::routes())
+        .merge(oauth::routes())
+        .merge(webauthn::routes())
+        .merge(events::routes())
+        .merge(webhooks::routes())
+        .merge(notifications::routes())
+        .merge(workspaces::routes())
+        .merge(lists::routes())
+        .merge(share_links::routes())
         .layer(middleware::map_response(main_response_mapper))
+        .layer(middleware::from_fn(csrf_protect))
         .layer(CookieManagerLayer::new())
+        .layer(Extension(sms_sender_from_env()))
+        .layer(Extension(mailer_from_env()))
+        .layer(Extension(LockoutPolicy::from_env()))
+        .layer(Extension(PasswordPolicy::from_env()))
+        .layer(Extension(api_key_usage))
+        .layer(Extension(oidc))
+        .layer(Extension(WebAuthnConfig::from_env()))
+        .layer(Extension(rate_limit_store()))
+        .layer(Extension(event_bus))
         .layer(Extension(pool));
 
     Ok(app)
@@ -0,0 +1,31 @@
+use axum::{middleware, routing::{get, post}, Router};
+
+use crate::controllers::lists_controller::{
+    lists_create,
+    lists_delete,
+    lists_find,
+    lists_index,
+    lists_todos,
+    lists_update
+};
+use crate::controllers::share_links_controller::list_share_links_create;
+use crate::utils::rate_limit::RateLimitPolicy;
+
+use super::middlewares::{check_token_auth, rate_limit, require_scope, resolve_workspace};
+
+// Create list routes. Lists hold todos, so OAuth apps reach them with the todos scope.
+pub fn routes() -> Router {
+    Router::new()
+        .nest(
+        "/api/lists",
+        Router::new()
+            .route("/", get(lists_index).post(lists_create))
+            .route("/:id", get(lists_find).patch(lists_update).delete(lists_delete))
+            .route("/:id/todos", get(lists_todos))
+            .route("/:id/share_links", post(list_share_links_create))
+            .route_layer(middleware::from_fn(resolve_workspace))
+            .route_layer(middleware::from_fn_with_state("todos", require_scope))
+            .route_layer(middleware::from_fn_with_state(RateLimitPolicy::api(), rate_limit))
+            .route_layer(middleware::from_fn(check_token_auth))
+        )
+}
@@ -0,0 +1,31 @@
+use axum::{middleware, routing::{delete, get, post}, Router};
+
+use crate::controllers::notifications_controller::{
+    notifications_delete,
+    notifications_index,
+    notifications_read,
+    notifications_read_all,
+    notifications_unread,
+    notifications_unread_count
+};
+use crate::utils::rate_limit::RateLimitPolicy;
+
+use super::middlewares::{check_token_auth, rate_limit, require_first_party};
+
+// Create notification routes
+pub fn routes() -> Router {
+    Router::new()
+        .nest(
+        "/api/notifications",
+        Router::new()
+            .route("/", get(notifications_index))
+            .route("/unread_count", get(notifications_unread_count))
+            .route("/read_all", post(notifications_read_all))
+            .route("/:id", delete(notifications_delete))
+            .route("/:id/read", post(notifications_read))
+            .route("/:id/unread", post(notifications_unread))
+            .route_layer(middleware::from_fn(require_first_party))
+            .route_layer(middleware::from_fn_with_state(RateLimitPolicy::api(), rate_limit))
+            .route_layer(middleware::from_fn(check_token_auth))
+        )
+}
@@ -0,0 +1,56 @@
+use axum::{middleware, routing::{get, post}, Router};
+
+use crate::controllers::{
+    oauth_clients_controller::{
+        oauth_clients_create,
+        oauth_clients_delete,
+        oauth_clients_find,
+        oauth_clients_index,
+        oauth_clients_update
+    },
+    oauth_controller::{
+        oauth_authorize_consent,
+        oauth_authorize_show,
+        oauth_introspect,
+        oauth_revoke,
+        oauth_token
+    }
+};
+use crate::utils::rate_limit::RateLimitPolicy;
+
+use super::middlewares::{check_token_auth, forbid_impersonation, rate_limit, require_admin};
+
+// Create oauth routes
+pub fn routes() -> Router {
+    Router::new()
+        .nest(
+        "/api/oauth",
+        Router::new()
+            // Client-facing endpoints, authenticated by client credentials.
+            .route("/token", post(oauth_token))
+            .route("/introspect", post(oauth_introspect))
+            .route("/revoke", post(oauth_revoke))
+            .route_layer(middleware::from_fn_with_state(RateLimitPolicy::auth(), rate_limit))
+            // The consent screen, for the logged-in user. A grant outlives an impersonation
+            // session, so only the user themselves can give one.
+            .merge(
+                Router::new()
+                    .route(
+                        "/authorize",
+                        get(oauth_authorize_show)
+                        .merge(post(oauth_authorize_consent).route_layer(middleware::from_fn(forbid_impersonation)))
+                    )
+                    .route_layer(middleware::from_fn_with_state(RateLimitPolicy::api(), rate_limit))
+                    .route_layer(middleware::from_fn(check_token_auth))
+            )
+            // Client registration.
+            .merge(
+                Router::new()
+                    .route("/clients", get(oauth_clients_index).post(oauth_clients_create))
+                    .route("/clients/:id", get(oauth_clients_find).patch(oauth_clients_update).delete(oauth_clients_delete))
+                    .route_layer(middleware::from_fn_with_state(RateLimitPolicy::api(), rate_limit))
+                    .route_layer(middleware::from_fn(require_admin))
+                    .route_layer(middleware::from_fn(check_token_auth))
+            )
+        )
+}
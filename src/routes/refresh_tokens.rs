@@ -1,4 +1,4 @@
-use axum::{routing::get, Router};
+use axum::{middleware, routing::get, Router};
 
 use crate::controllers::refresh_tokens_controller::{
     refresh_tokens_create, 
@@ -8,18 +8,29 @@ use crate::controllers::refresh_tokens_controller::{
     refresh_tokens_update
 };
 
-// Create user routes
+use crate::utils::rate_limit::RateLimitPolicy;
+
+use super::middlewares::{check_token_auth, rate_limit, require_admin};
+
+// Create refresh token routes. Tokens are issued through /api/auth, so these are for admins only.
 pub fn routes() -> Router {
     Router::new()
-        .route(
-            "/api/refresh_tokens",
-            get(refresh_tokens_index)
-            .post(refresh_tokens_create)
-        )
-        .route(
-            "/api/refresh_tokens/:id",
-            get(refresh_tokens_find)
-            .patch(refresh_tokens_update)
-            .delete(refresh_tokens_delete)
+        .nest(
+        "/api/refresh_tokens",
+        Router::new()
+            .route(
+                "/",
+                get(refresh_tokens_index)
+                .post(refresh_tokens_create)
+            )
+            .route(
+                "/:id",
+                get(refresh_tokens_find)
+                .patch(refresh_tokens_update)
+                .delete(refresh_tokens_delete)
+            )
+            .route_layer(middleware::from_fn_with_state(RateLimitPolicy::api(), rate_limit))
+            .route_layer(middleware::from_fn(require_admin))
+            .route_layer(middleware::from_fn(check_token_auth))
         )
-}
\ No newline at end of file
+}
@@ -0,0 +1,31 @@
+use axum::{middleware, routing::{delete, get}, Router};
+
+use crate::controllers::share_links_controller::{
+    public_share_link,
+    share_links_delete,
+    share_links_index
+};
+use crate::utils::rate_limit::RateLimitPolicy;
+
+use super::middlewares::{check_token_auth, rate_limit, require_scope, resolve_workspace};
+
+// Create share link routes. Links themselves are made on the list or todo they share.
+pub fn routes() -> Router {
+    Router::new()
+        .nest(
+        "/api/share_links",
+        Router::new()
+            .route("/", get(share_links_index))
+            .route("/:id", delete(share_links_delete))
+            .route_layer(middleware::from_fn(resolve_workspace))
+            .route_layer(middleware::from_fn_with_state("todos", require_scope))
+            .route_layer(middleware::from_fn_with_state(RateLimitPolicy::api(), rate_limit))
+            .route_layer(middleware::from_fn(check_token_auth))
+        )
+        // Public, outside check_token_auth: the token in the path is the only credential.
+        .route(
+            "/api/public/:token",
+            get(public_share_link)
+            .route_layer(middleware::from_fn_with_state(RateLimitPolicy::public(), rate_limit))
+        )
+}
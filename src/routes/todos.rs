@@ -1,12 +1,25 @@
-use axum::{routing::get, Router};
+use axum::{middleware, routing::{delete, get, post}, Router};
 
-use crate::controllers::todos_controller::{
+use crate::controllers::{share_links_controller::todo_share_links_create, todo_shares_controller::{
+    todo_invitations_accept,
+    todo_invitations_decline,
+    todo_invitations_index,
+    todo_shares_create,
+    todo_shares_delete,
+    todo_shares_index,
+    todo_shares_update,
+    todos_shared
+}, todos_controller::{
     todos_create, 
     todos_delete, 
     todos_find, 
     todos_index, 
     todos_update
-};
+}};
+
+use crate::utils::rate_limit::RateLimitPolicy;
+
+use super::middlewares::{check_token_auth, rate_limit, require_scope, resolve_workspace};
 
 // Create todo routes
 pub fn routes() -> Router {
@@ -22,4 +35,15 @@ pub fn routes() -> Router {
             .patch(todos_update)
             .delete(todos_delete)
         )
+        .route("/api/todos/shared", get(todos_shared))
+        .route("/api/todos/invitations", get(todo_invitations_index))
+        .route("/api/todos/invitations/:id/accept", post(todo_invitations_accept))
+        .route("/api/todos/invitations/:id/decline", post(todo_invitations_decline))
+        .route("/api/todos/:id/shares", get(todo_shares_index).post(todo_shares_create))
+        .route("/api/todos/:id/share_links", post(todo_share_links_create))
+        .route("/api/todos/:id/shares/:share_id", delete(todo_shares_delete).patch(todo_shares_update))
+        .route_layer(middleware::from_fn(resolve_workspace))
+        .route_layer(middleware::from_fn_with_state("todos", require_scope))
+        .route_layer(middleware::from_fn_with_state(RateLimitPolicy::api(), rate_limit))
+        .route_layer(middleware::from_fn(check_token_auth))
 }
\ No newline at end of file
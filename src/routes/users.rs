@@ -1,6 +1,15 @@
-use axum::{middleware, routing::{get, post}, Router};
+use axum::{middleware, routing::{delete, get, post}, Router};
 
-use crate::controllers::{auth_controller::{login, logout, refresh}, users_controller::{
+use crate::controllers::{auth_controller::{login, logout, refresh, token}, digest_preferences_controller::{
+    digest_preferences_find,
+    digest_preferences_update
+}, magic_links_controller::{magic_links_callback, magic_links_create}, oidc_controller::{oidc_callback, oidc_login}, phone_verifications_controller::{
+    phone_verifications_confirm,
+    phone_verifications_create
+}, reminder_preferences_controller::{
+    reminder_preferences_find,
+    reminder_preferences_update
+}, users_controller::{
     users_create, 
     users_delete, 
     users_find, 
@@ -8,7 +17,11 @@ use crate::controllers::{auth_controller::{login, logout, refresh}, users_contro
     users_update
 }};
 
-use super::middlewares::{api_key_auth, check_token_auth};
+use crate::utils::rate_limit::RateLimitPolicy;
+
+use super::middlewares::{
+    api_key_auth, check_token_auth, forbid_impersonation, rate_limit, require_scope, require_workspace_user, resolve_workspace
+};
 
 // Create user routes
 pub fn routes() -> Router {
@@ -16,8 +29,22 @@ pub fn routes() -> Router {
         .nest(
         "/api/users",
         Router::new()
+            // Second factors cannot be changed while impersonating.
+            .route("/:id/phone_verification", post(phone_verifications_create))
+            .route("/:id/phone_verification/confirm", post(phone_verifications_confirm))
+            .route_layer(middleware::from_fn(forbid_impersonation))
             .route("/", get(users_index).post(users_create))
-            .route("/:id", get(users_find).patch(users_update).delete(users_delete))
+            // Nor can the account be deleted.
+            .route("/:id", get(users_find).patch(users_update).merge(
+                delete(users_delete).route_layer(middleware::from_fn(forbid_impersonation))
+            ))
+            .route("/:id/reminder_preferences", get(reminder_preferences_find).patch(reminder_preferences_update))
+            .route("/:id/digest_preferences", get(digest_preferences_find).patch(digest_preferences_update))
+            // Users of other workspaces are out of reach.
+            .route_layer(middleware::from_fn(require_workspace_user))
+            .route_layer(middleware::from_fn(resolve_workspace))
+            .route_layer(middleware::from_fn_with_state("users", require_scope))
+            .route_layer(middleware::from_fn_with_state(RateLimitPolicy::api(), rate_limit))
             .route_layer(middleware::from_fn(check_token_auth))
         )
         .nest(
@@ -25,12 +52,19 @@ pub fn routes() -> Router {
         Router::new()
             .route("/", get(users_index))
             .route("/:id", get(users_find))
-            .route_layer(middleware::from_fn(api_key_auth))
+            .route_layer(middleware::from_fn_with_state(RateLimitPolicy::external(), rate_limit))
+            .route_layer(middleware::from_fn_with_state("users:read", api_key_auth))
         )
         .nest(
             "/api/auth", 
             Router::new()
                 .route("/login", post(login))
+                .route("/token", post(token))
+                .route("/oidc/:provider/login", get(oidc_login))
+                .route("/oidc/:provider/callback", get(oidc_callback))
+                .route("/magic-link", post(magic_links_create))
+                .route("/magic-link/callback", get(magic_links_callback))
+                .route_layer(middleware::from_fn_with_state(RateLimitPolicy::auth(), rate_limit))
                 .route("/refresh", post(refresh)) 
                 .route("/logout", post(logout)) 
         )
@@ -0,0 +1,38 @@
+use axum::{middleware, routing::{delete, get, post}, Router};
+
+use crate::controllers::webauthn_controller::{
+    webauthn_credentials_delete,
+    webauthn_credentials_index,
+    webauthn_login,
+    webauthn_login_options,
+    webauthn_register,
+    webauthn_register_options
+};
+use crate::utils::rate_limit::RateLimitPolicy;
+
+use super::middlewares::{check_token_auth, forbid_impersonation, rate_limit, require_first_party};
+
+// Create webauthn routes
+pub fn routes() -> Router {
+    Router::new()
+        .nest(
+        "/api/webauthn",
+        Router::new()
+            // Passkey login, an alternative to /api/auth/login.
+            .route("/login/options", post(webauthn_login_options))
+            .route("/login", post(webauthn_login))
+            .route_layer(middleware::from_fn_with_state(RateLimitPolicy::auth(), rate_limit))
+            // Managing the logged-in user's passkeys.
+            .merge(
+                Router::new()
+                    .route("/register/options", post(webauthn_register_options))
+                    .route("/register", post(webauthn_register))
+                    .route("/credentials/:id", delete(webauthn_credentials_delete))
+                    .route_layer(middleware::from_fn(forbid_impersonation))
+                    .route("/credentials", get(webauthn_credentials_index))
+                    .route_layer(middleware::from_fn(require_first_party))
+                    .route_layer(middleware::from_fn_with_state(RateLimitPolicy::api(), rate_limit))
+                    .route_layer(middleware::from_fn(check_token_auth))
+            )
+        )
+}
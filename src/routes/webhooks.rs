@@ -0,0 +1,45 @@
+use axum::{middleware, routing::{get, patch, post, MethodRouter}, Router};
+
+use crate::controllers::webhooks_controller::{
+    webhook_deliveries_find,
+    webhook_deliveries_index,
+    webhook_deliveries_retry,
+    webhooks_create,
+    webhooks_delete,
+    webhooks_find,
+    webhooks_index,
+    webhooks_update
+};
+use crate::utils::rate_limit::RateLimitPolicy;
+
+use super::middlewares::{api_key_auth, check_token_auth, forbid_impersonation, rate_limit, require_first_party};
+
+// Create webhook routes
+pub fn routes() -> Router {
+    Router::new()
+        .nest(
+        "/api/webhooks",
+        // A webhook keeps receiving the user's data after an impersonation session ends.
+        webhook_routes(|route| route.route_layer(middleware::from_fn(forbid_impersonation)))
+            .route_layer(middleware::from_fn(require_first_party))
+            .route_layer(middleware::from_fn_with_state(RateLimitPolicy::api(), rate_limit))
+            .route_layer(middleware::from_fn(check_token_auth))
+        )
+        // The same for API keys, whose webhooks receive what their read scopes allow.
+        .nest(
+        "/api/external/webhooks",
+        webhook_routes(|route| route)
+            .route_layer(middleware::from_fn_with_state(RateLimitPolicy::external(), rate_limit))
+            .route_layer(middleware::from_fn_with_state("webhooks:manage", api_key_auth))
+        )
+}
+
+// `sends` wraps the routes that decide where deliveries go or send them again.
+fn webhook_routes(sends: impl Fn(MethodRouter) -> MethodRouter) -> Router {
+    Router::new()
+        .route("/", get(webhooks_index).merge(sends(post(webhooks_create))))
+        .route("/:id", get(webhooks_find).delete(webhooks_delete).merge(sends(patch(webhooks_update))))
+        .route("/:id/deliveries", get(webhook_deliveries_index))
+        .route("/:id/deliveries/:delivery_id", get(webhook_deliveries_find))
+        .route("/:id/deliveries/:delivery_id/retry", sends(post(webhook_deliveries_retry)))
+}
@@ -0,0 +1,9 @@
+use axum::{routing::get, Router};
+
+use crate::controllers::jwks_controller::jwks_show;
+
+// Create .well-known routes
+pub fn routes() -> Router {
+    Router::new()
+        .route("/.well-known/jwks.json", get(jwks_show))
+}
@@ -0,0 +1,42 @@
+use axum::{middleware, routing::{get, patch, post}, Router};
+
+use crate::controllers::workspaces_controller::{
+    workspace_invitations_accept,
+    workspace_invitations_create,
+    workspace_invitations_decline,
+    workspace_invitations_index,
+    workspace_members_delete,
+    workspace_members_index,
+    workspace_members_update,
+    workspaces_create,
+    workspaces_delete,
+    workspaces_find,
+    workspaces_index,
+    workspaces_switch,
+    workspaces_update
+};
+use crate::utils::rate_limit::RateLimitPolicy;
+
+use super::middlewares::{check_token_auth, forbid_impersonation, rate_limit, require_first_party};
+
+// Create workspace routes
+pub fn routes() -> Router {
+    Router::new()
+        .nest(
+        "/api/workspaces",
+        Router::new()
+            .route("/", get(workspaces_index).post(workspaces_create))
+            .route("/:id", get(workspaces_find).patch(workspaces_update).delete(workspaces_delete))
+            .route("/:id/members", get(workspace_members_index))
+            .route("/:id/members/:user_id", patch(workspace_members_update).delete(workspace_members_delete))
+            .route("/:id/invitations", post(workspace_invitations_create))
+            .route("/invitations", get(workspace_invitations_index))
+            .route("/invitations/:id/accept", post(workspace_invitations_accept))
+            .route("/invitations/:id/decline", post(workspace_invitations_decline))
+            // Switching mints a new session, which an impersonating admin must not walk away with.
+            .route("/:id/switch", post(workspaces_switch).route_layer(middleware::from_fn(forbid_impersonation)))
+            .route_layer(middleware::from_fn(require_first_party))
+            .route_layer(middleware::from_fn_with_state(RateLimitPolicy::api(), rate_limit))
+            .route_layer(middleware::from_fn(check_token_auth))
+        )
+}
@@ -0,0 +1,91 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use dotenv::dotenv;
+use sqlx::MySqlPool;
+
+use crate::utils::env::env_or;
+
+#[derive(Debug, Clone, Copy)]
+struct PendingUsage {
+    count: i64,
+    last_used_at: DateTime<Utc>,
+}
+
+// Collects API key usage in memory so api_key_auth does not write to the database on every
+// request. A background task flushes the totals every API_KEY_USAGE_FLUSH_SECONDS (default 60),
+// and main flushes what is left once the server has stopped. The counts only live in this
+// instance's memory, which is why this is not a job that any instance could pick up.
+#[derive(Clone, Default)]
+pub struct ApiKeyUsage {
+    pending: Arc<Mutex<HashMap<i32, PendingUsage>>>,
+}
+
+impl ApiKeyUsage {
+    pub fn record(&self, api_key_id: i32) {
+        let now = Utc::now();
+        let mut pending = self.pending.lock().unwrap();
+
+        pending
+            .entry(api_key_id)
+            .and_modify(|usage| {
+                usage.count += 1;
+                usage.last_used_at = now;
+            })
+            .or_insert(PendingUsage { count: 1, last_used_at: now });
+    }
+
+    // Writes the collected counts. Counts that fail to save are kept for the next flush.
+    pub async fn flush(&self, pool: &MySqlPool) {
+        let batch = std::mem::take(&mut *self.pending.lock().unwrap());
+
+        for (api_key_id, usage) in batch {
+            let q = "UPDATE api_keys SET usage_count = usage_count + ?, \
+                     last_used_at = GREATEST(COALESCE(last_used_at, ?), ?) WHERE id = ?";
+
+            let result = sqlx::query(q)
+                .bind(usage.count)
+                .bind(usage.last_used_at)
+                .bind(usage.last_used_at)
+                .bind(api_key_id)
+                .execute(pool)
+                .await;
+
+            if let Err(e) = result {
+                println!("Failed to flush usage for api key {}: {}", api_key_id, e);
+                self.merge(api_key_id, usage);
+            }
+        }
+    }
+
+    pub fn spawn_flusher(&self, pool: MySqlPool) {
+        dotenv().ok();
+        // tokio's interval panics on zero.
+        let seconds = env_or("API_KEY_USAGE_FLUSH_SECONDS", 60).max(1);
+
+        let usage = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(seconds));
+            loop {
+                interval.tick().await;
+                usage.flush(&pool).await;
+            }
+        });
+    }
+
+    fn merge(&self, api_key_id: i32, usage: PendingUsage) {
+        let mut pending = self.pending.lock().unwrap();
+
+        pending
+            .entry(api_key_id)
+            .and_modify(|existing| {
+                existing.count += usage.count;
+                existing.last_used_at = existing.last_used_at.max(usage.last_used_at);
+            })
+            .or_insert(usage);
+    }
+}
@@ -0,0 +1,56 @@
+use std::{convert::Infallible, env, net::SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::request::Parts,
+};
+use dotenv::dotenv;
+
+// Best-effort address of the caller.
+// X-Forwarded-For / X-Real-IP are only honoured when TRUST_PROXY_HEADERS=true,
+// otherwise anybody could pick their own address by sending the header.
+pub struct ClientIp(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(ClientIp(client_ip(parts)))
+    }
+}
+
+pub fn client_ip(parts: &Parts) -> String {
+    dotenv().ok();
+    let trust_proxy = env::var("TRUST_PROXY_HEADERS")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+
+    if trust_proxy {
+        let forwarded = parts
+            .headers
+            .get("X-Forwarded-For")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.split(',').next())
+            .map(|ip| ip.trim().to_string())
+            .filter(|ip| !ip.is_empty());
+
+        if let Some(ip) = forwarded {
+            return ip;
+        }
+
+        if let Some(ip) = parts.headers.get("X-Real-IP").and_then(|h| h.to_str().ok()) {
+            return ip.trim().to_string();
+        }
+    }
+
+    parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
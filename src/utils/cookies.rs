@@ -0,0 +1,127 @@
+use std::env;
+
+use dotenv::dotenv;
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use tower_cookies::{
+    cookie::{time::Duration, SameSite},
+    Cookie,
+};
+
+use crate::utils::tokens::ACCESS_TOKEN_TTL_MINUTES;
+
+// Lifetime of the refresh token cookie, matching the refresh_tokens.expires_at we store.
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 7;
+// The refresh cookie is only ever sent to the auth endpoints, so that logout can revoke it.
+pub const REFRESH_TOKEN_COOKIE_PATH: &str = "/api/auth";
+pub const CSRF_COOKIE: &str = "csrf_token";
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+// Binds an OIDC login to the browser that started it.
+pub const OIDC_STATE_COOKIE: &str = "oidc_state";
+const OIDC_STATE_COOKIE_PATH: &str = "/api/auth/oidc";
+// Recognises a browser the user has signed in from before. Survives logout on purpose.
+pub const DEVICE_COOKIE: &str = "device_id";
+const DEVICE_COOKIE_DAYS: i64 = 365;
+
+// Attributes shared by every auth cookie.
+//   COOKIE_SECURE     true (default) | false, turn off only for plain-http local development
+//   COOKIE_SAME_SITE  Lax (default) | Strict | None
+//   COOKIE_DOMAIN     unset by default (host-only cookies)
+#[derive(Debug, Clone)]
+pub struct CookieSettings {
+    pub secure: bool,
+    pub same_site: SameSite,
+    pub domain: Option<String>,
+}
+
+impl CookieSettings {
+    pub fn from_env() -> Self {
+        dotenv().ok();
+        let same_site = match env::var("COOKIE_SAME_SITE").as_deref() {
+            Ok("Strict") | Ok("strict") => SameSite::Strict,
+            Ok("None") | Ok("none") => SameSite::None,
+            _ => SameSite::Lax,
+        };
+
+        Self {
+            // Browsers reject SameSite=None without Secure.
+            secure: same_site == SameSite::None
+                || env::var("COOKIE_SECURE").map(|v| v != "false").unwrap_or(true),
+            same_site,
+            domain: env::var("COOKIE_DOMAIN").ok().filter(|d| !d.is_empty()),
+        }
+    }
+
+    fn build(&self, name: &'static str, value: String, path: &'static str, max_age: Duration, http_only: bool) -> Cookie<'static> {
+        let mut cookie = Cookie::build((name, value))
+            .http_only(http_only)
+            .secure(self.secure)
+            .same_site(self.same_site)
+            .path(path)
+            .max_age(max_age)
+            .build();
+
+        if let Some(domain) = &self.domain {
+            cookie.set_domain(domain.clone());
+        }
+
+        cookie
+    }
+}
+
+pub fn access_token_cookie(token: String) -> Cookie<'static> {
+    CookieSettings::from_env().build("access_token", token, "/api", Duration::minutes(ACCESS_TOKEN_TTL_MINUTES), true)
+}
+
+pub fn refresh_token_cookie(token: String) -> Cookie<'static> {
+    CookieSettings::from_env().build("refresh_token", token, REFRESH_TOKEN_COOKIE_PATH, Duration::days(REFRESH_TOKEN_TTL_DAYS), true)
+}
+
+// Readable by JavaScript on purpose: the client echoes it back in the X-CSRF-Token header.
+pub fn csrf_cookie(token: String) -> Cookie<'static> {
+    CookieSettings::from_env().build(CSRF_COOKIE, token, "/", Duration::days(REFRESH_TOKEN_TTL_DAYS), false)
+}
+
+// Has to come back on the provider's cross-site redirect, so it is never SameSite=Strict.
+pub fn oidc_state_cookie(state: String) -> Cookie<'static> {
+    let mut settings = CookieSettings::from_env();
+    if settings.same_site == SameSite::Strict {
+        settings.same_site = SameSite::Lax;
+    }
+    settings.build(OIDC_STATE_COOKIE, state, OIDC_STATE_COOKIE_PATH, Duration::minutes(10), true)
+}
+
+pub fn oidc_state_removal_cookie() -> Cookie<'static> {
+    CookieSettings::from_env().build(OIDC_STATE_COOKIE, String::new(), OIDC_STATE_COOKIE_PATH, Duration::ZERO, true)
+}
+
+pub fn device_cookie(device_id: String) -> Cookie<'static> {
+    CookieSettings::from_env().build(DEVICE_COOKIE, device_id, "/api", Duration::days(DEVICE_COOKIE_DAYS), true)
+}
+
+// Expired copies of the auth cookies. Path and domain have to match for the browser to drop them.
+pub fn removal_cookies() -> Vec<Cookie<'static>> {
+    let settings = CookieSettings::from_env();
+
+    vec![
+        settings.build("access_token", String::new(), "/api", Duration::ZERO, true),
+        settings.build("refresh_token", String::new(), REFRESH_TOKEN_COOKIE_PATH, Duration::ZERO, true),
+        settings.build(CSRF_COOKIE, String::new(), "/", Duration::ZERO, false),
+    ]
+}
+
+pub fn generate_csrf_token() -> String {
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+// Compares secrets without leaking where the first difference is through timing.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,169 @@
+// Digests: one email summarising a user's overdue, due-today and recently completed todos,
+// for those who would rather not get a reminder for each. Both bodies are rendered from
+// the templates in templates/.
+use chrono::{DateTime, Days, Utc};
+use chrono_tz::Tz;
+use minijinja::{context, Environment};
+use serde::Serialize;
+use sqlx::MySqlPool;
+
+use crate::models::{
+    digest::{local_time, DigestPreferences},
+    user::User,
+};
+
+const TEXT_TEMPLATE: &str = include_str!("templates/digest.txt");
+const HTML_TEMPLATE: &str = include_str!("templates/digest.html");
+// Todos listed per section. The rest are only counted.
+const SECTION_LIMIT: i64 = 25;
+
+#[derive(Debug, Serialize)]
+pub struct DigestTodo {
+    pub description: String,
+    // E.g. "due Mon 3 Mar", in the user's time zone.
+    pub when: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DigestSection {
+    pub title: &'static str,
+    pub total: i64,
+    pub todos: Vec<DigestTodo>,
+}
+
+pub struct Digest {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+// The digest for the period ending at `period_end`, or None when there is nothing in it.
+pub async fn compose_digest(
+    pool: &MySqlPool,
+    user: &User,
+    preferences: &DigestPreferences,
+    period_start: DateTime<Utc>,
+    period_end: DateTime<Utc>,
+) -> Result<Option<Digest>, String> {
+    let tz = preferences.time_zone();
+    let today = period_end.with_timezone(&tz).date_naive();
+    let start_of_today = local_time(&tz, today, 0).unwrap_or(period_end);
+    let start_of_tomorrow = today
+        .checked_add_days(Days::new(1))
+        .and_then(|tomorrow| local_time(&tz, tomorrow, 0))
+        .unwrap_or(period_end);
+
+    let queries = [
+        SectionQuery {
+            title: "Overdue",
+            filter: "NOT done AND due_at < ?",
+            bounds: vec![start_of_today],
+            column: "due_at",
+            when: "due %a %-d %b",
+        },
+        SectionQuery {
+            title: "Due today",
+            filter: "NOT done AND due_at >= ? AND due_at < ?",
+            bounds: vec![start_of_today, start_of_tomorrow],
+            column: "due_at",
+            when: "due at %H:%M",
+        },
+        SectionQuery {
+            title: "Recently completed",
+            filter: "done AND completed_at >= ? AND completed_at < ?",
+            bounds: vec![period_start, period_end],
+            column: "completed_at",
+            when: "done %a %-d %b",
+        },
+    ];
+
+    let mut sections = Vec::new();
+    for query in &queries {
+        sections.push(section(pool, user.id, &tz, query).await?);
+    }
+
+    if sections.iter().all(|section| section.total == 0) {
+        return Ok(None);
+    }
+
+    let frequency = if preferences.digest_frequency == "weekly" { "weekly" } else { "daily" };
+    let date = today.format("%A %-d %B %Y").to_string();
+    let subject = format!("Your {} todo digest for {}", frequency, date);
+    let sections: Vec<&DigestSection> = sections.iter().filter(|section| section.total > 0).collect();
+
+    let mut env = Environment::new();
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+    env.add_template("digest.txt", TEXT_TEMPLATE).map_err(|e| format!("Invalid digest template: {}", e))?;
+    // Values in .html templates are escaped.
+    env.add_template("digest.html", HTML_TEMPLATE).map_err(|e| format!("Invalid digest template: {}", e))?;
+
+    let ctx = context! { subject => &subject, username => &user.username, frequency, date, sections };
+    let render = |name: &str| {
+        env.get_template(name)
+            .and_then(|template| template.render(&ctx))
+            .map_err(|e| format!("Failed to render {}: {}", name, e))
+    };
+
+    Ok(Some(Digest { text: render("digest.txt")?, html: render("digest.html")?, subject }))
+}
+
+// Todos of the user matching `filter`, whose placeholders take `bounds`, listed by `column`
+// and described with the `when` format.
+struct SectionQuery {
+    title: &'static str,
+    filter: &'static str,
+    bounds: Vec<DateTime<Utc>>,
+    column: &'static str,
+    when: &'static str,
+}
+
+async fn section(pool: &MySqlPool, user_id: i32, tz: &Tz, query: &SectionQuery) -> Result<DigestSection, String> {
+    let SectionQuery { title, filter, bounds, column, when } = query;
+
+    let count_q = format!("SELECT COUNT(*) FROM todos WHERE user_id = ? AND {}", filter);
+    let list_q = format!("SELECT description, {c} FROM todos WHERE user_id = ? AND {} ORDER BY {c} LIMIT ?", filter, c = column);
+
+    let mut count = sqlx::query_scalar::<_, i64>(&count_q).bind(user_id);
+    let mut list = sqlx::query_as::<_, (String, DateTime<Utc>)>(&list_q).bind(user_id);
+    for bound in bounds {
+        count = count.bind(*bound);
+        list = list.bind(*bound);
+    }
+
+    let total = count
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to count {} todos: {}", title, e))?;
+    let todos = list
+        .bind(SECTION_LIMIT)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch {} todos: {}", title, e))?
+        .into_iter()
+        .map(|(description, at)| DigestTodo { description, when: at.with_timezone(tz).format(when).to_string() })
+        .collect();
+
+    Ok(DigestSection { title: query.title, total, todos })
+}
+
+// Works out when the user's next digest is due. Called whenever anything it depends on
+// changes.
+pub async fn reschedule_digest(pool: &MySqlPool, user_id: i32) -> Result<(), String> {
+    let q = "SELECT time_zone, digest_frequency, digest_hour, digest_weekday, next_digest_at FROM users WHERE id = ?";
+
+    let preferences = sqlx::query_as::<_, DigestPreferences>(q)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch digest preferences: {}", e))?;
+
+    sqlx::query("UPDATE users SET next_digest_at = ? WHERE id = ?")
+        .bind(preferences.next_digest_at(Utc::now()))
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to schedule digest: {}", e))?;
+
+    Ok(())
+}
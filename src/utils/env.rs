@@ -0,0 +1,10 @@
+// Settings read from the environment.
+use std::{env, str::FromStr};
+
+// The variable parsed as T, or `default` if it is unset or does not parse.
+pub fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
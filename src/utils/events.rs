@@ -0,0 +1,221 @@
+// Change events for the user's connected clients.
+//
+// Controllers call `record` inside the transaction that makes a change, which writes the
+// event to the events table and gives it its id, and hand the returned events to
+// `EventBus::send` once that transaction has committed; they are then fanned out in-process
+// to open WebSockets and SSE streams. A client that reconnects passes the last id it saw and
+// is sent the rest from the table. Old events are pruned by the events.prune job; a client
+// that was away for longer is told to resync instead.
+//
+// Concurrent transactions can commit out of id order, so a live client may see id 5 before
+// id 4. Subscriptions therefore only skip the events they already replayed, not every id
+// below the last one sent.
+use std::{collections::HashSet, future::Future, sync::Arc};
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::{Executor, MySql, MySqlPool};
+use tokio::sync::{broadcast, watch};
+
+use crate::models::event::{Event, EventRecord};
+
+// Events a slow subscriber may fall behind before it has to resync.
+const CHANNEL_CAPACITY: usize = 256;
+// A client that missed more than this many events resyncs rather than replaying them.
+const REPLAY_LIMIT: i64 = 1000;
+
+// What a subscriber is sent: an event, or word that it missed events and has to reload.
+pub enum Delivery {
+    Event(Arc<Event>),
+    Resync { last_event_id: i64 },
+}
+
+// One user's view of the bus, for as long as a client stays connected.
+pub struct Subscription {
+    receiver: broadcast::Receiver<Arc<Event>>,
+    user_id: i32,
+    last_delivered: i64,
+    // Ids sent from the table on subscribing, whose live copies are not sent again.
+    replayed: HashSet<i64>,
+    lagged: bool,
+    pending: Option<Arc<Event>>,
+}
+
+impl Subscription {
+    // Next live event for the user, or None once the bus is gone. Cancel safe.
+    pub async fn next(&mut self) -> Option<Delivery> {
+        loop {
+            let event = match self.pending.take() {
+                Some(event) => event,
+                None => match self.receiver.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        self.lagged = true;
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                },
+            };
+
+            // After falling behind, everything before the oldest event still in the channel
+            // is lost: resync up to there and carry on from it.
+            if self.lagged {
+                self.lagged = false;
+                self.last_delivered = event.id - 1;
+                self.pending = Some(event);
+                return Some(Delivery::Resync { last_event_id: self.last_delivered });
+            }
+
+            if event.user_id != self.user_id || self.replayed.remove(&event.id) {
+                continue;
+            }
+            self.last_delivered = self.last_delivered.max(event.id);
+            return Some(Delivery::Event(event));
+        }
+    }
+
+    pub fn last_event_id(&self) -> i64 {
+        self.last_delivered
+    }
+}
+
+// Writes an event to the events table. Pass the transaction of the change it describes and
+// send the returned event once it has committed. `user_id` is the user whose clients get it.
+pub async fn record<'e, E>(executor: E, user_id: i32, kind: &str, data: &impl Serialize) -> Result<Event, (StatusCode, String)>
+where
+    E: Executor<'e, Database = MySql>,
+{
+    let data = serde_json::to_value(data)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to serialise {} event: {}", kind, e)))?;
+
+    let id = sqlx::query("INSERT INTO events (user_id, type, data) VALUES (?, ?, ?)")
+        .bind(user_id)
+        .bind(kind)
+        .bind(data.to_string())
+        .execute(executor)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store {} event: {}", kind, e)))?
+        .last_insert_id() as i64;
+
+    Ok(Event {
+        id,
+        kind: kind.to_string(),
+        user_id,
+        data,
+        occurred_at: Utc::now().into(),
+    })
+}
+
+#[derive(Clone)]
+pub struct EventBus {
+    pool: MySqlPool,
+    sender: broadcast::Sender<Arc<Event>>,
+    closed: Arc<watch::Sender<bool>>,
+}
+
+impl EventBus {
+    pub fn new(pool: MySqlPool) -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let (closed, _) = watch::channel(false);
+
+        Self { pool, sender, closed: Arc::new(closed) }
+    }
+
+    // Closes every live stream, at shutdown. Streams otherwise stay open until their token
+    // expires and the server would wait for them before stopping.
+    pub fn close(&self) {
+        self.closed.send_replace(true);
+    }
+
+    // Resolves once the bus is closed; streams select on it next to their subscription.
+    pub fn closed(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut closed = self.closed.subscribe();
+
+        async move {
+            let _ = closed.wait_for(|closed| *closed).await;
+        }
+    }
+
+    // Fans recorded events out to connected clients. Only call it after their transaction
+    // has committed, so live clients never hear of a change that was rolled back.
+    pub fn send(&self, events: impl IntoIterator<Item = Event>) {
+        for event in events {
+            let _ = self.sender.send(Arc::new(event));
+        }
+    }
+
+    // Records and sends an event that is not part of any transaction. Never fails the
+    // request that made the change; an event that cannot be stored is not sent either.
+    pub async fn publish(&self, user_id: i32, kind: &str, data: &impl Serialize) {
+        match record(&self.pool, user_id, kind, data).await {
+            Ok(event) => self.send([event]),
+            Err((_, e)) => println!("Failed to publish event to user {}: {}", user_id, e),
+        }
+    }
+
+    // Subscribes the user and returns what has to be sent before any live event: the events
+    // missed since `resume_from`, or a resync if they cannot be replayed.
+    pub async fn subscribe(&self, user_id: i32, resume_from: Option<i64>) -> Result<(Subscription, Vec<Delivery>), sqlx::Error> {
+        // Subscribe before reading the log so nothing published in between is lost.
+        // Events that show up in both are skipped by id.
+        let receiver = self.sender.subscribe();
+        let mut backlog = Vec::new();
+        let mut replayed = HashSet::new();
+
+        let last_delivered = match resume_from {
+            Some(last_event_id) => match self.events_since(user_id, last_event_id).await? {
+                Some(missed) => {
+                    let last_delivered = missed.last().map_or(last_event_id, |event| event.id);
+                    replayed.extend(missed.iter().map(|event| event.id));
+                    backlog.extend(missed.into_iter().map(|event| Delivery::Event(Arc::new(event))));
+                    last_delivered
+                }
+                None => {
+                    let last_event_id = self.last_event_id().await?;
+                    backlog.push(Delivery::Resync { last_event_id });
+                    last_event_id
+                }
+            },
+            None => self.last_event_id().await?,
+        };
+
+        let subscription = Subscription { receiver, user_id, last_delivered, replayed, lagged: false, pending: None };
+
+        Ok((subscription, backlog))
+    }
+
+    // Id of the latest event, 0 if there is none.
+    async fn last_event_id(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar::<_, i64>("SELECT CAST(COALESCE(MAX(id), 0) AS SIGNED) FROM events")
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    // The user's events after `last_event_id`, or None if the client has to resync because
+    // some of them were pruned or there are too many to replay.
+    async fn events_since(&self, user_id: i32, last_event_id: i64) -> Result<Option<Vec<Event>>, sqlx::Error> {
+        let (oldest, latest) = sqlx::query_as::<_, (i64, i64)>(
+            "SELECT CAST(COALESCE(MIN(id), 0) AS SIGNED), CAST(COALESCE(MAX(id), 0) AS SIGNED) FROM events",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        if last_event_id > latest || (oldest > 0 && last_event_id < oldest - 1) {
+            return Ok(None);
+        }
+
+        let records = sqlx::query_as::<_, EventRecord>("SELECT * FROM events WHERE user_id = ? AND id > ? ORDER BY id LIMIT ?")
+            .bind(user_id)
+            .bind(last_event_id)
+            .bind(REPLAY_LIMIT + 1)
+            .fetch_all(&self.pool)
+            .await?;
+
+        if records.len() as i64 > REPLAY_LIMIT {
+            return Ok(None);
+        }
+
+        Ok(Some(records.into_iter().map(Event::from).collect()))
+    }
+}
@@ -1,3 +1,4 @@
+use serde::{Deserialize, Deserializer};
 use validator::ValidationErrors;
 
 // Helper function to format validation errors
@@ -15,4 +16,15 @@ pub fn handle_validation_errors(errors: ValidationErrors) -> String {
         })
         .collect();
     formatted_errors.join(", ")
-}
\ No newline at end of file
+}
+
+// For optional fields of update payloads that can also be cleared:
+// `#[serde(default, deserialize_with = "nullable")]` on an `Option<Option<T>>` gives None when
+// the field is missing and Some(None) when it is null.
+pub fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
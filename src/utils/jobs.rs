@@ -0,0 +1,443 @@
+// Background jobs.
+//
+// Jobs are rows in the jobs table, so they survive restarts and are shared by every instance
+// of the server. `enqueue` adds one, optionally in the transaction of the change that needs
+// it. A pool of workers claims due jobs with SELECT ... FOR UPDATE SKIP LOCKED and runs them
+// with the handler registered for their kind. A failed job is retried with exponential
+// backoff until it runs out of attempts and is marked dead. Recurring jobs are declared
+// with a cron expression and enqueued by whichever instance claims the slot first.
+//
+// On shutdown workers stop claiming jobs and the ones in flight get
+// JOB_SHUTDOWN_TIMEOUT_SECONDS (default 30) to finish. A job that is cut off, or whose
+// server dies, is picked up again once its lock expires.
+//
+//   JOB_WORKERS                 jobs run at the same time per instance (default 4)
+//   JOB_POLL_INTERVAL_SECONDS   how often idle workers look for jobs (default 1)
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use dotenv::dotenv;
+use rand::{thread_rng, Rng};
+use serde::Serialize;
+use sqlx::{Executor, MySql, MySqlPool};
+use tokio::{sync::watch, task::JoinSet, time::sleep};
+use uuid::Uuid;
+
+use crate::{models::job::{Job, JobSchedule}, utils::env::env_or};
+
+const RETRY_BASE_SECONDS: i64 = 10;
+const RETRY_MAX_SECONDS: i64 = 3600;
+const MAX_ERROR_LENGTH: usize = 1024;
+// Extra time a running job stays locked beyond its handler's timeout.
+const LOCK_SLACK_SECONDS: i64 = 60;
+
+// Runs jobs of one kind. Handlers hold whatever they need, e.g. the pool or a mailer.
+#[async_trait]
+pub trait JobHandler: Send + Sync {
+    async fn run(&self, job: &Job) -> Result<(), String>;
+
+    // A run taking longer than this is aborted and counts as a failed attempt.
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(300)
+    }
+}
+
+pub struct JobOptions {
+    // While a job with this key is pending or running, enqueueing another one is a no-op.
+    pub unique_key: Option<String>,
+    // Defaults to now.
+    pub run_at: Option<DateTime<Utc>>,
+    pub max_attempts: i32,
+}
+
+impl Default for JobOptions {
+    fn default() -> Self {
+        Self { unique_key: None, run_at: None, max_attempts: 5 }
+    }
+}
+
+// Adds a job. Returns false if a job with the same unique key is already queued.
+pub async fn enqueue<'e, E>(executor: E, kind: &str, payload: &impl Serialize, options: JobOptions) -> Result<bool, (StatusCode, String)>
+where
+    E: Executor<'e, Database = MySql>,
+{
+    let payload = serde_json::to_string(payload)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to serialise {} job: {}", kind, e)))?;
+
+    // Only a duplicate unique key is ignored, unlike with INSERT IGNORE.
+    let q = "INSERT INTO jobs (kind, payload, unique_key, max_attempts, run_at) VALUES (?, ?, ?, ?, ?) \
+             ON DUPLICATE KEY UPDATE id = id";
+
+    let result = sqlx::query(q)
+        .bind(kind)
+        .bind(payload)
+        .bind(options.unique_key)
+        .bind(options.max_attempts.max(1))
+        .bind(options.run_at.unwrap_or_else(Utc::now))
+        .execute(executor)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to enqueue {} job: {}", kind, e)))?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+// Delay before retrying a job that failed `attempts` times: 10s, 20s, 40s, ... capped at an
+// hour, plus jitter.
+pub fn retry_delay(attempts: i32) -> chrono::Duration {
+    backoff_delay(attempts, RETRY_BASE_SECONDS, RETRY_MAX_SECONDS)
+}
+
+// Exponential backoff after `attempts` failures: `base_seconds` after the first, doubling
+// up to `max_seconds`, plus up to 10% jitter so that whatever failed together is not all
+// retried at once.
+pub fn backoff_delay(attempts: i32, base_seconds: i64, max_seconds: i64) -> chrono::Duration {
+    let exponent = attempts.clamp(1, 20) as u32 - 1;
+    let seconds = base_seconds.saturating_mul(2_i64.saturating_pow(exponent)).min(max_seconds);
+    let jitter = thread_rng().gen_range(0..=seconds / 10);
+
+    chrono::Duration::seconds(seconds + jitter)
+}
+
+struct RecurringJob {
+    kind: &'static str,
+    cron: String,
+    schedule: Schedule,
+}
+
+// The job kinds this server runs, and which of them recur.
+#[derive(Default)]
+pub struct JobRegistry {
+    handlers: HashMap<&'static str, Arc<dyn JobHandler>>,
+    recurring: Vec<RecurringJob>,
+}
+
+impl JobRegistry {
+    pub fn register(mut self, kind: &'static str, handler: impl JobHandler + 'static) -> Self {
+        self.handlers.insert(kind, Arc::new(handler));
+        self
+    }
+
+    // Enqueues a registered kind on a cron schedule, in UTC, with seconds:
+    // "sec min hour day-of-month month day-of-week", e.g. "0 0 * * * *" for hourly.
+    // A run that is still queued when the next one is due is not doubled up.
+    pub fn schedule(mut self, kind: &'static str, cron: &str) -> Result<Self, String> {
+        if !self.handlers.contains_key(kind) {
+            return Err(format!("Cannot schedule unregistered job kind '{}'", kind));
+        }
+        let schedule = Schedule::from_str(cron).map_err(|e| format!("Invalid schedule for {}: {}", kind, e))?;
+
+        self.recurring.push(RecurringJob { kind, cron: cron.to_string(), schedule });
+        Ok(self)
+    }
+}
+
+pub struct JobRunner {
+    pool: MySqlPool,
+    registry: JobRegistry,
+    workers: usize,
+    poll_interval: Duration,
+    shutdown_timeout: Duration,
+    // Tells this instance's locks apart from those of other instances.
+    instance_id: String,
+}
+
+impl JobRunner {
+    pub fn from_env(pool: MySqlPool, registry: JobRegistry) -> Self {
+        dotenv().ok();
+        let workers = env_or("JOB_WORKERS", 4);
+        let poll_seconds = env_or("JOB_POLL_INTERVAL_SECONDS", 1);
+        let shutdown_seconds = env_or("JOB_SHUTDOWN_TIMEOUT_SECONDS", 30);
+
+        Self {
+            pool,
+            registry,
+            workers: workers.max(1),
+            poll_interval: Duration::from_secs(poll_seconds),
+            shutdown_timeout: Duration::from_secs(shutdown_seconds),
+            instance_id: Uuid::new_v4().simple().to_string()[..12].to_string(),
+        }
+    }
+
+    // Starts the scheduler and the workers. Call `shutdown` on the handle before exiting.
+    pub async fn spawn(self) -> Result<JobRunnerHandle, sqlx::Error> {
+        self.register_schedules().await?;
+
+        let (shutdown, receiver) = watch::channel(false);
+        let shutdown_timeout = self.shutdown_timeout;
+        let workers = self.workers;
+        let runner = Arc::new(self);
+        let mut tasks = JoinSet::new();
+
+        tasks.spawn(runner.clone().schedule_loop(receiver.clone()));
+        for worker in 0..workers {
+            let worker_id = format!("{}-{}", runner.instance_id, worker);
+            tasks.spawn(runner.clone().work_loop(worker_id, receiver.clone()));
+        }
+
+        Ok(JobRunnerHandle { shutdown, tasks, shutdown_timeout })
+    }
+
+    // Adds new schedules, and moves the next run of any whose cron expression changed.
+    async fn register_schedules(&self) -> Result<(), sqlx::Error> {
+        let q = "INSERT INTO job_schedules (name, cron, next_run_at) VALUES (?, ?, ?) \
+                 ON DUPLICATE KEY UPDATE next_run_at = IF(cron = VALUES(cron), next_run_at, VALUES(next_run_at)), cron = VALUES(cron)";
+
+        for recurring in &self.registry.recurring {
+            let Some(next_run_at) = recurring.schedule.upcoming(Utc).next() else {
+                continue;
+            };
+
+            sqlx::query(q)
+                .bind(recurring.kind)
+                .bind(&recurring.cron)
+                .bind(next_run_at)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn schedule_loop(self: Arc<Self>, mut shutdown: watch::Receiver<bool>) {
+        while !*shutdown.borrow() {
+            if let Err(e) = self.enqueue_due_schedules().await {
+                println!("Failed to enqueue recurring jobs: {}", e);
+            }
+            if let Err(e) = self.release_abandoned_jobs().await {
+                println!("Failed to release abandoned jobs: {}", e);
+            }
+
+            tokio::select! {
+                _ = sleep(self.poll_interval.max(Duration::from_secs(1))) => {}
+                changed = shutdown.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn enqueue_due_schedules(&self) -> Result<(), sqlx::Error> {
+        if self.registry.recurring.is_empty() {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let due = sqlx::query_as::<_, JobSchedule>("SELECT * FROM job_schedules WHERE next_run_at <= ?")
+            .bind(now)
+            .fetch_all(&self.pool)
+            .await?;
+
+        for schedule in due {
+            let Some(recurring) = self.registry.recurring.iter().find(|r| r.kind == schedule.name) else {
+                continue;
+            };
+            let Some(next_run_at) = recurring.schedule.after(&now).next() else {
+                continue;
+            };
+
+            // Whoever moves next_run_at on owns this run; other instances see no change.
+            let claimed = sqlx::query("UPDATE job_schedules SET next_run_at = ?, last_run_at = ? WHERE name = ? AND next_run_at = ?")
+                .bind(next_run_at)
+                .bind(now)
+                .bind(&schedule.name)
+                .bind(schedule.next_run_at)
+                .execute(&self.pool)
+                .await?;
+
+            if claimed.rows_affected() == 1 {
+                let options = JobOptions { unique_key: Some(format!("schedule:{}", recurring.kind)), ..JobOptions::default() };
+                if let Err((_, e)) = enqueue(&self.pool, recurring.kind, &serde_json::json!({}), options).await {
+                    println!("Failed to enqueue scheduled {} run: {}", recurring.kind, e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Jobs still running after their lock expired belong to a worker that died or was cut
+    // off at shutdown. They count as a failed attempt.
+    async fn release_abandoned_jobs(&self) -> Result<(), sqlx::Error> {
+        let now = Utc::now();
+        let q = "UPDATE jobs SET \
+                 status = IF(attempts >= max_attempts, 'dead', 'pending'), \
+                 unique_key = IF(attempts >= max_attempts, NULL, unique_key), \
+                 finished_at = IF(attempts >= max_attempts, ?, NULL), \
+                 last_error = 'Abandoned by its worker', locked_by = NULL, locked_until = NULL \
+                 WHERE status = 'running' AND locked_until < ?";
+
+        sqlx::query(q).bind(now).bind(now).execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    async fn work_loop(self: Arc<Self>, worker_id: String, mut shutdown: watch::Receiver<bool>) {
+        while !*shutdown.borrow() {
+            match self.claim(&worker_id).await {
+                // Straight on to the next one while there is work.
+                Ok(Some(job)) => {
+                    self.execute(&worker_id, job).await;
+                    continue;
+                }
+                Ok(None) => {}
+                Err(e) => println!("Failed to claim job: {}", e),
+            }
+
+            tokio::select! {
+                _ = sleep(self.poll_interval) => {}
+                changed = shutdown.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn claim(&self, worker_id: &str) -> Result<Option<Job>, sqlx::Error> {
+        if self.registry.handlers.is_empty() {
+            return Ok(None);
+        }
+
+        // Only kinds this server knows, so that during a deploy an older instance leaves
+        // new kinds to the newer ones.
+        let kinds: Vec<&str> = self.registry.handlers.keys().copied().collect();
+        let placeholders = vec!["?"; kinds.len()].join(", ");
+        let q = format!(
+            "SELECT * FROM jobs WHERE status = 'pending' AND run_at <= ? AND kind IN ({}) \
+             ORDER BY run_at, id LIMIT 1 FOR UPDATE SKIP LOCKED",
+            placeholders
+        );
+
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+        let mut query = sqlx::query_as::<_, Job>(&q).bind(now);
+        for kind in &kinds {
+            query = query.bind(*kind);
+        }
+
+        let Some(mut job) = query.fetch_optional(&mut *tx).await? else {
+            return Ok(None);
+        };
+
+        let timeout = self.registry.handlers[job.kind.as_str()].timeout();
+        let locked_until = now + chrono::Duration::seconds(timeout.as_secs() as i64 + LOCK_SLACK_SECONDS);
+
+        sqlx::query("UPDATE jobs SET status = 'running', attempts = attempts + 1, locked_by = ?, locked_until = ? WHERE id = ?")
+            .bind(worker_id)
+            .bind(locked_until)
+            .bind(job.id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        job.status = "running".to_string();
+        job.attempts += 1;
+        job.locked_by = Some(worker_id.to_string());
+
+        Ok(Some(job))
+    }
+
+    async fn execute(&self, worker_id: &str, job: Job) {
+        let handler = self.registry.handlers[job.kind.as_str()].clone();
+        let timeout = handler.timeout();
+
+        // On a task of its own, so a panicking handler fails the job rather than the worker.
+        let task_job = job.clone();
+        let task = tokio::spawn(async move { handler.run(&task_job).await });
+        let abort = task.abort_handle();
+
+        let result = match tokio::time::timeout(timeout, task).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(e)) => Err(format!("Job panicked: {}", e)),
+            Err(_) => {
+                abort.abort();
+                Err(format!("Timed out after {}s", timeout.as_secs()))
+            }
+        };
+
+        let recorded = match result {
+            Ok(()) => self.complete(worker_id, &job).await,
+            Err(error) => self.fail(worker_id, &job, &error).await,
+        };
+
+        if let Err(e) = recorded {
+            println!("Failed to record result of {} job {}: {}", job.kind, job.id, e);
+        }
+    }
+
+    // The checks on locked_by keep a worker that overran its lock from overwriting the
+    // result of whoever ran the job after it.
+    async fn complete(&self, worker_id: &str, job: &Job) -> Result<(), sqlx::Error> {
+        let q = "UPDATE jobs SET status = 'completed', unique_key = NULL, finished_at = ?, last_error = NULL, \
+                 locked_by = NULL, locked_until = NULL WHERE id = ? AND locked_by = ?";
+
+        sqlx::query(q)
+            .bind(Utc::now())
+            .bind(job.id)
+            .bind(worker_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn fail(&self, worker_id: &str, job: &Job, error: &str) -> Result<(), sqlx::Error> {
+        let error: String = error.chars().take(MAX_ERROR_LENGTH).collect();
+        let now = Utc::now();
+
+        if job.attempts >= job.max_attempts {
+            println!("{} job {} is dead after {} attempts: {}", job.kind, job.id, job.attempts, error);
+
+            let q = "UPDATE jobs SET status = 'dead', unique_key = NULL, finished_at = ?, last_error = ?, \
+                     locked_by = NULL, locked_until = NULL WHERE id = ? AND locked_by = ?";
+            sqlx::query(q)
+                .bind(now)
+                .bind(&error)
+                .bind(job.id)
+                .bind(worker_id)
+                .execute(&self.pool)
+                .await?;
+        } else {
+            let q = "UPDATE jobs SET status = 'pending', run_at = ?, last_error = ?, \
+                     locked_by = NULL, locked_until = NULL WHERE id = ? AND locked_by = ?";
+            sqlx::query(q)
+                .bind(now + retry_delay(job.attempts))
+                .bind(&error)
+                .bind(job.id)
+                .bind(worker_id)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct JobRunnerHandle {
+    shutdown: watch::Sender<bool>,
+    tasks: JoinSet<()>,
+    shutdown_timeout: Duration,
+}
+
+impl JobRunnerHandle {
+    // Stops claiming jobs and waits for the running ones to finish.
+    pub async fn shutdown(mut self) {
+        let _ = self.shutdown.send(true);
+
+        let tasks = &mut self.tasks;
+        let drained = tokio::time::timeout(self.shutdown_timeout, async move {
+            while tasks.join_next().await.is_some() {}
+        })
+        .await;
+
+        if drained.is_err() {
+            println!("Jobs still running after {}s were cut off and will be retried", self.shutdown_timeout.as_secs());
+        }
+    }
+}
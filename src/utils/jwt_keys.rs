@@ -0,0 +1,210 @@
+// Signing keys for access tokens.
+//
+// JWT_ALGORITHM selects the scheme:
+//   HS256 (default) - a single shared secret from SECRET_KEY. Nothing is published in the JWKS.
+//   RS256 / EdDSA   - every `<kid>.pem` file in JWT_KEYS_DIR is loaded. Private keys (PKCS#8)
+//                     can sign and verify, `<kid>.pub.pem` public keys can only verify.
+//                     JWT_ACTIVE_KID names the key used for new tokens.
+//
+// Rotating: drop the new private key into the directory, point JWT_ACTIVE_KID at it and restart.
+// Keep the old key around (optionally as `<old>.pub.pem`) until every token it signed has expired.
+//
+//   openssl genpkey -algorithm RSA -pkeyopt rsa_keygen_bits:2048 -out keys/2024-08.pem
+//   openssl genpkey -algorithm ED25519 -out keys/2024-08.pem
+use std::{env, fs, path::Path, sync::OnceLock};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use dotenv::dotenv;
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey,
+};
+use rsa::{pkcs1::DecodeRsaPrivateKey, traits::PublicKeyParts, RsaPrivateKey, RsaPublicKey};
+
+pub struct JwtKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    // None for verification-only keys.
+    pub encoding_key: Option<EncodingKey>,
+    pub decoding_key: DecodingKey,
+    // Public half, published through the JWKS endpoint. None for shared secrets.
+    pub jwk: Option<Jwk>,
+}
+
+pub struct KeySet {
+    pub active_kid: String,
+    pub keys: Vec<JwtKey>,
+}
+
+impl KeySet {
+    pub fn from_env() -> Result<Self, String> {
+        dotenv().ok();
+        let algorithm = env::var("JWT_ALGORITHM").unwrap_or_else(|_| "HS256".to_string());
+
+        match algorithm.as_str() {
+            "HS256" => {
+                let secret_key = env::var("SECRET_KEY").map_err(|_| "Secret key not found".to_string())?;
+                let kid = env::var("JWT_ACTIVE_KID").unwrap_or_else(|_| "default".to_string());
+
+                Ok(Self {
+                    active_kid: kid.clone(),
+                    keys: vec![JwtKey {
+                        kid,
+                        algorithm: Algorithm::HS256,
+                        encoding_key: Some(EncodingKey::from_secret(secret_key.as_bytes())),
+                        decoding_key: DecodingKey::from_secret(secret_key.as_bytes()),
+                        jwk: None,
+                    }],
+                })
+            }
+            "RS256" | "EdDSA" => {
+                let algorithm = if algorithm == "RS256" { Algorithm::RS256 } else { Algorithm::EdDSA };
+                let keys_dir = env::var("JWT_KEYS_DIR").map_err(|_| "JWT_KEYS_DIR not set".to_string())?;
+                let active_kid = env::var("JWT_ACTIVE_KID").map_err(|_| "JWT_ACTIVE_KID not set".to_string())?;
+
+                let keys = load_keys(Path::new(&keys_dir), algorithm)?;
+
+                let can_sign = keys
+                    .iter()
+                    .any(|k| k.kid == active_kid && k.encoding_key.is_some());
+                if !can_sign {
+                    return Err(format!("No private key found for active kid '{}'", active_kid));
+                }
+
+                Ok(Self { active_kid, keys })
+            }
+            other => Err(format!("Unsupported JWT_ALGORITHM '{}'", other)),
+        }
+    }
+
+    // Key used to sign new tokens.
+    pub fn active(&self) -> &JwtKey {
+        self.find(&self.active_kid)
+            .expect("Active signing key missing from key set")
+    }
+
+    pub fn find(&self, kid: &str) -> Option<&JwtKey> {
+        self.keys.iter().find(|k| k.kid == kid)
+    }
+
+    // Public keys other services need to verify our tokens.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().filter_map(|k| k.jwk.clone()).collect(),
+        }
+    }
+}
+
+// Key set shared by the whole process, loaded on first use.
+pub fn keyset() -> &'static KeySet {
+    static KEYSET: OnceLock<KeySet> = OnceLock::new();
+    KEYSET.get_or_init(|| KeySet::from_env().expect("Failed to load JWT signing keys"))
+}
+
+fn load_keys(dir: &Path, algorithm: Algorithm) -> Result<Vec<JwtKey>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read JWT_KEYS_DIR: {}", e))?;
+    let mut keys = vec![];
+
+    for entry in entries {
+        let path = entry.map_err(|e| format!("Failed to read JWT_KEYS_DIR: {}", e))?.path();
+        let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+
+        let (kid, public_only) = if let Some(kid) = file_name.strip_suffix(".pub.pem") {
+            (kid.to_string(), true)
+        } else if let Some(kid) = file_name.strip_suffix(".pem") {
+            (kid.to_string(), false)
+        } else {
+            continue;
+        };
+
+        let pem = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", file_name, e))?;
+
+        let key = match algorithm {
+            Algorithm::RS256 => load_rsa_key(&kid, &pem, public_only),
+            _ => load_ed_key(&kid, &pem, public_only),
+        }
+        .map_err(|e| format!("Invalid key {}: {}", file_name, e))?;
+
+        // A private key wins over a public-only file for the same kid.
+        keys.retain(|k: &JwtKey| !(k.kid == key.kid && k.encoding_key.is_none()));
+        if !keys.iter().any(|k| k.kid == key.kid) {
+            keys.push(key);
+        }
+    }
+
+    keys.sort_by(|a, b| a.kid.cmp(&b.kid));
+    Ok(keys)
+}
+
+fn load_rsa_key(kid: &str, pem: &str, public_only: bool) -> Result<JwtKey, String> {
+    let (public_key, encoding_key) = if public_only {
+        let public_key = RsaPublicKey::from_public_key_pem(pem).map_err(|e| e.to_string())?;
+        (public_key, None)
+    } else {
+        let private_key = RsaPrivateKey::from_pkcs8_pem(pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+            .map_err(|e| e.to_string())?;
+        let encoding_key = EncodingKey::from_rsa_pem(pem.as_bytes()).map_err(|e| e.to_string())?;
+        (private_key.to_public_key(), Some(encoding_key))
+    };
+
+    let jwk = Jwk {
+        common: common_parameters(kid, KeyAlgorithm::RS256),
+        algorithm: AlgorithmParameters::RSA(RSAKeyParameters {
+            key_type: RSAKeyType::RSA,
+            n: URL_SAFE_NO_PAD.encode(public_key.n().to_bytes_be()),
+            e: URL_SAFE_NO_PAD.encode(public_key.e().to_bytes_be()),
+        }),
+    };
+
+    build_key(kid, Algorithm::RS256, encoding_key, jwk)
+}
+
+fn load_ed_key(kid: &str, pem: &str, public_only: bool) -> Result<JwtKey, String> {
+    let (public_key, encoding_key) = if public_only {
+        let public_key = ed25519_dalek::VerifyingKey::from_public_key_pem(pem).map_err(|e| e.to_string())?;
+        (public_key, None)
+    } else {
+        let private_key = ed25519_dalek::SigningKey::from_pkcs8_pem(pem).map_err(|e| e.to_string())?;
+        let encoding_key = EncodingKey::from_ed_pem(pem.as_bytes()).map_err(|e| e.to_string())?;
+        (private_key.verifying_key(), Some(encoding_key))
+    };
+
+    let jwk = Jwk {
+        common: common_parameters(kid, KeyAlgorithm::EdDSA),
+        algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+            key_type: OctetKeyPairType::OctetKeyPair,
+            curve: EllipticCurve::Ed25519,
+            x: URL_SAFE_NO_PAD.encode(public_key.as_bytes()),
+        }),
+    };
+
+    build_key(kid, Algorithm::EdDSA, encoding_key, jwk)
+}
+
+fn build_key(kid: &str, algorithm: Algorithm, encoding_key: Option<EncodingKey>, jwk: Jwk) -> Result<JwtKey, String> {
+    let decoding_key = DecodingKey::from_jwk(&jwk).map_err(|e| e.to_string())?;
+
+    Ok(JwtKey {
+        kid: kid.to_string(),
+        algorithm,
+        encoding_key,
+        decoding_key,
+        jwk: Some(jwk),
+    })
+}
+
+fn common_parameters(kid: &str, key_algorithm: KeyAlgorithm) -> CommonParameters {
+    CommonParameters {
+        public_key_use: Some(PublicKeyUse::Signature),
+        key_algorithm: Some(key_algorithm),
+        key_id: Some(kid.to_string()),
+        ..Default::default()
+    }
+}
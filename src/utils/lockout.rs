@@ -0,0 +1,180 @@
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Duration, Utc};
+use dotenv::dotenv;
+use sqlx::MySqlPool;
+
+use crate::{
+    models::{auth::ResponseMessage, login_lockout::LoginLockout},
+    utils::env::env_or,
+};
+
+// Brute-force protection settings for login. All values can be overridden through env vars.
+#[derive(Debug, Clone)]
+pub struct LockoutPolicy {
+    // Failures allowed before the first lockout kicks in.
+    pub max_failed_attempts: i32,
+    // Length of the first lockout. Every further failure doubles it.
+    pub base_lockout_seconds: i64,
+    // Upper bound for a single lockout.
+    pub max_lockout_seconds: i64,
+    // Failures older than this no longer count towards the threshold.
+    pub failure_window_seconds: i64,
+}
+
+impl LockoutPolicy {
+    pub fn from_env() -> Self {
+        dotenv().ok();
+        Self {
+            max_failed_attempts: env_or("LOGIN_MAX_FAILED_ATTEMPTS", 5),
+            base_lockout_seconds: env_or("LOGIN_LOCKOUT_BASE_SECONDS", 30),
+            max_lockout_seconds: env_or("LOGIN_LOCKOUT_MAX_SECONDS", 3600),
+            failure_window_seconds: env_or("LOGIN_FAILURE_WINDOW_SECONDS", 900),
+        }
+    }
+
+    // Lockout for the given number of consecutive failures, if any.
+    pub fn lockout_duration(&self, failed_attempts: i32) -> Option<Duration> {
+        if failed_attempts < self.max_failed_attempts {
+            return None;
+        }
+
+        let exponent = (failed_attempts - self.max_failed_attempts).min(30) as u32;
+        let seconds = self
+            .base_lockout_seconds
+            .saturating_mul(2_i64.saturating_pow(exponent))
+            .min(self.max_lockout_seconds);
+
+        Some(Duration::seconds(seconds))
+    }
+}
+
+pub fn user_key(username: &str) -> String {
+    format!("user:{}", username.to_lowercase())
+}
+
+pub fn ip_key(ip: &str) -> String {
+    format!("ip:{}", ip)
+}
+
+// Seconds until every lock on the given keys has expired, or None if nothing is locked.
+pub async fn retry_after(pool: &MySqlPool, keys: &[String]) -> Result<Option<i64>, (StatusCode, String)> {
+    let mut retry_after = None;
+
+    for key in keys {
+        let lockout = fetch_lockout(pool, key).await?;
+
+        if let Some(locked_until) = lockout.and_then(|l| l.locked_until) {
+            let remaining = (locked_until.with_timezone(&Utc) - Utc::now()).num_seconds();
+            if remaining > 0 {
+                retry_after = Some(retry_after.map_or(remaining, |r: i64| r.max(remaining)));
+            }
+        }
+    }
+
+    Ok(retry_after)
+}
+
+// Counts a failed login against the key and locks it once the policy threshold is reached.
+pub async fn register_failure(
+    pool: &MySqlPool,
+    policy: &LockoutPolicy,
+    key: &str,
+) -> Result<(), (StatusCode, String)> {
+    let now = Utc::now();
+    let window_start = now - Duration::seconds(policy.failure_window_seconds);
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))?;
+
+    // Counted in the database, so parallel failures all count. The upsert also locks the row
+    // until commit, so locked_until is set from the count of this failure. MySQL assigns left
+    // to right: failed_attempts still sees the previous last_failed_at.
+    let q = "INSERT INTO login_lockouts (lock_key, failed_attempts, last_failed_at) VALUES (?, 1, ?) \
+             ON DUPLICATE KEY UPDATE failed_attempts = IF(last_failed_at > ?, failed_attempts + 1, 1), last_failed_at = VALUES(last_failed_at)";
+
+    sqlx::query(q)
+        .bind(key)
+        .bind(now)
+        .bind(window_start)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store login failure in database: {}", e)))?;
+
+    let failed_attempts = sqlx::query_scalar::<_, i32>("SELECT failed_attempts FROM login_lockouts WHERE lock_key = ?")
+        .bind(key)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch login lockout from database: {}", e)))?;
+
+    let locked_until = policy
+        .lockout_duration(failed_attempts)
+        .map(|duration| now + duration);
+
+    sqlx::query("UPDATE login_lockouts SET locked_until = ? WHERE lock_key = ?")
+        .bind(locked_until)
+        .bind(key)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store login lockout in database: {}", e)))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit login failure: {}", e)))?;
+
+    Ok(())
+}
+
+pub async fn clear_lockout(pool: &MySqlPool, key: &str) -> Result<(), (StatusCode, String)> {
+    sqlx::query("DELETE FROM login_lockouts WHERE lock_key = ?")
+        .bind(key)
+        .execute(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to clear login lockout in database: {}", e)))?;
+
+    Ok(())
+}
+
+// Audit trail of every login attempt, successful or not.
+pub async fn record_attempt(
+    pool: &MySqlPool,
+    username: &str,
+    ip_address: &str,
+    succeeded: bool,
+    reason: Option<&str>,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query("INSERT INTO login_attempts (username, ip_address, succeeded, reason) VALUES (?, ?, ?, ?)")
+        .bind(username)
+        .bind(ip_address)
+        .bind(succeeded)
+        .bind(reason)
+        .execute(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to record login attempt in database: {}", e)))?;
+
+    Ok(())
+}
+
+pub fn locked_response(retry_after: i64) -> Response {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, retry_after.to_string())],
+        Json(ResponseMessage {
+            message: format!("Too many failed login attempts. Try again in {} seconds.", retry_after),
+        }),
+    )
+        .into_response()
+}
+
+async fn fetch_lockout(pool: &MySqlPool, key: &str) -> Result<Option<LoginLockout>, (StatusCode, String)> {
+    sqlx::query_as::<_, LoginLockout>("SELECT * FROM login_lockouts WHERE lock_key = ?")
+        .bind(key)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch login lockout from database: {}", e)))
+}
@@ -0,0 +1,91 @@
+use std::{env, sync::Arc};
+
+use async_trait::async_trait;
+use dotenv::dotenv;
+use tokio::{fs::OpenOptions, io::AsyncWriteExt};
+
+// Anything that can deliver an email.
+// Swap in a real provider (SMTP, SES, ...) by implementing this trait.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), String>;
+
+    // An email with an HTML body and a plain text one for clients that do not show HTML.
+    // Mailers that cannot send HTML send the text alone.
+    async fn send_html(&self, to: &str, subject: &str, text: &str, _html: &str) -> Result<(), String> {
+        self.send(to, subject, text).await
+    }
+}
+
+// Prints emails to stdout. Useful for local development.
+pub struct ConsoleMailer;
+
+#[async_trait]
+impl Mailer for ConsoleMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), String> {
+        println!("Email to {}: {}\n{}", to, subject, body);
+        Ok(())
+    }
+}
+
+// Appends emails to a file so they can be inspected after the fact, e.g. by a test that
+// needs the link from a login email.
+pub struct FileMailer {
+    pub path: String,
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), String> {
+        self.append(&format!("{}\n{}\n\n", headers(to, subject), body)).await
+    }
+
+    // Written as multipart/alternative, the way it would go over SMTP.
+    async fn send_html(&self, to: &str, subject: &str, text: &str, html: &str) -> Result<(), String> {
+        let boundary = format!("alt-{}", uuid::Uuid::new_v4().simple());
+        let entry = format!(
+            "{}MIME-Version: 1.0\nContent-Type: multipart/alternative; boundary=\"{b}\"\n\n\
+             --{b}\nContent-Type: text/plain; charset=utf-8\n\n{}\n\
+             --{b}\nContent-Type: text/html; charset=utf-8\n\n{}\n\
+             --{b}--\n\n",
+            headers(to, subject),
+            text,
+            html,
+            b = boundary
+        );
+
+        self.append(&entry).await
+    }
+}
+
+impl FileMailer {
+    async fn append(&self, entry: &str) -> Result<(), String> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|e| format!("Failed to open mail outbox: {}", e))?;
+
+        file.write_all(entry.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write mail outbox: {}", e))?;
+
+        Ok(())
+    }
+}
+
+fn headers(to: &str, subject: &str) -> String {
+    format!("Date: {}\nTo: {}\nSubject: {}\n", chrono::Utc::now().to_rfc3339(), to, subject)
+}
+
+// Picks the mailer from MAILER ("console" or "file"). Defaults to console.
+pub fn mailer_from_env() -> Arc<dyn Mailer> {
+    dotenv().ok();
+    match env::var("MAILER").as_deref() {
+        Ok("file") => Arc::new(FileMailer {
+            path: env::var("MAIL_OUTBOX_PATH").unwrap_or_else(|_| "mail_outbox.log".to_string()),
+        }),
+        _ => Arc::new(ConsoleMailer),
+    }
+}
@@ -0,0 +1,195 @@
+// The in-app inbox. `notify` stores a notification and pushes it to the user's connected
+// clients as a "notification.created" event, followed by the new unread count.
+use async_trait::async_trait;
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts, StatusCode},
+};
+use chrono::Utc;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::MySqlPool;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        notification::{Notification, UnreadCount},
+        user::User,
+    },
+    utils::{
+        cookies::{device_cookie, DEVICE_COOKIE},
+        events::{self, EventBus},
+    },
+};
+
+pub async fn notify(
+    pool: &MySqlPool,
+    bus: &EventBus,
+    user_id: i32,
+    kind: &str,
+    title: &str,
+    body: &str,
+    data: Option<Value>,
+) -> Result<Notification, String> {
+    // The notification and both events are committed together or not at all.
+    let mut tx = pool.begin().await.map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let result = sqlx::query("INSERT INTO notifications (user_id, kind, title, body, data) VALUES (?, ?, ?, ?, ?)")
+        .bind(user_id)
+        .bind(kind)
+        .bind(title)
+        .bind(body)
+        .bind(data.map(|data| data.to_string()))
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to store notification: {}", e))?;
+
+    let notification = sqlx::query_as::<_, Notification>("SELECT * FROM notifications WHERE id = ?")
+        .bind(result.last_insert_id() as i64)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to fetch notification: {}", e))?;
+
+    let unread_count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM notifications WHERE user_id = ? AND read_at IS NULL")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to count unread notifications: {}", e))?;
+
+    let created = events::record(&mut *tx, user_id, "notification.created", &notification).await.map_err(|(_, e)| e)?;
+    let counted = events::record(&mut *tx, user_id, "notification.unread_count", &UnreadCount { unread_count })
+        .await
+        .map_err(|(_, e)| e)?;
+
+    tx.commit().await.map_err(|e| format!("Failed to commit notification: {}", e))?;
+
+    bus.send([created, counted]);
+
+    Ok(notification)
+}
+
+pub async fn unread_count(pool: &MySqlPool, user_id: i32) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM notifications WHERE user_id = ? AND read_at IS NULL")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn publish_unread_count(pool: &MySqlPool, bus: &EventBus, user_id: i32) {
+    match unread_count(pool, user_id).await {
+        Ok(unread_count) => bus.publish(user_id, "notification.unread_count", &UnreadCount { unread_count }).await,
+        Err(e) => println!("Failed to count unread notifications: {}", e),
+    }
+}
+
+// Extractor for login handlers. Remembers the devices each user signs in from and tells
+// them about sign-ins from new ones; the first device an account uses is not worth a
+// notification. Never fails the login: errors are only logged.
+pub struct LoginDevices {
+    pool: MySqlPool,
+    bus: EventBus,
+    cookies: Option<Cookies>,
+    user_agent: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for LoginDevices
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let missing = || (StatusCode::INTERNAL_SERVER_ERROR, "Login device tracking is not configured".to_string());
+        let pool = parts.extensions.get::<MySqlPool>().cloned().ok_or_else(missing)?;
+        let bus = parts.extensions.get::<EventBus>().cloned().ok_or_else(missing)?;
+        let cookies = Cookies::from_request_parts(parts, state).await.ok();
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(512).collect());
+
+        Ok(Self { pool, bus, cookies, user_agent })
+    }
+}
+
+impl LoginDevices {
+    // Browsers are told apart by the device cookie, set on their first login.
+    pub async fn notice_browser_login(&self, user: &User, ip_address: &str) {
+        let existing = self
+            .cookies
+            .as_ref()
+            .and_then(|cookies| cookies.get(DEVICE_COOKIE))
+            .map(|cookie| cookie.value().to_string())
+            .filter(|value| !value.is_empty());
+
+        let device_id = match existing {
+            Some(device_id) => device_id,
+            None => {
+                let device_id = Uuid::new_v4().simple().to_string();
+                if let Some(cookies) = &self.cookies {
+                    cookies.add(device_cookie(device_id.clone()));
+                }
+                device_id
+            }
+        };
+
+        self.notice(user, ip_address, &format!("cookie:{}", device_id)).await;
+    }
+
+    // Clients without cookies are told apart by their user agent.
+    pub async fn notice_api_login(&self, user: &User, ip_address: &str) {
+        let device_key = format!("agent:{}", self.user_agent.as_deref().unwrap_or_default());
+        self.notice(user, ip_address, &device_key).await;
+    }
+
+    async fn notice(&self, user: &User, ip_address: &str, device_key: &str) {
+        let device_hash = format!("{:x}", Sha256::digest(device_key.as_bytes()));
+
+        let q = "INSERT INTO known_devices (user_id, device_hash, user_agent, ip_address, last_seen_at) VALUES (?, ?, ?, ?, ?) \
+                 ON DUPLICATE KEY UPDATE ip_address = VALUES(ip_address), last_seen_at = VALUES(last_seen_at)";
+
+        let inserted = sqlx::query(q)
+            .bind(user.id)
+            .bind(&device_hash)
+            .bind(&self.user_agent)
+            .bind(ip_address)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .map(|result| result.rows_affected() == 1);
+
+        let new_device = match inserted {
+            Ok(new_device) => new_device,
+            Err(e) => {
+                println!("Failed to record login device: {}", e);
+                return;
+            }
+        };
+        if !new_device {
+            return;
+        }
+
+        let known_devices = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM known_devices WHERE user_id = ?")
+            .bind(user.id)
+            .fetch_one(&self.pool)
+            .await
+            .unwrap_or(0);
+        if known_devices <= 1 {
+            return;
+        }
+
+        let device = self.user_agent.as_deref().unwrap_or("an unknown device");
+        let body = format!(
+            "Your account was signed in to from {} ({}). If this wasn't you, change your password.",
+            device, ip_address
+        );
+        let data = json!({ "ip_address": ip_address, "user_agent": self.user_agent });
+
+        if let Err(e) = notify(&self.pool, &self.bus, user.id, "auth.new_device", "New sign-in", &body, Some(data)).await {
+            println!("Failed to notify user {} of sign-in from a new device: {}", user.id, e);
+        }
+    }
+}
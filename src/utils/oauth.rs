@@ -0,0 +1,281 @@
+// Helpers for the OAuth 2.0 authorization server (see controllers::oauth_controller).
+//
+// Every OAuth credential is an opaque random string with a recognisable prefix:
+//   tdc_  public client id          tdcs_ client secret
+//   tdo_  authorization code        tda_  access token        tdr_  refresh token
+// Only SHA-256 hashes of secrets, codes and tokens are stored.
+use std::fmt::Display;
+
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    Json,
+};
+use base64::{engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD}, Engine};
+use chrono::{Duration, Utc};
+use dotenv::dotenv;
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use sha2::{Digest, Sha256};
+use sqlx::MySqlPool;
+
+use crate::{
+    models::{
+        auth::{Claims, ResponseMessage},
+        oauth::{OAuthAccess, OAuthError, OAuthToken, OAuthTokenResponse},
+        oauth_client::OAuthClient,
+    },
+    utils::{cookies::constant_time_eq, env::env_or, tokens::ACCESS_TOKEN_TTL_MINUTES},
+};
+
+pub const CLIENT_ID_PREFIX: &str = "tdc_";
+pub const CLIENT_SECRET_PREFIX: &str = "tdcs_";
+pub const AUTHORIZATION_CODE_PREFIX: &str = "tdo_";
+pub const ACCESS_TOKEN_PREFIX: &str = "tda_";
+pub const REFRESH_TOKEN_PREFIX: &str = "tdr_";
+
+pub const AUTHORIZATION_CODE_TTL_MINUTES: i64 = 10;
+
+pub type OAuthErrorResponse = (StatusCode, Json<OAuthError>);
+
+pub fn oauth_error(status: StatusCode, error: &str, description: impl Into<String>) -> OAuthErrorResponse {
+    (
+        status,
+        Json(OAuthError {
+            error: error.to_string(),
+            error_description: description.into(),
+        }),
+    )
+}
+
+pub fn server_error(context: &str, e: impl Display) -> OAuthErrorResponse {
+    oauth_error(StatusCode::INTERNAL_SERVER_ERROR, "server_error", format!("{}: {}", context, e))
+}
+
+pub fn random_token(prefix: &str, len: usize) -> String {
+    let secret: String = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect();
+
+    format!("{}{}", prefix, secret)
+}
+
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+// Lets check_token_auth tell OAuth access tokens apart from first-party JWTs.
+pub fn is_oauth_access_token(token: &str) -> bool {
+    token.starts_with(ACCESS_TOKEN_PREFIX)
+}
+
+// Lifetime of OAuth refresh tokens, OAUTH_REFRESH_TOKEN_TTL_DAYS (default 30).
+pub fn refresh_token_ttl() -> Duration {
+    dotenv().ok();
+    let days = env_or("OAUTH_REFRESH_TOKEN_TTL_DAYS", 30);
+
+    Duration::days(days)
+}
+
+// PKCE with S256 (RFC 7636): the challenge is BASE64URL(SHA-256(verifier)) without padding.
+pub fn verify_pkce(code_verifier: &str, code_challenge: &str) -> bool {
+    let well_formed = (43..=128).contains(&code_verifier.len())
+        && code_verifier
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~'));
+
+    if !well_formed {
+        return false;
+    }
+
+    constant_time_eq(pkce_challenge(code_verifier).as_bytes(), code_challenge.as_bytes())
+}
+
+pub fn pkce_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+// Normalises a requested scope string. Every scope has to be allowed for the client;
+// without a request the client gets everything it is allowed.
+pub fn resolve_scopes(requested: Option<&str>, allowed: &str) -> Result<String, OAuthErrorResponse> {
+    let requested = match requested.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(requested) => requested,
+        None => return Ok(allowed.to_string()),
+    };
+
+    let allowed: Vec<&str> = allowed.split_whitespace().collect();
+    let mut scopes: Vec<&str> = vec![];
+    for scope in requested.split_whitespace() {
+        if !allowed.contains(&scope) {
+            return Err(oauth_error(StatusCode::BAD_REQUEST, "invalid_scope", format!("Scope '{}' is not allowed", scope)));
+        }
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+
+    Ok(scopes.join(" "))
+}
+
+pub async fn fetch_client_by_client_id(pool: &MySqlPool, client_id: &str) -> Result<Option<OAuthClient>, OAuthErrorResponse> {
+    sqlx::query_as::<_, OAuthClient>("SELECT * FROM oauth_clients WHERE client_id = ? AND is_active = true")
+        .bind(client_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| server_error("Failed to fetch oauth client from database", e))
+}
+
+// Client authentication for the token, introspection and revocation endpoints.
+// Credentials come from an HTTP Basic Authorization header or, failing that, the form body.
+// Confidential clients must present their secret; public clients identify themselves by
+// client_id only.
+pub async fn authenticate_client(
+    pool: &MySqlPool,
+    headers: &HeaderMap,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> Result<OAuthClient, OAuthErrorResponse> {
+    let invalid_client = || oauth_error(StatusCode::UNAUTHORIZED, "invalid_client", "Client authentication failed");
+
+    let (client_id, client_secret) = match basic_credentials(headers) {
+        Some((id, secret)) => (id, Some(secret)),
+        None => (
+            client_id.ok_or_else(invalid_client)?.to_string(),
+            client_secret.map(str::to_string),
+        ),
+    };
+
+    let client = fetch_client_by_client_id(pool, &client_id)
+        .await?
+        .ok_or_else(invalid_client)?;
+
+    match (&client.client_secret_hash, client_secret) {
+        (Some(expected), Some(secret)) if constant_time_eq(expected.as_bytes(), hash_token(&secret).as_bytes()) => Ok(client),
+        (None, None) => Ok(client),
+        _ => Err(invalid_client()),
+    }
+}
+
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, encoded) = value.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("Basic") {
+        return None;
+    }
+
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (id, secret) = decoded.split_once(':')?;
+    Some((id.to_string(), secret.to_string()))
+}
+
+// Creates an access and refresh token pair belonging to the given token family.
+pub async fn issue_oauth_tokens(
+    pool: &MySqlPool,
+    client_id: i32,
+    user_id: i32,
+    scopes: &str,
+    family: &str,
+) -> Result<OAuthTokenResponse, OAuthErrorResponse> {
+    let now = Utc::now();
+    let access_token = random_token(ACCESS_TOKEN_PREFIX, 40);
+    let refresh_token = random_token(REFRESH_TOKEN_PREFIX, 48);
+
+    let q = "INSERT INTO oauth_tokens (token_hash, token_type, family, client_id, user_id, scopes, expires_at) VALUES (?, ?, ?, ?, ?, ?, ?), (?, ?, ?, ?, ?, ?, ?)";
+    sqlx::query(q)
+        .bind(hash_token(&access_token))
+        .bind("access")
+        .bind(family)
+        .bind(client_id)
+        .bind(user_id)
+        .bind(scopes)
+        .bind(now + Duration::minutes(ACCESS_TOKEN_TTL_MINUTES))
+        .bind(hash_token(&refresh_token))
+        .bind("refresh")
+        .bind(family)
+        .bind(client_id)
+        .bind(user_id)
+        .bind(scopes)
+        .bind(now + refresh_token_ttl())
+        .execute(pool)
+        .await
+        .map_err(|e| server_error("Failed to store oauth tokens in database", e))?;
+
+    Ok(OAuthTokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: ACCESS_TOKEN_TTL_MINUTES * 60,
+        refresh_token,
+        scope: scopes.to_string(),
+    })
+}
+
+// Looks a token up by its hash, whatever its type or state.
+pub async fn find_token(pool: &MySqlPool, token: &str) -> Result<Option<OAuthToken>, OAuthErrorResponse> {
+    sqlx::query_as::<_, OAuthToken>("SELECT * FROM oauth_tokens WHERE token_hash = ?")
+        .bind(hash_token(token))
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| server_error("Failed to fetch oauth token from database", e))
+}
+
+// Returns false when the token had already been revoked, e.g. by a concurrent request.
+pub async fn revoke_token(pool: &MySqlPool, id: i32) -> Result<bool, OAuthErrorResponse> {
+    let result = sqlx::query("UPDATE oauth_tokens SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL")
+        .bind(Utc::now())
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| server_error("Failed to revoke oauth token in database", e))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Revokes every token issued from the same authorization.
+pub async fn revoke_family(pool: &MySqlPool, family: &str) -> Result<(), OAuthErrorResponse> {
+    sqlx::query("UPDATE oauth_tokens SET revoked_at = ? WHERE family = ? AND revoked_at IS NULL")
+        .bind(Utc::now())
+        .bind(family)
+        .execute(pool)
+        .await
+        .map_err(|e| server_error("Failed to revoke oauth tokens in database", e))?;
+
+    Ok(())
+}
+
+pub fn is_active(token: &OAuthToken) -> bool {
+    token.revoked_at.is_none() && token.expires_at > Utc::now()
+}
+
+// Used by check_token_auth for Bearer tokens issued through OAuth.
+pub async fn authenticate_access_token(
+    pool: &MySqlPool,
+    token: &str,
+) -> Result<(Claims, OAuthAccess), (StatusCode, Json<ResponseMessage>)> {
+    let unauthorized = |message: &str| (StatusCode::UNAUTHORIZED, Json(ResponseMessage { message: message.to_string() }));
+
+    // Tokens stop working as soon as their client is deactivated.
+    let q = "SELECT t.* FROM oauth_tokens t JOIN oauth_clients c ON c.id = t.client_id WHERE t.token_hash = ? AND t.token_type = 'access' AND c.is_active = true";
+    let token = sqlx::query_as::<_, OAuthToken>(q)
+        .bind(hash_token(token))
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ResponseMessage { message: format!("Failed to fetch oauth token from database: {}", e) })))?
+        .ok_or_else(|| unauthorized("Invalid token"))?;
+
+    if !is_active(&token) {
+        return Err(unauthorized("Token expired or revoked"));
+    }
+
+    let claims = Claims {
+        sub: token.user_id.to_string(),
+        exp: token.expires_at.timestamp() as usize,
+        act: None,
+        wid: None,
+    };
+    let access = OAuthAccess {
+        client_id: token.client_id,
+        scopes: token.scopes,
+    };
+
+    Ok((claims, access))
+}
@@ -0,0 +1,272 @@
+// Sign in with external OpenID Connect providers (we are the relying party).
+//
+// OIDC_PROVIDERS lists the provider names, e.g. "acme,okta". For each name:
+//   OIDC_<NAME>_ISSUER         issuer URL; endpoints are discovered from <issuer>/.well-known/openid-configuration
+//   OIDC_<NAME>_CLIENT_ID      client id registered at the provider
+//   OIDC_<NAME>_CLIENT_SECRET  optional, for confidential clients
+//   OIDC_<NAME>_REDIRECT_URI   e.g. https://todos.example.com/api/auth/oidc/acme/callback
+//   OIDC_<NAME>_SCOPES         default "openid email profile"
+// OIDC_POST_LOGIN_REDIRECT is where the browser ends up after a successful login (default "/").
+use std::{collections::HashMap, env, sync::Arc, time::Duration};
+
+use axum::http::StatusCode;
+use dotenv::dotenv;
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use tokio::sync::RwLock;
+use url::Url;
+
+use crate::models::oidc::{IdTokenClaims, ProviderMetadata, ProviderTokenResponse};
+use crate::utils::cookies::constant_time_eq;
+
+// Only asymmetric algorithms: a provider's JWKS never contains shared secrets.
+const ID_TOKEN_ALGORITHMS: [Algorithm; 8] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::EdDSA,
+];
+
+#[derive(Debug, Clone)]
+pub struct OidcProvider {
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: String,
+}
+
+// Configured providers plus discovery documents and signing keys, fetched on first use.
+#[derive(Clone)]
+pub struct Oidc {
+    providers: Arc<HashMap<String, OidcProvider>>,
+    http: reqwest::Client,
+    metadata: Arc<RwLock<HashMap<String, ProviderMetadata>>>,
+    jwks: Arc<RwLock<HashMap<String, JwkSet>>>,
+    pub post_login_redirect: String,
+}
+
+impl Oidc {
+    pub fn from_env() -> Result<Self, String> {
+        dotenv().ok();
+        let names = env::var("OIDC_PROVIDERS").unwrap_or_default();
+        let mut providers = Vec::new();
+
+        for name in names.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let var = |key: &str| env::var(format!("OIDC_{}_{}", name.to_uppercase(), key)).ok().filter(|v| !v.is_empty());
+            let required = |key: &str| var(key).ok_or(format!("OIDC_{}_{} not set", name.to_uppercase(), key));
+
+            providers.push(OidcProvider {
+                name: name.to_string(),
+                issuer: required("ISSUER")?,
+                client_id: required("CLIENT_ID")?,
+                client_secret: var("CLIENT_SECRET"),
+                redirect_uri: required("REDIRECT_URI")?,
+                scopes: var("SCOPES").unwrap_or_else(|| "openid email profile".to_string()),
+            });
+        }
+
+        Self::new(providers, env::var("OIDC_POST_LOGIN_REDIRECT").unwrap_or_else(|_| "/".to_string()))
+    }
+
+    pub fn new(providers: Vec<OidcProvider>, post_login_redirect: String) -> Result<Self, String> {
+        let providers = providers
+            .into_iter()
+            .map(|provider| {
+                let issuer = provider.issuer.trim_end_matches('/').to_string();
+                (provider.name.clone(), OidcProvider { issuer, ..provider })
+            })
+            .collect();
+
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+        Ok(Self {
+            providers: Arc::new(providers),
+            http,
+            metadata: Arc::default(),
+            jwks: Arc::default(),
+            post_login_redirect,
+        })
+    }
+
+    pub fn provider(&self, name: &str) -> Result<&OidcProvider, (StatusCode, String)> {
+        self.providers
+            .get(name)
+            .ok_or((StatusCode::NOT_FOUND, format!("Unknown identity provider '{}'", name)))
+    }
+
+    // The provider's discovery document, fetched once and kept for the life of the process.
+    pub async fn metadata(&self, provider: &OidcProvider) -> Result<ProviderMetadata, (StatusCode, String)> {
+        if let Some(metadata) = self.metadata.read().await.get(&provider.name) {
+            return Ok(metadata.clone());
+        }
+
+        let url = format!("{}/.well-known/openid-configuration", provider.issuer);
+        let metadata: ProviderMetadata = self.get_json(&url).await?;
+
+        // The document has to be about the issuer we were configured with.
+        if metadata.issuer.trim_end_matches('/') != provider.issuer {
+            return Err((StatusCode::BAD_GATEWAY, format!("Identity provider '{}' reports a different issuer", provider.name)));
+        }
+
+        self.metadata.write().await.insert(provider.name.clone(), metadata.clone());
+        Ok(metadata)
+    }
+
+    pub fn authorization_url(
+        &self,
+        provider: &OidcProvider,
+        metadata: &ProviderMetadata,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> Result<String, (StatusCode, String)> {
+        let mut url = Url::parse(&metadata.authorization_endpoint)
+            .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Invalid authorization endpoint: {}", e)))?;
+
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &provider.client_id)
+            .append_pair("redirect_uri", &provider.redirect_uri)
+            .append_pair("scope", &provider.scopes)
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", code_challenge)
+            .append_pair("code_challenge_method", "S256");
+
+        Ok(url.to_string())
+    }
+
+    // Exchanges the authorization code for the provider's ID token.
+    pub async fn exchange_code(
+        &self,
+        provider: &OidcProvider,
+        metadata: &ProviderMetadata,
+        code: &str,
+        code_verifier: &str,
+    ) -> Result<String, (StatusCode, String)> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", provider.redirect_uri.as_str()),
+            ("client_id", provider.client_id.as_str()),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(client_secret) = &provider.client_secret {
+            form.push(("client_secret", client_secret.as_str()));
+        }
+
+        let response = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Failed to reach identity provider: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err((StatusCode::UNAUTHORIZED, format!("Identity provider rejected the code: {}", response.status())));
+        }
+
+        let tokens: ProviderTokenResponse = response
+            .json()
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Invalid token response from identity provider: {}", e)))?;
+
+        tokens
+            .id_token
+            .ok_or((StatusCode::BAD_GATEWAY, "Identity provider returned no ID token".to_string()))
+    }
+
+    // Verifies the ID token's signature against the provider's JWKS, its issuer, audience,
+    // expiry and the nonce we sent.
+    pub async fn validate_id_token(
+        &self,
+        provider: &OidcProvider,
+        metadata: &ProviderMetadata,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, (StatusCode, String)> {
+        let invalid = |reason: String| (StatusCode::UNAUTHORIZED, format!("Invalid ID token: {}", reason));
+
+        let header = decode_header(id_token).map_err(|e| invalid(e.to_string()))?;
+        if !ID_TOKEN_ALGORITHMS.contains(&header.alg) {
+            return Err(invalid(format!("unsupported algorithm {:?}", header.alg)));
+        }
+
+        let decoding_key = self.decoding_key(provider, metadata, header.kid.as_deref()).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&provider.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims = decode::<IdTokenClaims>(id_token, &decoding_key, &validation)
+            .map_err(|e| invalid(e.to_string()))?
+            .claims;
+
+        let nonce_matches = claims
+            .nonce
+            .as_deref()
+            .is_some_and(|claimed| constant_time_eq(claimed.as_bytes(), nonce.as_bytes()));
+        if !nonce_matches {
+            return Err(invalid("nonce mismatch".to_string()));
+        }
+
+        Ok(claims)
+    }
+
+    // Finds the signing key by kid. An unknown kid refetches the JWKS once, since that is
+    // what a provider's key rotation looks like from here.
+    async fn decoding_key(
+        &self,
+        provider: &OidcProvider,
+        metadata: &ProviderMetadata,
+        kid: Option<&str>,
+    ) -> Result<DecodingKey, (StatusCode, String)> {
+        for refresh in [false, true] {
+            let cached = self.jwks.read().await.get(&provider.name).cloned();
+            let jwks = match cached {
+                Some(jwks) if !refresh => jwks,
+                _ => {
+                    let jwks: JwkSet = self.get_json(&metadata.jwks_uri).await?;
+                    self.jwks.write().await.insert(provider.name.clone(), jwks.clone());
+                    jwks
+                }
+            };
+
+            // Without a kid the set has to be unambiguous.
+            let jwk = match kid {
+                Some(kid) => jwks.find(kid),
+                None if jwks.keys.len() == 1 => jwks.keys.first(),
+                None => None,
+            };
+
+            if let Some(jwk) = jwk {
+                return DecodingKey::from_jwk(jwk)
+                    .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Unusable signing key from identity provider: {}", e)));
+            }
+        }
+
+        Err((StatusCode::UNAUTHORIZED, "Invalid ID token: unknown signing key".to_string()))
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T, (StatusCode, String)> {
+        self.http
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Failed to reach identity provider: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Invalid response from identity provider: {}", e)))
+    }
+}
@@ -0,0 +1,138 @@
+use std::borrow::Cow;
+
+use argon2::{
+    password_hash::{PasswordHasher, SaltString},
+    Algorithm, Argon2, Params, PasswordHash, Version,
+};
+use axum::http::StatusCode;
+use dotenv::dotenv;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::utils::env::env_or;
+
+// Bundled list of passwords that are always rejected, one per line, lowercase.
+const COMMON_PASSWORDS: &str = include_str!("data/common_passwords.txt");
+
+// Rules a new password has to satisfy. Every value can be overridden through env vars.
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+}
+
+impl PasswordPolicy {
+    pub fn from_env() -> Self {
+        dotenv().ok();
+        Self {
+            min_length: env_or("PASSWORD_MIN_LENGTH", 10),
+            require_lowercase: env_or("PASSWORD_REQUIRE_LOWERCASE", true),
+            require_uppercase: env_or("PASSWORD_REQUIRE_UPPERCASE", true),
+            require_digit: env_or("PASSWORD_REQUIRE_DIGIT", true),
+            require_symbol: env_or("PASSWORD_REQUIRE_SYMBOL", false),
+        }
+    }
+
+    // Checks the password against the policy. `identifiers` are values the password
+    // must not contain, such as the username and email of the account.
+    pub fn validate(&self, password: &str, identifiers: &[&str]) -> Result<(), ValidationErrors> {
+        let mut messages: Vec<String> = vec![];
+
+        if password.chars().count() < self.min_length {
+            messages.push(format!("Password must be at least {} characters long.", self.min_length));
+        }
+        if self.require_lowercase && !password.chars().any(|c| c.is_lowercase()) {
+            messages.push("Password must contain a lowercase letter.".to_string());
+        }
+        if self.require_uppercase && !password.chars().any(|c| c.is_uppercase()) {
+            messages.push("Password must contain an uppercase letter.".to_string());
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            messages.push("Password must contain a digit.".to_string());
+        }
+        if self.require_symbol && password.chars().all(|c| c.is_alphanumeric()) {
+            messages.push("Password must contain a symbol.".to_string());
+        }
+
+        let lowered = password.to_lowercase();
+
+        for identifier in identifiers {
+            // Also catch the local part of an email address, e.g. "jane" in "jane@example.com".
+            let identifier = identifier.split('@').next().unwrap_or_default().to_lowercase();
+            if identifier.len() >= 3 && lowered.contains(&identifier) {
+                messages.push("Password must not contain your username or email.".to_string());
+                break;
+            }
+        }
+
+        if is_common_password(&lowered) {
+            messages.push("Password is too common.".to_string());
+        }
+
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        let mut errors = ValidationErrors::new();
+        for message in messages {
+            errors.add(
+                "password",
+                ValidationError::new("Password does not meet the password policy")
+                    .with_message(Cow::Owned(message)),
+            );
+        }
+        Err(errors)
+    }
+}
+
+fn is_common_password(lowered: &str) -> bool {
+    COMMON_PASSWORDS
+        .lines()
+        .map(str::trim)
+        .any(|common| !common.is_empty() && common == lowered)
+}
+
+// Argon2 parameters used for new hashes. Defaults match the argon2 crate (OWASP minimum).
+pub fn argon2_params() -> Params {
+    dotenv().ok();
+    let defaults = Params::default();
+
+    Params::new(
+        env_or("ARGON2_MEMORY_KIB", defaults.m_cost()),
+        env_or("ARGON2_ITERATIONS", defaults.t_cost()),
+        env_or("ARGON2_PARALLELISM", defaults.p_cost()),
+        None,
+    )
+    .unwrap_or(defaults)
+}
+
+pub fn argon2() -> Argon2<'static> {
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params())
+}
+
+pub fn hash_password(password: &str) -> Result<String, (StatusCode, String)> {
+    let salt = SaltString::generate(&mut rand::thread_rng());
+
+    argon2()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Error hashing password: {}", e)))
+}
+
+// True when a stored hash was made with a different algorithm, version or parameters
+// than the ones currently configured.
+pub fn needs_rehash(hash: &PasswordHash) -> bool {
+    let params = argon2_params();
+
+    let Ok(stored) = Params::try_from(hash) else {
+        return true;
+    };
+
+    hash.algorithm != Algorithm::Argon2id.ident()
+        || hash.version != Some(Version::V0x13.into())
+        || stored.m_cost() != params.m_cost()
+        || stored.t_cost() != params.t_cost()
+        || stored.p_cost() != params.p_cost()
+}
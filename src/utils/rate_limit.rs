@@ -0,0 +1,191 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use async_trait::async_trait;
+use axum::{
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use dotenv::dotenv;
+
+use crate::{models::auth::ResponseMessage, utils::env::env_or};
+
+// A token bucket: `capacity` requests in a burst, refilled at `per_minute` requests per minute.
+// Policies are named so each one gets its own buckets and its own env overrides:
+//   RATE_LIMIT_<NAME>_CAPACITY, RATE_LIMIT_<NAME>_PER_MINUTE
+#[derive(Debug, Clone)]
+pub struct RateLimitPolicy {
+    pub name: &'static str,
+    pub capacity: u32,
+    pub per_minute: u32,
+}
+
+impl RateLimitPolicy {
+    pub fn from_env(name: &'static str, capacity: u32, per_minute: u32) -> Self {
+        dotenv().ok();
+        let prefix = format!("RATE_LIMIT_{}", name.to_uppercase());
+
+        Self {
+            name,
+            capacity: env_or(&format!("{}_CAPACITY", prefix), capacity).max(1),
+            per_minute: env_or(&format!("{}_PER_MINUTE", prefix), per_minute).max(1),
+        }
+    }
+
+    // Credential endpoints: login and token.
+    pub fn auth() -> Self {
+        Self::from_env("auth", 10, 10)
+    }
+
+    // Regular API traffic from users.
+    pub fn api() -> Self {
+        Self::from_env("api", 120, 600)
+    }
+
+    // Partners authenticating with API keys.
+    pub fn external() -> Self {
+        Self::from_env("external", 60, 300)
+    }
+
+    // Login emails, per email address.
+    pub fn magic_link() -> Self {
+        Self::from_env("magic_link", 3, 1)
+    }
+
+    // Verification texts, per phone number.
+    pub fn phone_verification() -> Self {
+        Self::from_env("phone_verification", 3, 1)
+    }
+
+    // Unauthenticated share link views, per IP. Low enough to make guessing link passwords slow.
+    pub fn public() -> Self {
+        Self::from_env("public", 30, 30)
+    }
+
+    fn refill_per_second(&self) -> f64 {
+        self.per_minute as f64 / 60.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    // Seconds until the bucket is full again.
+    pub reset_after: u64,
+    // Seconds until the next request would be allowed. 0 when allowed.
+    pub retry_after: u64,
+}
+
+// Where buckets live. The in-memory store is per process; implement this for Redis or
+// similar to share limits between several instances.
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    async fn take(&self, key: &str, policy: &RateLimitPolicy) -> RateLimitDecision;
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    capacity: f64,
+    refill_per_second: f64,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        self.refilled_at = now;
+    }
+}
+
+// Buckets that have refilled completely carry no information and are dropped once the
+// map grows past this size.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Default)]
+pub struct InMemoryRateLimitStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+// The store handed to the rate_limit middleware. Swap this out for a shared store.
+pub fn rate_limit_store() -> Arc<dyn RateLimitStore> {
+    Arc::new(InMemoryRateLimitStore::default())
+}
+
+#[async_trait]
+impl RateLimitStore for InMemoryRateLimitStore {
+    async fn take(&self, key: &str, policy: &RateLimitPolicy) -> RateLimitDecision {
+        let now = Instant::now();
+        let capacity = policy.capacity as f64;
+        let refill_per_second = policy.refill_per_second();
+
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() > PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| {
+                bucket.refill(now);
+                bucket.tokens < bucket.capacity
+            });
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            refilled_at: now,
+            capacity,
+            refill_per_second,
+        });
+        bucket.refill(now);
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        let retry_after = if allowed {
+            0
+        } else {
+            ((1.0 - bucket.tokens) / refill_per_second).ceil() as u64
+        };
+
+        RateLimitDecision {
+            allowed,
+            limit: policy.capacity,
+            remaining: bucket.tokens.floor() as u32,
+            reset_after: ((capacity - bucket.tokens) / refill_per_second).ceil() as u64,
+            retry_after,
+        }
+    }
+}
+
+pub const LIMIT_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+pub const REMAINING_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+pub const RESET_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+
+impl RateLimitDecision {
+    // X-RateLimit-Limit, X-RateLimit-Remaining and X-RateLimit-Reset (seconds until the bucket is full).
+    pub fn apply_headers(&self, headers: &mut HeaderMap) {
+        headers.insert(LIMIT_HEADER, HeaderValue::from(self.limit));
+        headers.insert(REMAINING_HEADER, HeaderValue::from(self.remaining));
+        headers.insert(RESET_HEADER, HeaderValue::from(self.reset_after));
+    }
+
+    pub fn limited_response(self) -> Response {
+        let mut res = (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, self.retry_after.to_string())],
+            Json(ResponseMessage {
+                message: format!("Rate limit exceeded. Try again in {} seconds.", self.retry_after),
+            }),
+        )
+            .into_response();
+        self.apply_headers(res.headers_mut());
+        res
+    }
+}
@@ -0,0 +1,98 @@
+// Channels a due reminder is delivered through. Which ones a user gets is up to their
+// reminder_channels; the reminders.deliver job records each channel that succeeded, so a
+// retry only repeats the ones that failed.
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use chrono_tz::Tz;
+use serde_json::json;
+use sqlx::MySqlPool;
+
+use crate::{
+    models::{reminder::ReminderEvent, todo::Todo, user::User},
+    utils::{events::EventBus, mailer::Mailer, notifications::notify, webhooks},
+};
+
+// Everything a channel needs to tell the user about their todo.
+pub struct DueReminder {
+    pub id: i64,
+    pub user: User,
+    pub todo: Todo,
+    pub time_zone: Tz,
+}
+
+impl DueReminder {
+    pub fn title(&self) -> String {
+        format!("Reminder: {}", self.todo.description)
+    }
+
+    // Times are shown in the user's own time zone.
+    pub fn body(&self) -> String {
+        match self.todo.due_at {
+            Some(due_at) => format!(
+                "\"{}\" is due {}.",
+                self.todo.description,
+                due_at.with_timezone(&self.time_zone).format("%a %-d %b %Y at %H:%M %Z")
+            ),
+            None => format!("You asked to be reminded about \"{}\".", self.todo.description),
+        }
+    }
+}
+
+#[async_trait]
+pub trait ReminderChannel: Send + Sync {
+    async fn send(&self, reminder: &DueReminder) -> Result<(), String>;
+}
+
+pub struct EmailChannel {
+    pub mailer: Arc<dyn Mailer>,
+}
+
+#[async_trait]
+impl ReminderChannel for EmailChannel {
+    async fn send(&self, reminder: &DueReminder) -> Result<(), String> {
+        self.mailer.send(&reminder.user.email, &reminder.title(), &reminder.body()).await
+    }
+}
+
+// Goes out as a "todo.reminder" event to the user's webhooks, through the outbox.
+pub struct WebhookChannel {
+    pub pool: MySqlPool,
+}
+
+#[async_trait]
+impl ReminderChannel for WebhookChannel {
+    async fn send(&self, reminder: &DueReminder) -> Result<(), String> {
+        let event = ReminderEvent { reminder_id: reminder.id, todo: &reminder.todo };
+
+        webhooks::enqueue(&self.pool, reminder.user.id, "todo.reminder", &event)
+            .await
+            .map_err(|(_, e)| e)
+    }
+}
+
+// Stored in the user's notifications and pushed to their connected clients.
+pub struct InAppChannel {
+    pub pool: MySqlPool,
+    pub bus: EventBus,
+}
+
+#[async_trait]
+impl ReminderChannel for InAppChannel {
+    async fn send(&self, reminder: &DueReminder) -> Result<(), String> {
+        let data = json!({ "todo_id": reminder.todo.id, "reminder_id": reminder.id });
+
+        notify(&self.pool, &self.bus, reminder.user.id, "todo.reminder", &reminder.title(), &reminder.body(), Some(data)).await?;
+
+        Ok(())
+    }
+}
+
+// One of each channel, by the name users pick them with.
+pub fn reminder_channels(pool: MySqlPool, mailer: Arc<dyn Mailer>, bus: EventBus) -> HashMap<&'static str, Arc<dyn ReminderChannel>> {
+    let mut channels: HashMap<&'static str, Arc<dyn ReminderChannel>> = HashMap::new();
+    channels.insert("email", Arc::new(EmailChannel { mailer }));
+    channels.insert("webhook", Arc::new(WebhookChannel { pool: pool.clone() }));
+    channels.insert("in_app", Arc::new(InAppChannel { pool, bus }));
+    channels
+}
@@ -0,0 +1,59 @@
+// Who may do what with a todo. In the caller's current workspace, the user a todo belongs
+// to may do anything, and so may workspace admins, and any member may edit the todos on its
+// lists. Accepted shares count in every workspace, so todos can be shared with users who are
+// not members, e.g. from a personal workspace.
+use axum::http::StatusCode;
+use sqlx::{Executor, FromRow, MySql};
+
+use crate::{
+    models::{todo::Todo, todo_share::TodoRole, workspace::WorkspaceRole},
+    utils::workspaces::Tenant,
+};
+
+#[derive(FromRow)]
+struct TodoWithShare {
+    #[sqlx(flatten)]
+    todo: Todo,
+    share_role: Option<String>,
+}
+
+// The todo and the caller's role on it, if they have one.
+pub async fn todo_role<'e, E>(executor: E, todo_id: i32, tenant: &Tenant) -> Result<Option<(Todo, TodoRole)>, (StatusCode, String)>
+where
+    E: Executor<'e, Database = MySql>,
+{
+    let q = "SELECT t.*, s.role AS share_role FROM todos t \
+             LEFT JOIN todo_shares s ON s.todo_id = t.id AND s.user_id = ? AND s.status = 'accepted' \
+             WHERE t.id = ? AND (t.workspace_id = ? OR s.role IS NOT NULL)";
+
+    let row = sqlx::query_as::<_, TodoWithShare>(q)
+        .bind(tenant.user_id)
+        .bind(todo_id)
+        .bind(tenant.workspace_id)
+        .fetch_optional(executor)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch todo from database: {}", e)))?;
+
+    Ok(row.and_then(|row| {
+        let in_workspace = row.todo.workspace_id == tenant.workspace_id;
+        let roles = [
+            (in_workspace && (row.todo.user_id == tenant.user_id || tenant.role >= WorkspaceRole::Admin)).then_some(TodoRole::Owner),
+            row.todo.list_id.filter(|_| in_workspace).map(|_| TodoRole::Editor),
+            row.share_role.and_then(|role| role.parse().ok()),
+        ];
+        roles.into_iter().flatten().max().map(|role| (row.todo, role))
+    }))
+}
+
+// The todo, if the caller's role on it is at least `needed`. Todos the caller cannot see at
+// all are not found, so that their existence is not given away.
+pub async fn authorize_todo<'e, E>(executor: E, todo_id: i32, tenant: &Tenant, needed: TodoRole) -> Result<(Todo, TodoRole), (StatusCode, String)>
+where
+    E: Executor<'e, Database = MySql>,
+{
+    match todo_role(executor, todo_id, tenant).await? {
+        Some((todo, role)) if role >= needed => Ok((todo, role)),
+        Some((_, role)) => Err((StatusCode::FORBIDDEN, format!("A todo {} cannot do that; it needs {} access", role, needed))),
+        None => Err((StatusCode::NOT_FOUND, "Todo not found".to_string())),
+    }
+}
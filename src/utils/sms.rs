@@ -0,0 +1,58 @@
+use std::{env, sync::Arc};
+
+use async_trait::async_trait;
+use dotenv::dotenv;
+use tokio::{fs::OpenOptions, io::AsyncWriteExt};
+
+// Anything that can deliver a text message to a phone number.
+// Swap in a real provider (Twilio, SNS, ...) by implementing this trait.
+#[async_trait]
+pub trait SmsSender: Send + Sync {
+    async fn send(&self, phone_number: &str, message: &str) -> Result<(), String>;
+}
+
+// Prints messages to stdout. Useful for local development.
+pub struct ConsoleSmsSender;
+
+#[async_trait]
+impl SmsSender for ConsoleSmsSender {
+    async fn send(&self, phone_number: &str, message: &str) -> Result<(), String> {
+        println!("SMS to {}: {}", phone_number, message);
+        Ok(())
+    }
+}
+
+// Appends messages to a file so they can be inspected after the fact.
+pub struct FileSmsSender {
+    pub path: String,
+}
+
+#[async_trait]
+impl SmsSender for FileSmsSender {
+    async fn send(&self, phone_number: &str, message: &str) -> Result<(), String> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|e| format!("Failed to open sms outbox: {}", e))?;
+
+        let line = format!("{}\t{}\t{}\n", chrono::Utc::now().to_rfc3339(), phone_number, message);
+        file.write_all(line.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write sms outbox: {}", e))?;
+
+        Ok(())
+    }
+}
+
+// Picks the sender from SMS_SENDER ("console" or "file"). Defaults to console.
+pub fn sms_sender_from_env() -> Arc<dyn SmsSender> {
+    dotenv().ok();
+    match env::var("SMS_SENDER").as_deref() {
+        Ok("file") => Arc::new(FileSmsSender {
+            path: env::var("SMS_OUTBOX_PATH").unwrap_or_else(|_| "sms_outbox.log".to_string()),
+        }),
+        _ => Arc::new(ConsoleSmsSender),
+    }
+}
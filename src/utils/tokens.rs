@@ -1,4 +1,5 @@
 use std::env;
+
 use axum::{http::StatusCode, Json};
 use dotenv::dotenv;
 use chrono::{
@@ -7,12 +8,22 @@ use chrono::{
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use sqlx::MySqlPool;
 use jsonwebtoken::{
-    decode, encode, Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation
+    decode, decode_header, encode, Header, TokenData, Validation
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    models::{access_token::AccessToken, auth::{Actor, Claims, ResponseMessage}},
+    utils::jwt_keys::keyset
 };
-use uuid::Uuid;
 
-use crate::models::{access_token::AccessToken, auth::{Claims, ResponseMessage}, refresh_token::RefreshToken};
+// Lifetime of a JWT access token.
+pub const ACCESS_TOKEN_TTL_MINUTES: i64 = 60;
+
+// Lifetime of an impersonation token. Short, so support sessions do not linger.
+pub const IMPERSONATION_TTL_MINUTES: i64 = 15;
 
+// Like API keys, refresh tokens are only stored hashed with hash_api_key.
 pub async fn generate_refresh_token(
     pool: &MySqlPool
 ) -> String {
@@ -25,13 +36,13 @@ pub async fn generate_refresh_token(
             .map(char::from)
             .collect();
 
-        let q = format!("SELECT 1 FROM refresh_tokens WHERE token = {refresh_token}");
         // Check for uniqueness in the database
-        let exists = sqlx::query_as::<_, RefreshToken>(&q)
+        let exists = sqlx::query_scalar::<_, i32>("SELECT id FROM refresh_tokens WHERE token = ?")
+            .bind(hash_api_key(&refresh_token))
             .fetch_optional(pool)
             .await;
 
-        if !exists.is_ok() {
+        if !matches!(exists, Ok(Some(_))) {
             // Token is unique, break the loop
             break;
         }
@@ -41,29 +52,24 @@ pub async fn generate_refresh_token(
 
 pub async fn generate_access_token(
     user_id: &i32,
+    workspace_id: Option<i32>,
     pool: &MySqlPool
 ) -> Result<String, (StatusCode, String)> {
-    dotenv().ok();
     let mut token: String;
-    let secret_key = env::var("SECRET_KEY").expect("Secret key not found");
 
     let offset = FixedOffset::east_opt(6 * 3600);
     let now_dhaka = Utc::now().with_timezone(&offset.unwrap());
-    let expiration = now_dhaka + Duration::minutes(60);
+    let expiration = now_dhaka + Duration::minutes(ACCESS_TOKEN_TTL_MINUTES);
 
     let claims = Claims {
         sub: user_id.to_string(), 
         exp: expiration.timestamp() as usize,
+        act: None,
+        wid: workspace_id,
     };
     
     loop {
-        token = encode(
-            &Header::default(), 
-            &claims,
-            &EncodingKey::from_secret(secret_key.as_bytes()), 
-        ).map_err(|e| {
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to generate access token: {}", e))
-        })?;
+        token = sign_claims(&claims)?;
 
         
     
@@ -73,7 +79,7 @@ pub async fn generate_access_token(
             .fetch_optional(pool)
             .await;
     
-        if !exists.is_ok() {
+        if exists.is_err() {
             // Token is unique, break the loop
             break;
         }
@@ -82,43 +88,118 @@ pub async fn generate_access_token(
     Ok(token)
 }
 
+// Access token for an admin acting as `user_id`. It is not stored and cannot be refreshed;
+// check_token_auth also checks that the impersonation session is still open.
+pub fn generate_impersonation_token(user_id: &i32, actor: Actor) -> Result<String, (StatusCode, String)> {
+    let expiration = Utc::now() + Duration::minutes(IMPERSONATION_TTL_MINUTES);
+
+    let claims = Claims {
+        sub: user_id.to_string(),
+        exp: expiration.timestamp() as usize,
+        act: Some(actor),
+        wid: None,
+    };
+
+    sign_claims(&claims)
+}
+
+fn sign_claims(claims: &Claims) -> Result<String, (StatusCode, String)> {
+    let signing_key = keyset().active();
+    let mut header = Header::new(signing_key.algorithm);
+    header.kid = Some(signing_key.kid.clone());
+
+    encode(
+        &header, 
+        claims,
+        signing_key.encoding_key.as_ref().expect("Active signing key has no private key"), 
+    ).map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to generate access token: {}", e))
+    })
+}
+
 pub async fn decode_access_token(token: &str) -> Result<TokenData<Claims>, (StatusCode, Json<ResponseMessage>)>{
-    dotenv().ok();
-    let secret_key = env::var("SECRET_KEY").expect("Secret key not found");
+    let header = decode_header(token).map_err(|err| {
+        (StatusCode::UNAUTHORIZED, Json(ResponseMessage { message: format!("Error decoding JWT: {}", err) }))
+    })?;
+
+    // Pick the verification key by kid. Tokens issued before kids were added fall back to the active key.
+    let keys = keyset();
+    let key = match &header.kid {
+        Some(kid) => keys.find(kid),
+        None => Some(keys.active()),
+    }
+    .ok_or((StatusCode::UNAUTHORIZED, Json(ResponseMessage { message: "Unknown signing key".to_string() })))?;
 
-    let validation = Validation::new(Algorithm::HS256);
+    // Never let the token choose its own algorithm.
+    let validation = Validation::new(key.algorithm);
 
-    let decoding_key = DecodingKey::from_secret(secret_key.as_bytes());
-    let result = decode::<Claims>(token, &decoding_key, &validation);
+    let result = decode::<Claims>(token, &key.decoding_key, &validation);
 
     match result {
         Ok(token_data) => Ok(token_data),
         Err(err) => {
             match err.kind() {
                 jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
-                    return Err((StatusCode::UNAUTHORIZED, Json(ResponseMessage { message: "Token expired in decode token".to_string() })));
+                    Err((StatusCode::UNAUTHORIZED, Json(ResponseMessage { message: "Token expired in decode token".to_string() })))
                 }
                 jsonwebtoken::errors::ErrorKind::InvalidToken => {
-                    return Err((StatusCode::UNAUTHORIZED, Json(ResponseMessage { message: format!("Error decoding JWT: {}", err) })));
+                    Err((StatusCode::UNAUTHORIZED, Json(ResponseMessage { message: format!("Error decoding JWT: {}", err) })))
                 },
                 _ => {
-                    return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(ResponseMessage { message: format!("Error decoding JWT: {}", err) })));
+                    Err((StatusCode::INTERNAL_SERVER_ERROR, Json(ResponseMessage { message: format!("Error decoding JWT: {}", err) })))
                 }
             }
         }
     }
 }
 
+// Number of leading characters of an API key kept in the clear, e.g. "tdk_live_ab12".
+pub const API_KEY_PREFIX_LEN: usize = 13;
+
+// Keys look like "tdk_live_<40 random characters>". The environment part comes from
+// API_KEY_ENVIRONMENT (default "live") so test keys are recognisable at a glance.
 pub async fn generate_api_key() -> String {
-    Uuid::new_v4().to_string() 
+    dotenv().ok();
+    let environment = env::var("API_KEY_ENVIRONMENT").unwrap_or_else(|_| "live".to_string());
+
+    let secret: String = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect();
+
+    format!("tdk_{}_{}", environment, secret)
+}
+
+// API keys carry enough entropy that a fast hash is sufficient, and it keeps lookups indexable.
+pub fn hash_api_key(api_key: &str) -> String {
+    format!("{:x}", Sha256::digest(api_key.as_bytes()))
+}
+
+pub fn api_key_prefix(api_key: &str) -> String {
+    api_key.chars().take(API_KEY_PREFIX_LEN).collect()
+}
+
+// Number of leading characters of a share token kept in the clear, e.g. "shr_ab12cd34".
+pub const SHARE_TOKEN_PREFIX_LEN: usize = 12;
+
+// Share tokens look like "shr_<40 random characters>" and, like API keys, are only stored
+// hashed with hash_api_key.
+pub fn generate_share_token() -> String {
+    let secret: String = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect();
+
+    format!("shr_{}", secret)
 }
 
 pub async fn time_in_dhaka(timestamp: i64) -> DateTime<FixedOffset> {
     // Convert to UTC time and time in Dhaka
-    let expiration_datetime_utc = Utc.timestamp_opt(timestamp as i64, 0); 
+    let expiration_datetime_utc = Utc.timestamp_opt(timestamp, 0); 
 
     let offset_opt = FixedOffset::east_opt(6 * 3600);
     let offset = offset_opt.unwrap();
-    let expiration_datetime_bst = expiration_datetime_utc.unwrap().with_timezone(&offset);
-    expiration_datetime_bst
+    expiration_datetime_utc.unwrap().with_timezone(&offset)
 }
\ No newline at end of file
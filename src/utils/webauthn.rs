@@ -0,0 +1,288 @@
+// WebAuthn (passkeys) relying-party checks.
+//
+// Only attestation "none" is accepted: we do not care which make of authenticator a user
+// has, only that it holds the private key. Supported credential algorithms are ES256,
+// EdDSA and RS256, which covers platform authenticators and security keys in practice.
+//
+//   WEBAUTHN_RP_ID    the site's domain, e.g. todos.example.com (default localhost)
+//   WEBAUTHN_RP_NAME  shown by the authenticator (default "Todos")
+//   WEBAUTHN_ORIGINS  comma separated origins the browser may report (default http://localhost:8000)
+use std::env;
+
+use axum::http::StatusCode;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::value::Value;
+use dotenv::dotenv;
+use rand::{thread_rng, RngCore};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+pub const COSE_ES256: i32 = -7;
+pub const COSE_EDDSA: i32 = -8;
+pub const COSE_RS256: i32 = -257;
+pub const SUPPORTED_ALGORITHMS: [i32; 3] = [COSE_ES256, COSE_EDDSA, COSE_RS256];
+
+// How long the browser, and the user, get to complete a ceremony.
+pub const CEREMONY_TIMEOUT_MS: u32 = 300_000;
+pub const CHALLENGE_TTL_MINUTES: i64 = 5;
+
+// Longest credential id the spec allows.
+pub const MAX_CREDENTIAL_ID_LENGTH: usize = 1023;
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+type WebAuthnError = (StatusCode, String);
+
+fn bad_request(message: impl Into<String>) -> WebAuthnError {
+    (StatusCode::BAD_REQUEST, message.into())
+}
+
+#[derive(Debug, Clone)]
+pub struct WebAuthnConfig {
+    pub rp_id: String,
+    pub rp_name: String,
+    pub origins: Vec<String>,
+}
+
+impl WebAuthnConfig {
+    pub fn from_env() -> Self {
+        dotenv().ok();
+        let origins = env::var("WEBAUTHN_ORIGINS").unwrap_or_else(|_| "http://localhost:8000".to_string());
+
+        Self {
+            rp_id: env::var("WEBAUTHN_RP_ID").unwrap_or_else(|_| "localhost".to_string()),
+            rp_name: env::var("WEBAUTHN_RP_NAME").unwrap_or_else(|_| "Todos".to_string()),
+            origins: origins
+                .split(',')
+                .map(|origin| origin.trim().trim_end_matches('/').to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect(),
+        }
+    }
+}
+
+pub fn generate_challenge() -> String {
+    let mut bytes = [0u8; 32];
+    thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn encode(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+// Browsers send base64url without padding, but some serialisers add it.
+pub fn decode(value: &str, field: &str) -> Result<Vec<u8>, WebAuthnError> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| bad_request(format!("{} is not valid base64url", field)))
+}
+
+#[derive(Debug, Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+    origin: String,
+}
+
+// Checks the ceremony type and origin in clientDataJSON and returns the challenge it signs,
+// which the caller still has to match against a stored one.
+pub fn check_client_data(config: &WebAuthnConfig, client_data_json: &[u8], expected_type: &str) -> Result<String, WebAuthnError> {
+    let client_data: ClientData = serde_json::from_slice(client_data_json)
+        .map_err(|e| bad_request(format!("Invalid clientDataJSON: {}", e)))?;
+
+    if client_data.kind != expected_type {
+        return Err(bad_request(format!("Expected a {} ceremony", expected_type)));
+    }
+    if !config.origins.contains(&client_data.origin) {
+        return Err(bad_request(format!("Origin {} is not allowed", client_data.origin)));
+    }
+
+    Ok(client_data.challenge)
+}
+
+pub struct AuthenticatorData {
+    pub sign_count: u32,
+    // Only present during registration.
+    pub credential_id: Option<Vec<u8>>,
+    pub public_key: Option<Vec<u8>>,
+}
+
+// Parses authenticatorData and checks that it was made for our RP ID with the user present.
+pub fn parse_authenticator_data(config: &WebAuthnConfig, data: &[u8]) -> Result<AuthenticatorData, WebAuthnError> {
+    if data.len() < 37 {
+        return Err(bad_request("authenticatorData is too short"));
+    }
+
+    let rp_id_hash = Sha256::digest(config.rp_id.as_bytes());
+    if data[..32] != rp_id_hash[..] {
+        return Err(bad_request("authenticatorData is for another RP ID"));
+    }
+
+    let flags = data[32];
+    if flags & FLAG_USER_PRESENT == 0 {
+        return Err(bad_request("User presence is required"));
+    }
+
+    let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+    let mut authenticator_data = AuthenticatorData { sign_count, credential_id: None, public_key: None };
+
+    if flags & FLAG_ATTESTED_CREDENTIAL_DATA != 0 {
+        // aaguid (16 bytes), credential id length (2 bytes), credential id, COSE public key
+        let rest = &data[37..];
+        if rest.len() < 18 {
+            return Err(bad_request("Attested credential data is too short"));
+        }
+        let id_len = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+        if id_len > MAX_CREDENTIAL_ID_LENGTH {
+            return Err(bad_request("Credential id is too long"));
+        }
+        let rest = &rest[18..];
+        if rest.len() < id_len {
+            return Err(bad_request("Attested credential data is too short"));
+        }
+        let (credential_id, rest) = rest.split_at(id_len);
+
+        // The key is a single CBOR item; extensions may follow it.
+        let mut reader = rest;
+        let _: Value = ciborium::de::from_reader(&mut reader)
+            .map_err(|e| bad_request(format!("Invalid credential public key: {}", e)))?;
+        let key_len = rest.len() - reader.len();
+
+        authenticator_data.credential_id = Some(credential_id.to_vec());
+        authenticator_data.public_key = Some(rest[..key_len].to_vec());
+    }
+
+    Ok(authenticator_data)
+}
+
+// Returns authData from an attestation object with fmt "none".
+pub fn parse_attestation_object(attestation_object: &[u8]) -> Result<Vec<u8>, WebAuthnError> {
+    let value: Value = ciborium::de::from_reader(attestation_object)
+        .map_err(|e| bad_request(format!("Invalid attestationObject: {}", e)))?;
+    let map = value.as_map().ok_or_else(|| bad_request("attestationObject is not a map"))?;
+
+    let field = |name: &str| map.iter().find(|(key, _)| key.as_text() == Some(name)).map(|(_, value)| value);
+
+    let fmt = field("fmt").and_then(Value::as_text);
+    if fmt != Some("none") {
+        return Err(bad_request("Only attestation \"none\" is supported"));
+    }
+
+    field("authData")
+        .and_then(Value::as_bytes)
+        .cloned()
+        .ok_or_else(|| bad_request("attestationObject has no authData"))
+}
+
+fn cose_field(key: &[(Value, Value)], label: i64) -> Option<&Value> {
+    key.iter()
+        .find(|(k, _)| k.as_integer().map(i128::from) == Some(label as i128))
+        .map(|(_, v)| v)
+}
+
+fn cose_int(key: &[(Value, Value)], label: i64) -> Option<i128> {
+    cose_field(key, label).and_then(Value::as_integer).map(i128::from)
+}
+
+fn cose_bytes(key: &[(Value, Value)], label: i64) -> Result<&[u8], WebAuthnError> {
+    cose_field(key, label)
+        .and_then(Value::as_bytes)
+        .map(Vec::as_slice)
+        .ok_or_else(|| bad_request("Incomplete credential public key"))
+}
+
+fn parse_cose_key(cose_key: &[u8]) -> Result<Vec<(Value, Value)>, WebAuthnError> {
+    let value: Value = ciborium::de::from_reader(cose_key)
+        .map_err(|e| bad_request(format!("Invalid credential public key: {}", e)))?;
+
+    value
+        .into_map()
+        .map_err(|_| bad_request("Credential public key is not a map"))
+}
+
+// The COSE algorithm of a credential public key, if it is one we support.
+pub fn cose_algorithm(cose_key: &[u8]) -> Result<i32, WebAuthnError> {
+    let key = parse_cose_key(cose_key)?;
+    let algorithm = cose_int(&key, 3)
+        .and_then(|alg| i32::try_from(alg).ok())
+        .ok_or_else(|| bad_request("Credential public key has no algorithm"))?;
+
+    if !SUPPORTED_ALGORITHMS.contains(&algorithm) {
+        return Err(bad_request(format!("Unsupported credential algorithm {}", algorithm)));
+    }
+
+    // Parse it fully now so a broken key is refused at registration, not at login.
+    verify_signature(cose_key, b"", b"")?;
+
+    Ok(algorithm)
+}
+
+// Verifies an assertion signature with a stored COSE public key.
+// Ok(false) means a well-formed key and a wrong signature.
+pub fn verify_signature(cose_key: &[u8], message: &[u8], signature: &[u8]) -> Result<bool, WebAuthnError> {
+    let key = parse_cose_key(cose_key)?;
+    let kty = cose_int(&key, 1);
+    let alg = cose_int(&key, 3);
+
+    match (kty, alg) {
+        // EC2 key on P-256
+        (Some(2), Some(-7)) => {
+            use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+
+            let (x, y) = (cose_bytes(&key, -2)?, cose_bytes(&key, -3)?);
+            if cose_int(&key, -1) != Some(1) || x.len() != 32 || y.len() != 32 {
+                return Err(bad_request("Unsupported EC2 credential public key"));
+            }
+            let point = p256::EncodedPoint::from_affine_coordinates(x.into(), y.into(), false);
+            let verifying_key = VerifyingKey::from_encoded_point(&point)
+                .map_err(|_| bad_request("Invalid EC2 credential public key"))?;
+
+            Ok(Signature::from_der(signature).is_ok_and(|signature| verifying_key.verify(message, &signature).is_ok()))
+        }
+        // OKP key on Ed25519
+        (Some(1), Some(-8)) => {
+            use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+            let x: [u8; 32] = cose_bytes(&key, -2)?
+                .try_into()
+                .map_err(|_| bad_request("Invalid OKP credential public key"))?;
+            if cose_int(&key, -1) != Some(6) {
+                return Err(bad_request("Unsupported OKP credential public key"));
+            }
+            let verifying_key = VerifyingKey::from_bytes(&x)
+                .map_err(|_| bad_request("Invalid OKP credential public key"))?;
+
+            Ok(Signature::from_slice(signature).is_ok_and(|signature| verifying_key.verify(message, &signature).is_ok()))
+        }
+        // RSA with PKCS#1 v1.5 and SHA-256
+        (Some(3), Some(-257)) => {
+            use rsa::{pkcs1v15::{Signature, VerifyingKey}, signature::Verifier, BigUint, RsaPublicKey};
+
+            let n = BigUint::from_bytes_be(cose_bytes(&key, -1)?);
+            let e = BigUint::from_bytes_be(cose_bytes(&key, -2)?);
+            let public_key = RsaPublicKey::new(n, e).map_err(|_| bad_request("Invalid RSA credential public key"))?;
+            let verifying_key = VerifyingKey::<Sha256>::new(public_key);
+
+            Ok(Signature::try_from(signature).is_ok_and(|signature| verifying_key.verify(message, &signature).is_ok()))
+        }
+        _ => Err(bad_request("Unsupported credential public key")),
+    }
+}
+
+// Whether an assertion's signature counter moved forward from the stored one. Authenticators
+// that do not keep a counter always report 0. A counter that stands still or goes backwards
+// means the credential was cloned.
+pub fn sign_count_advanced(stored: i64, reported: u32) -> bool {
+    let reported = i64::from(reported);
+    (reported == 0 && stored == 0) || reported > stored
+}
+
+// WebAuthn assertions sign authenticatorData followed by the hash of clientDataJSON.
+pub fn signed_message(authenticator_data: &[u8], client_data_json: &[u8]) -> Vec<u8> {
+    let mut message = authenticator_data.to_vec();
+    message.extend_from_slice(&Sha256::digest(client_data_json));
+    message
+}
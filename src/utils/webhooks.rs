@@ -0,0 +1,462 @@
+// Outgoing webhooks.
+//
+// Controllers call `enqueue` inside the transaction that makes a change, which writes the
+// event to webhook_outbox: the event exists if and only if the change was committed. A
+// recurring webhooks.deliver job then fans outbox events out to the subscribed webhooks (one row in
+// webhook_deliveries each) and POSTs them, signed with the webhook's secret:
+//   X-Webhook-Id:        event id, the same for every retry
+//   X-Webhook-Event:     event type, e.g. todo.created
+//   X-Webhook-Timestamp: unix time in seconds
+//   X-Webhook-Signature: v1=<hex HMAC-SHA256 of "<timestamp>.<body>", keyed with the secret>
+// Anything but a 2xx response is retried with exponential backoff. After
+// WEBHOOK_MAX_ATTEMPTS (default 8) the delivery is dead and only a manual retry sends it again.
+//
+//   WEBHOOK_WORKER_INTERVAL_SECONDS  how often the job looks for work, 1 to 59 (default 5)
+//   WEBHOOK_ALLOW_LOCAL              allow http and local addresses, for development (default false)
+use std::{
+    env,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts, StatusCode},
+};
+use chrono::{DateTime, Utc};
+use dotenv::dotenv;
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::Serialize;
+use sqlx::{Executor, FromRow, MySql, MySqlPool};
+use tokio::{task::JoinSet, time::Instant};
+use url::{Host, Url};
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        api_key::ApiKey,
+        auth::Claims,
+        webhook::{Webhook, WebhookDelivery, WebhookOutboxEvent, WebhookPayload},
+    },
+    utils::{env::env_or, jobs::backoff_delay, request_signing::sign},
+};
+
+pub const WEBHOOK_ID_HEADER: &str = "X-Webhook-Id";
+pub const WEBHOOK_EVENT_HEADER: &str = "X-Webhook-Event";
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+// Deliveries handled per worker tick, concurrently.
+const DELIVERY_BATCH: i64 = 20;
+// A claimed delivery is left alone for this long, in case the worker dies while sending it.
+const DELIVERY_LOCK_SECONDS: i64 = 120;
+const RETRY_BASE_SECONDS: i64 = 30;
+const RETRY_MAX_SECONDS: i64 = 6 * 3600;
+const MAX_ERROR_LENGTH: usize = 1024;
+
+pub fn generate_webhook_secret() -> String {
+    let secret: String = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(48)
+        .map(char::from)
+        .collect();
+
+    format!("whsec_{}", secret)
+}
+
+// Writes an event to the outbox. Pass the transaction of the change it describes.
+// `user_id` is the user the event is about, whose own webhooks receive it.
+pub async fn enqueue<'e, E>(executor: E, user_id: i32, event_type: &str, data: &impl Serialize) -> Result<(), (StatusCode, String)>
+where
+    E: Executor<'e, Database = MySql>,
+{
+    let event_id = Uuid::new_v4().to_string();
+    let payload = WebhookPayload { id: &event_id, event_type, created_at: Utc::now().into(), data };
+    let payload = serde_json::to_string(&payload)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to serialise webhook event: {}", e)))?;
+
+    sqlx::query("INSERT INTO webhook_outbox (event_id, event_type, user_id, payload) VALUES (?, ?, ?, ?)")
+        .bind(&event_id)
+        .bind(event_type)
+        .bind(user_id)
+        .bind(payload)
+        .execute(executor)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to enqueue webhook event: {}", e)))?;
+
+    Ok(())
+}
+
+fn allow_local() -> bool {
+    dotenv().ok();
+    env::var("WEBHOOK_ALLOW_LOCAL").map(|v| v == "true").unwrap_or(false)
+}
+
+// Webhook URLs must be https and must not point at this machine or a private network,
+// or anybody could make the server send requests inside our network. Host names are
+// checked again by PublicResolver each time a delivery is sent, once they have resolved.
+pub fn check_destination(url: &str) -> Result<Url, String> {
+    let parsed = Url::parse(url).map_err(|e| format!("Invalid webhook URL: {}", e))?;
+
+    if !parsed.username().is_empty() || parsed.password().is_some() {
+        return Err("Webhook URLs must not contain credentials".to_string());
+    }
+    if parsed.fragment().is_some() {
+        return Err("Webhook URLs must not contain a fragment".to_string());
+    }
+    if allow_local() && matches!(parsed.scheme(), "http" | "https") {
+        return Ok(parsed);
+    }
+    if parsed.scheme() != "https" {
+        return Err("Webhook URLs must use https".to_string());
+    }
+
+    let local = match parsed.host() {
+        Some(Host::Domain(domain)) => domain == "localhost" || domain.ends_with(".localhost"),
+        Some(Host::Ipv4(ip)) => is_local_ipv4(ip),
+        Some(Host::Ipv6(ip)) => is_local_ipv6(ip),
+        None => true,
+    };
+    if local {
+        return Err("Webhook URLs must not point at local or private addresses".to_string());
+    }
+
+    Ok(parsed)
+}
+
+fn is_local_ipv4(ip: Ipv4Addr) -> bool {
+    // 100.64.0.0/10, carrier-grade NAT.
+    let shared = ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64;
+
+    ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast() || shared
+}
+
+fn is_local_ipv6(ip: Ipv6Addr) -> bool {
+    let unique_local = (ip.segments()[0] & 0xfe00) == 0xfc00;
+    let link_local = (ip.segments()[0] & 0xffc0) == 0xfe80;
+    let mapped_local = ip.to_ipv4_mapped().is_some_and(is_local_ipv4);
+
+    ip.is_loopback() || ip.is_unspecified() || unique_local || link_local || mapped_local
+}
+
+fn is_local_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_local_ipv4(ip),
+        IpAddr::V6(ip) => is_local_ipv6(ip),
+    }
+}
+
+// Resolves webhook hosts for the HTTP client and refuses names with any local or private
+// address, so that a public name pointing inside our network is caught when it is used,
+// not just literal IPs. Resolving at connect time leaves no gap for the name to change.
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<_> = tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+
+            if addrs.iter().any(|addr| is_local_ip(addr.ip())) {
+                return Err(format!("{} resolves to a local or private address", name.as_str()).into());
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+// Delay before attempt number `attempts + 1`: 30s, 1m, 2m, 4m, ... capped at 6h, plus jitter
+// so a receiver that comes back is not hit by every retry at once.
+pub fn retry_delay(attempts: i32) -> chrono::Duration {
+    backoff_delay(attempts, RETRY_BASE_SECONDS, RETRY_MAX_SECONDS)
+}
+
+// Where a delivery stands after one more attempt.
+pub struct AttemptOutcome {
+    // "delivered", "pending" for another try at next_attempt_at, or "dead".
+    pub status: &'static str,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+}
+
+// Anything but a 2xx response counts as a failure. `previous_attempts` is how many times
+// the delivery was tried before this one.
+pub fn attempt_outcome(previous_attempts: i32, max_attempts: i32, response: Result<u16, String>) -> AttemptOutcome {
+    let (status_code, error) = match response {
+        Ok(status) if (200..300).contains(&status) => (Some(status as i32), None),
+        Ok(status) => (Some(status as i32), Some(format!("Receiver responded with HTTP {}", status))),
+        Err(error) => (None, Some(error.chars().take(MAX_ERROR_LENGTH).collect::<String>())),
+    };
+
+    let attempts = previous_attempts + 1;
+    let now = Utc::now();
+    let (status, next_attempt_at, delivered_at) = match &error {
+        None => ("delivered", now, Some(now)),
+        Some(_) if attempts >= max_attempts => ("dead", now, None),
+        Some(_) => ("pending", now + retry_delay(attempts), None),
+    };
+
+    AttemptOutcome { status, attempts, next_attempt_at, delivered_at, status_code, error }
+}
+
+// Who webhooks are managed for: the logged-in user, or the API key on /api/external routes.
+pub enum WebhookOwner {
+    User(i32),
+    ApiKey(i32),
+}
+
+impl WebhookOwner {
+    // Column of the webhooks table holding the owner. Never user input.
+    pub fn column(&self) -> &'static str {
+        match self {
+            WebhookOwner::User(_) => "user_id",
+            WebhookOwner::ApiKey(_) => "api_key_id",
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        match self {
+            WebhookOwner::User(id) | WebhookOwner::ApiKey(id) => *id,
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for WebhookOwner
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(api_key) = parts.extensions.get::<ApiKey>() {
+            return Ok(WebhookOwner::ApiKey(api_key.id));
+        }
+
+        let claims = parts
+            .extensions
+            .get::<Claims>()
+            .ok_or((StatusCode::UNAUTHORIZED, "Missing token".to_string()))?;
+        let user_id = claims
+            .sub
+            .parse()
+            .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token subject".to_string()))?;
+
+        Ok(WebhookOwner::User(user_id))
+    }
+}
+
+#[derive(FromRow)]
+struct WebhookTarget {
+    #[sqlx(flatten)]
+    webhook: Webhook,
+    api_key_scopes: Option<String>,
+}
+
+impl WebhookTarget {
+    // A user's webhooks get the user's own events. An API key's webhooks get every event
+    // its read scope covers, e.g. todos:read for todo.created. Lists hold todos and come
+    // under todos:read as well.
+    fn receives(&self, event: &WebhookOutboxEvent) -> bool {
+        if !self.webhook.subscribes_to(&event.event_type) {
+            return false;
+        }
+        if self.webhook.user_id == Some(event.user_id) {
+            return true;
+        }
+
+        let resource = match event.event_type.split('.').next().unwrap_or_default() {
+            "list" => "todo",
+            resource => resource,
+        };
+        let scope = format!("{}s:read", resource);
+        self.api_key_scopes
+            .as_deref()
+            .is_some_and(|scopes| scopes.split_whitespace().any(|s| s == scope))
+    }
+}
+
+#[derive(Clone)]
+pub struct WebhookWorker {
+    pool: MySqlPool,
+    http: reqwest::Client,
+    max_attempts: i32,
+}
+
+impl WebhookWorker {
+    pub fn from_env(pool: MySqlPool) -> Result<Self, String> {
+        dotenv().ok();
+        let max_attempts = env_or("WEBHOOK_MAX_ATTEMPTS", 8);
+
+        // Redirects are not followed: they could lead past check_destination.
+        let mut http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none())
+            .user_agent("todos-webhooks/1.0");
+        if !allow_local() {
+            http = http.dns_resolver(Arc::new(PublicResolver));
+        }
+        let http = http.build().map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+        Ok(Self { pool, http, max_attempts })
+    }
+
+    // How often the webhooks.deliver job runs, as a cron expression with seconds. Between 1
+    // and 59 seconds, the range a seconds step can take.
+    pub fn schedule() -> String {
+        dotenv().ok();
+        let seconds: u32 = env_or("WEBHOOK_WORKER_INTERVAL_SECONDS", 5);
+
+        format!("*/{} * * * * *", seconds.clamp(1, 59))
+    }
+
+    // Turns outbox events into one delivery per subscribed webhook.
+    pub async fn dispatch_outbox(&self) -> Result<(), sqlx::Error> {
+        let events = sqlx::query_as::<_, WebhookOutboxEvent>("SELECT * FROM webhook_outbox WHERE dispatched_at IS NULL ORDER BY id LIMIT 100")
+            .fetch_all(&self.pool)
+            .await?;
+
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let q = "SELECT w.*, k.scopes AS api_key_scopes FROM webhooks w LEFT JOIN api_keys k ON k.id = w.api_key_id \
+                 WHERE w.is_active AND (w.user_id IS NOT NULL OR (k.is_active AND (k.expires_at IS NULL OR k.expires_at > ?)))";
+        let targets = sqlx::query_as::<_, WebhookTarget>(q)
+            .bind(Utc::now())
+            .fetch_all(&self.pool)
+            .await?;
+
+        for event in events {
+            let mut tx = self.pool.begin().await?;
+
+            for target in targets.iter().filter(|target| target.receives(&event)) {
+                // The unique key makes a second dispatch of the same event harmless.
+                sqlx::query("INSERT IGNORE INTO webhook_deliveries (webhook_id, event_id, event_type, payload, next_attempt_at) VALUES (?, ?, ?, ?, ?)")
+                    .bind(target.webhook.id)
+                    .bind(&event.event_id)
+                    .bind(&event.event_type)
+                    .bind(&event.payload)
+                    .bind(Utc::now())
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            sqlx::query("UPDATE webhook_outbox SET dispatched_at = ? WHERE id = ?")
+                .bind(Utc::now())
+                .bind(event.id)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+        }
+
+        // Dispatched events live on in webhook_deliveries.
+        sqlx::query("DELETE FROM webhook_outbox WHERE dispatched_at < ? LIMIT 1000")
+            .bind(Utc::now() - chrono::Duration::days(1))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn deliver_due(&self) -> Result<(), sqlx::Error> {
+        let now = Utc::now();
+        let q = "SELECT d.* FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id \
+                 WHERE d.status = 'pending' AND d.next_attempt_at <= ? AND (d.locked_until IS NULL OR d.locked_until < ?) AND w.is_active \
+                 ORDER BY d.next_attempt_at LIMIT ?";
+        let due = sqlx::query_as::<_, WebhookDelivery>(q)
+            .bind(now)
+            .bind(now)
+            .bind(DELIVERY_BATCH)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut attempts = JoinSet::new();
+        for delivery in due {
+            // Claim it, so a second worker does not send it too.
+            let claimed = sqlx::query("UPDATE webhook_deliveries SET locked_until = ? WHERE id = ? AND (locked_until IS NULL OR locked_until < ?)")
+                .bind(now + chrono::Duration::seconds(DELIVERY_LOCK_SECONDS))
+                .bind(delivery.id)
+                .bind(now)
+                .execute(&self.pool)
+                .await?;
+
+            if claimed.rows_affected() == 1 {
+                let worker = self.clone();
+                attempts.spawn(async move { worker.attempt(delivery).await });
+            }
+        }
+
+        while let Some(result) = attempts.join_next().await {
+            if let Ok(Err(e)) = result {
+                println!("Failed to record webhook delivery: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn attempt(&self, delivery: WebhookDelivery) -> Result<(), sqlx::Error> {
+        let webhook = sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE id = ?")
+            .bind(delivery.webhook_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        let started = Instant::now();
+        let response = self.post(&webhook, &delivery).await;
+        let duration_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+        let outcome = attempt_outcome(delivery.attempts, self.max_attempts, response);
+
+        sqlx::query("INSERT INTO webhook_delivery_attempts (delivery_id, status_code, error, duration_ms) VALUES (?, ?, ?, ?)")
+            .bind(delivery.id)
+            .bind(outcome.status_code)
+            .bind(&outcome.error)
+            .bind(duration_ms)
+            .execute(&self.pool)
+            .await?;
+
+        let q = "UPDATE webhook_deliveries SET status = ?, attempts = ?, next_attempt_at = ?, delivered_at = ?, \
+                 last_status_code = ?, last_error = ?, locked_until = NULL WHERE id = ?";
+        sqlx::query(q)
+            .bind(outcome.status)
+            .bind(outcome.attempts)
+            .bind(outcome.next_attempt_at)
+            .bind(outcome.delivered_at)
+            .bind(outcome.status_code)
+            .bind(&outcome.error)
+            .bind(delivery.id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // The receiver's status code, or why there was none.
+    pub async fn post(&self, webhook: &Webhook, delivery: &WebhookDelivery) -> Result<u16, String> {
+        let url = check_destination(&webhook.url)?;
+        let timestamp = Utc::now().timestamp().to_string();
+        let signature = sign(&webhook.secret, &format!("{}.{}", timestamp, delivery.payload));
+
+        let response = self
+            .http
+            .post(url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(WEBHOOK_ID_HEADER, &delivery.event_id)
+            .header(WEBHOOK_EVENT_HEADER, &delivery.event_type)
+            .header(WEBHOOK_TIMESTAMP_HEADER, &timestamp)
+            .header(WEBHOOK_SIGNATURE_HEADER, signature)
+            .body(delivery.payload.clone())
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        Ok(response.status().as_u16())
+    }
+}
@@ -0,0 +1,144 @@
+// Workspaces are the tenants of the API: lists and todos belong to exactly one, and every
+// query on them is scoped by the Tenant of the request, which resolve_workspace works out
+// from the access token.
+use axum::http::StatusCode;
+use sqlx::{Executor, MySql, MySqlPool};
+
+use crate::models::{auth::Claims, workspace::WorkspaceRole};
+
+// The workspace a request acts in, and the caller's role there.
+#[derive(Debug, Clone)]
+pub struct Tenant {
+    pub user_id: i32,
+    pub workspace_id: i32,
+    pub role: WorkspaceRole,
+}
+
+impl Tenant {
+    pub fn require(&self, needed: WorkspaceRole) -> Result<(), (StatusCode, String)> {
+        if self.role < needed {
+            return Err((StatusCode::FORBIDDEN, format!("A workspace {} cannot do that; it needs {} access", self.role, needed)));
+        }
+
+        Ok(())
+    }
+
+    // Credentials, such as a password or an email address, and the account itself are only
+    // ever changed by their own user.
+    pub fn require_self(&self, user_id: i32) -> Result<(), (StatusCode, String)> {
+        if self.user_id != user_id {
+            return Err((StatusCode::FORBIDDEN, "Only the user themselves can do that".to_string()));
+        }
+
+        Ok(())
+    }
+
+    // Anything else about a user can also be changed by admins of the workspace.
+    pub fn require_self_or_admin(&self, user_id: i32) -> Result<(), (StatusCode, String)> {
+        if self.user_id != user_id {
+            self.require(WorkspaceRole::Admin)?;
+        }
+
+        Ok(())
+    }
+}
+
+// The workspace of the token, or the user's personal one. Fails once the user is no longer
+// a member, e.g. after being removed with a token that was switched there.
+pub async fn resolve_tenant(pool: &MySqlPool, claims: &Claims) -> Result<Tenant, (StatusCode, String)> {
+    let user_id: i32 = claims
+        .sub
+        .parse()
+        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token subject".to_string()))?;
+
+    let workspace_id = match claims.wid {
+        Some(workspace_id) => workspace_id,
+        None => personal_workspace(pool, user_id).await?,
+    };
+
+    let role = workspace_role(pool, workspace_id, user_id)
+        .await?
+        .ok_or((StatusCode::FORBIDDEN, "Not a member of this workspace; switch to another one".to_string()))?;
+
+    Ok(Tenant { user_id, workspace_id, role })
+}
+
+// The user's personal workspace, made the first time it is needed.
+pub async fn personal_workspace(pool: &MySqlPool, user_id: i32) -> Result<i32, (StatusCode, String)> {
+    let existing = sqlx::query_scalar::<_, i32>("SELECT id FROM workspaces WHERE personal_user_id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch workspace from database: {}", e)))?;
+
+    if let Some(workspace_id) = existing {
+        return Ok(workspace_id);
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start transaction: {}", e)))?;
+
+    // Two first requests at once make one workspace between them.
+    sqlx::query("INSERT IGNORE INTO workspaces (name, personal_user_id, created_by) VALUES ('Personal', ?, ?)")
+        .bind(user_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create workspace: {}", e)))?;
+
+    let workspace_id = sqlx::query_scalar::<_, i32>("SELECT id FROM workspaces WHERE personal_user_id = ?")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch workspace from database: {}", e)))?;
+
+    sqlx::query("INSERT IGNORE INTO workspace_members (workspace_id, user_id, role) VALUES (?, ?, 'owner')")
+        .bind(workspace_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to add workspace member: {}", e)))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to commit workspace: {}", e)))?;
+
+    Ok(workspace_id)
+}
+
+pub async fn workspace_role<'e, E>(executor: E, workspace_id: i32, user_id: i32) -> Result<Option<WorkspaceRole>, (StatusCode, String)>
+where
+    E: Executor<'e, Database = MySql>,
+{
+    let role = sqlx::query_scalar::<_, String>("SELECT role FROM workspace_members WHERE workspace_id = ? AND user_id = ?")
+        .bind(workspace_id)
+        .bind(user_id)
+        .fetch_optional(executor)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch workspace member from database: {}", e)))?;
+
+    Ok(role.and_then(|role| role.parse().ok()))
+}
+
+// Users outside the tenant's workspace are not found, so that their existence is not given
+// away.
+pub async fn require_member(pool: &MySqlPool, tenant: &Tenant, user_id: i32) -> Result<WorkspaceRole, (StatusCode, String)> {
+    workspace_role(pool, tenant.workspace_id, user_id)
+        .await?
+        .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))
+}
+
+// Lists of other workspaces are not found either.
+pub async fn require_list(pool: &MySqlPool, workspace_id: i32, list_id: i32) -> Result<(), (StatusCode, String)> {
+    sqlx::query_scalar::<_, i32>("SELECT id FROM lists WHERE id = ? AND workspace_id = ?")
+        .bind(list_id)
+        .bind(workspace_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch list from database: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "List not found".to_string()))?;
+
+    Ok(())
+}
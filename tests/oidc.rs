@@ -0,0 +1,304 @@
+// OIDC relying party against a stub issuer on a random local port: discovery, JWKS lookup
+// and rotation, nonce and state checks.
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use p256::{
+    elliptic_curve::sec1::ToEncodedPoint,
+    pkcs8::{EncodePrivateKey, LineEnding},
+    SecretKey,
+};
+use rand::rngs::OsRng;
+use serde_json::{json, Value};
+use sqlx::mysql::MySqlPoolOptions;
+use tower_cookies::CookieManagerLayer;
+
+use todos_web_api::{
+    controllers::oidc_controller::oidc_callback,
+    utils::{
+        events::EventBus,
+        oidc::{Oidc, OidcProvider},
+    },
+};
+
+const CLIENT_ID: &str = "todos";
+const NONCE: &str = "expected-nonce";
+
+struct Issuer {
+    url: String,
+    // What the discovery document claims the issuer is.
+    reported_issuer: Mutex<String>,
+    // (kid, key) pairs published in the JWKS.
+    keys: Mutex<Vec<(String, SecretKey)>>,
+    jwks_fetches: AtomicUsize,
+    id_token: Mutex<String>,
+}
+
+impl Issuer {
+    async fn start() -> Arc<Self> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let issuer = Arc::new(Self {
+            reported_issuer: Mutex::new(url.clone()),
+            url,
+            keys: Mutex::new(vec![("key-1".to_string(), SecretKey::random(&mut OsRng))]),
+            jwks_fetches: AtomicUsize::new(0),
+            id_token: Mutex::default(),
+        });
+
+        let router = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/jwks", get(jwks))
+            .route("/token", post(token))
+            .with_state(issuer.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        issuer
+    }
+
+    fn provider(&self) -> OidcProvider {
+        OidcProvider {
+            name: "stub".to_string(),
+            issuer: format!("{}/", self.url),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: None,
+            redirect_uri: "http://localhost/api/auth/oidc/stub/callback".to_string(),
+            scopes: "openid email".to_string(),
+        }
+    }
+
+    fn oidc(&self) -> Oidc {
+        Oidc::new(vec![self.provider()], "/".to_string()).unwrap()
+    }
+
+    // An ID token for `claims`, signed with `key` under `kid`.
+    fn sign(&self, kid: &str, key: &SecretKey, claims: Value) -> String {
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(kid.to_string());
+        let pem = key.to_pkcs8_pem(LineEnding::LF).unwrap();
+
+        encode(&header, &claims, &EncodingKey::from_ec_pem(pem.as_bytes()).unwrap()).unwrap()
+    }
+
+    fn claims(&self, nonce: &str) -> Value {
+        json!({
+            "iss": self.url,
+            "aud": CLIENT_ID,
+            "sub": "user-42",
+            "exp": Utc::now().timestamp() + 300,
+            "nonce": nonce,
+            "email": "alice@example.com",
+            "email_verified": true,
+        })
+    }
+
+    // Signs with the newest published key.
+    fn id_token(&self, nonce: &str) -> String {
+        let (kid, key) = self.keys.lock().unwrap().last().cloned().unwrap();
+        self.sign(&kid, &key, self.claims(nonce))
+    }
+
+    fn rotate(&self, kid: &str) {
+        self.keys.lock().unwrap().push((kid.to_string(), SecretKey::random(&mut OsRng)));
+    }
+}
+
+async fn discovery(State(issuer): State<Arc<Issuer>>) -> Json<Value> {
+    Json(json!({
+        "issuer": *issuer.reported_issuer.lock().unwrap(),
+        "authorization_endpoint": format!("{}/authorize", issuer.url),
+        "token_endpoint": format!("{}/token", issuer.url),
+        "jwks_uri": format!("{}/jwks", issuer.url),
+    }))
+}
+
+async fn jwks(State(issuer): State<Arc<Issuer>>) -> Json<Value> {
+    issuer.jwks_fetches.fetch_add(1, Ordering::SeqCst);
+
+    let keys: Vec<Value> = issuer
+        .keys
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(kid, key)| {
+            let point = key.public_key().to_encoded_point(false);
+            json!({
+                "kty": "EC",
+                "crv": "P-256",
+                "alg": "ES256",
+                "use": "sig",
+                "kid": kid,
+                "x": URL_SAFE_NO_PAD.encode(point.x().unwrap()),
+                "y": URL_SAFE_NO_PAD.encode(point.y().unwrap()),
+            })
+        })
+        .collect();
+
+    Json(json!({ "keys": keys }))
+}
+
+async fn token(State(issuer): State<Arc<Issuer>>) -> Json<Value> {
+    Json(json!({ "access_token": "unused", "token_type": "Bearer", "id_token": *issuer.id_token.lock().unwrap() }))
+}
+
+#[tokio::test]
+async fn discovers_endpoints_from_the_issuer() {
+    let issuer = Issuer::start().await;
+    let oidc = issuer.oidc();
+    let provider = oidc.provider("stub").unwrap();
+
+    let metadata = oidc.metadata(provider).await.unwrap();
+    assert_eq!(metadata.token_endpoint, format!("{}/token", issuer.url));
+    assert_eq!(metadata.jwks_uri, format!("{}/jwks", issuer.url));
+
+    let url = oidc.authorization_url(provider, &metadata, "the-state", NONCE, "the-challenge").unwrap();
+    assert!(url.starts_with(&format!("{}/authorize?", issuer.url)));
+    assert!(url.contains("state=the-state"));
+    assert!(url.contains("nonce=expected-nonce"));
+    assert!(url.contains("code_challenge=the-challenge"));
+    assert!(url.contains("code_challenge_method=S256"));
+}
+
+#[tokio::test]
+async fn rejects_discovery_for_another_issuer() {
+    let issuer = Issuer::start().await;
+    *issuer.reported_issuer.lock().unwrap() = "https://evil.example".to_string();
+    let oidc = issuer.oidc();
+
+    let (status, _) = oidc.metadata(oidc.provider("stub").unwrap()).await.unwrap_err();
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+}
+
+#[tokio::test]
+async fn exchanges_the_code_for_a_valid_id_token() {
+    let issuer = Issuer::start().await;
+    *issuer.id_token.lock().unwrap() = issuer.id_token(NONCE);
+    let oidc = issuer.oidc();
+    let provider = oidc.provider("stub").unwrap();
+    let metadata = oidc.metadata(provider).await.unwrap();
+
+    let id_token = oidc.exchange_code(provider, &metadata, "the-code", "the-verifier").await.unwrap();
+    let claims = oidc.validate_id_token(provider, &metadata, &id_token, NONCE).await.unwrap();
+
+    assert_eq!(claims.sub, "user-42");
+    assert_eq!(claims.email.as_deref(), Some("alice@example.com"));
+    assert!(claims.email_verified);
+}
+
+#[tokio::test]
+async fn caches_the_jwks_and_refetches_it_after_key_rotation() {
+    let issuer = Issuer::start().await;
+    let oidc = issuer.oidc();
+    let provider = oidc.provider("stub").unwrap();
+    let metadata = oidc.metadata(provider).await.unwrap();
+
+    for _ in 0..2 {
+        oidc.validate_id_token(provider, &metadata, &issuer.id_token(NONCE), NONCE).await.unwrap();
+    }
+    assert_eq!(issuer.jwks_fetches.load(Ordering::SeqCst), 1);
+
+    issuer.rotate("key-2");
+    oidc.validate_id_token(provider, &metadata, &issuer.id_token(NONCE), NONCE).await.unwrap();
+    assert_eq!(issuer.jwks_fetches.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn rejects_tokens_signed_with_an_unpublished_key() {
+    let issuer = Issuer::start().await;
+    let oidc = issuer.oidc();
+    let provider = oidc.provider("stub").unwrap();
+    let metadata = oidc.metadata(provider).await.unwrap();
+
+    // A published kid with someone else's key fails the signature check...
+    let forged = issuer.sign("key-1", &SecretKey::random(&mut OsRng), issuer.claims(NONCE));
+    let (status, _) = oidc.validate_id_token(provider, &metadata, &forged, NONCE).await.unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // ...and an unknown kid is not found, even after refetching.
+    let unknown = issuer.sign("key-9", &SecretKey::random(&mut OsRng), issuer.claims(NONCE));
+    let (status, message) = oidc.validate_id_token(provider, &metadata, &unknown, NONCE).await.unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(message.contains("unknown signing key"), "{}", message);
+}
+
+#[tokio::test]
+async fn rejects_a_nonce_mismatch() {
+    let issuer = Issuer::start().await;
+    let oidc = issuer.oidc();
+    let provider = oidc.provider("stub").unwrap();
+    let metadata = oidc.metadata(provider).await.unwrap();
+
+    let id_token = issuer.id_token("another-nonce");
+    let (status, message) = oidc.validate_id_token(provider, &metadata, &id_token, NONCE).await.unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(message.contains("nonce mismatch"), "{}", message);
+
+    let mut claims = issuer.claims(NONCE);
+    claims.as_object_mut().unwrap().remove("nonce");
+    let (kid, key) = issuer.keys.lock().unwrap()[0].clone();
+    let id_token = issuer.sign(&kid, &key, claims);
+    let (status, _) = oidc.validate_id_token(provider, &metadata, &id_token, NONCE).await.unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn rejects_a_token_for_another_client() {
+    let issuer = Issuer::start().await;
+    let oidc = issuer.oidc();
+    let provider = oidc.provider("stub").unwrap();
+    let metadata = oidc.metadata(provider).await.unwrap();
+
+    let mut claims = issuer.claims(NONCE);
+    claims["aud"] = json!("someone-else");
+    let (kid, key) = issuer.keys.lock().unwrap()[0].clone();
+    let id_token = issuer.sign(&kid, &key, claims);
+
+    let (status, _) = oidc.validate_id_token(provider, &metadata, &id_token, NONCE).await.unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+// The state check happens before anything touches the database, so the pool never connects.
+#[tokio::test]
+async fn callback_rejects_a_state_mismatch() {
+    let issuer = Issuer::start().await;
+    let pool = MySqlPoolOptions::new().connect_lazy("mysql://nobody@127.0.0.1:1/none").unwrap();
+
+    let app = Router::new()
+        .route("/api/auth/oidc/:provider/callback", get(oidc_callback))
+        .layer(Extension(issuer.oidc()))
+        .layer(Extension(EventBus::new(pool.clone())))
+        .layer(Extension(pool))
+        .layer(CookieManagerLayer::new());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/api/auth/oidc/stub/callback", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let http = reqwest::Client::new();
+    let callback = |cookie: Option<&str>| {
+        let mut request = http.get(&url).query(&[("code", "the-code"), ("state", "state-from-provider")]);
+        if let Some(cookie) = cookie {
+            request = request.header("Cookie", cookie);
+        }
+        request.send()
+    };
+
+    let response = callback(Some("oidc_state=state-of-another-login")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.text().await.unwrap(), "Login state does not match");
+
+    let response = callback(None).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.text().await.unwrap(), "Login state does not match");
+}
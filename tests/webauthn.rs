@@ -0,0 +1,278 @@
+// Passkey registration and login checks, driven by a software authenticator for each
+// supported algorithm.
+use ciborium::value::Value;
+use rand::{rngs::OsRng, RngCore};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use todos_web_api::utils::webauthn::{
+    check_client_data, cose_algorithm, encode, generate_challenge, parse_attestation_object, parse_authenticator_data,
+    sign_count_advanced, signed_message, verify_signature, WebAuthnConfig, COSE_EDDSA, COSE_ES256, COSE_RS256,
+    MAX_CREDENTIAL_ID_LENGTH,
+};
+
+const ORIGIN: &str = "https://todos.example.com";
+
+fn config() -> WebAuthnConfig {
+    WebAuthnConfig {
+        rp_id: "todos.example.com".to_string(),
+        rp_name: "Todos".to_string(),
+        origins: vec![ORIGIN.to_string()],
+    }
+}
+
+enum Key {
+    Es256(p256::ecdsa::SigningKey),
+    EdDsa(ed25519_dalek::SigningKey),
+    Rs256(rsa::pkcs1v15::SigningKey<Sha256>),
+}
+
+struct Authenticator {
+    key: Key,
+    credential_id: Vec<u8>,
+    sign_count: u32,
+}
+
+impl Authenticator {
+    fn new(key: Key) -> Self {
+        let mut credential_id = vec![0u8; 32];
+        OsRng.fill_bytes(&mut credential_id);
+
+        Self { key, credential_id, sign_count: 0 }
+    }
+
+    fn es256() -> Self {
+        Self::new(Key::Es256(p256::ecdsa::SigningKey::random(&mut OsRng)))
+    }
+
+    fn eddsa() -> Self {
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        Self::new(Key::EdDsa(ed25519_dalek::SigningKey::from_bytes(&secret)))
+    }
+
+    fn rs256() -> Self {
+        let private_key = rsa::RsaPrivateKey::new(&mut OsRng, 2048).unwrap();
+        Self::new(Key::Rs256(rsa::pkcs1v15::SigningKey::new(private_key)))
+    }
+
+    fn cose_key(&self) -> Vec<u8> {
+        let int = |i: i64| Value::Integer(i.into());
+        let entries = match &self.key {
+            Key::Es256(key) => {
+                let point = key.verifying_key().to_encoded_point(false);
+                vec![
+                    (int(1), int(2)),
+                    (int(3), int(COSE_ES256.into())),
+                    (int(-1), int(1)),
+                    (int(-2), Value::Bytes(point.x().unwrap().to_vec())),
+                    (int(-3), Value::Bytes(point.y().unwrap().to_vec())),
+                ]
+            }
+            Key::EdDsa(key) => vec![
+                (int(1), int(1)),
+                (int(3), int(COSE_EDDSA.into())),
+                (int(-1), int(6)),
+                (int(-2), Value::Bytes(key.verifying_key().to_bytes().to_vec())),
+            ],
+            Key::Rs256(key) => {
+                use rsa::traits::PublicKeyParts;
+
+                let public_key = key.as_ref().to_public_key();
+                vec![
+                    (int(1), int(3)),
+                    (int(3), int(COSE_RS256.into())),
+                    (int(-1), Value::Bytes(public_key.n().to_bytes_be())),
+                    (int(-2), Value::Bytes(public_key.e().to_bytes_be())),
+                ]
+            }
+        };
+
+        cbor(&Value::Map(entries))
+    }
+
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        match &self.key {
+            Key::Es256(key) => {
+                use p256::ecdsa::{signature::Signer, Signature};
+
+                let signature: Signature = key.sign(message);
+                signature.to_der().as_bytes().to_vec()
+            }
+            Key::EdDsa(key) => {
+                use ed25519_dalek::Signer;
+
+                key.sign(message).to_bytes().to_vec()
+            }
+            Key::Rs256(key) => {
+                use rsa::signature::{SignatureEncoding, Signer};
+
+                key.sign(message).to_vec()
+            }
+        }
+    }
+
+    // authenticatorData for `rp_id` with the user present, plus the new credential when
+    // registering.
+    fn authenticator_data(&self, rp_id: &str, flags: u8, attested: bool) -> Vec<u8> {
+        let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        data.push(flags | if attested { 0x40 } else { 0 });
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+
+        if attested {
+            data.extend_from_slice(&[0u8; 16]);
+            data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+            data.extend_from_slice(&self.credential_id);
+            data.extend_from_slice(&self.cose_key());
+        }
+
+        data
+    }
+
+    // navigator.credentials.create: (clientDataJSON, attestationObject)
+    fn register(&self, challenge: &str) -> (Vec<u8>, Vec<u8>) {
+        let auth_data = self.authenticator_data("todos.example.com", 0x01, true);
+        let attestation_object = cbor(&Value::Map(vec![
+            (Value::Text("fmt".to_string()), Value::Text("none".to_string())),
+            (Value::Text("attStmt".to_string()), Value::Map(Vec::new())),
+            (Value::Text("authData".to_string()), Value::Bytes(auth_data)),
+        ]));
+
+        (client_data("webauthn.create", challenge, ORIGIN), attestation_object)
+    }
+
+    // navigator.credentials.get: (clientDataJSON, authenticatorData, signature)
+    fn assert(&mut self, challenge: &str) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        self.sign_count += 1;
+        let client_data_json = client_data("webauthn.get", challenge, ORIGIN);
+        let authenticator_data = self.authenticator_data("todos.example.com", 0x01, false);
+        let signature = self.sign(&signed_message(&authenticator_data, &client_data_json));
+
+        (client_data_json, authenticator_data, signature)
+    }
+}
+
+fn cbor(value: &Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+fn client_data(kind: &str, challenge: &str, origin: &str) -> Vec<u8> {
+    serde_json::to_vec(&json!({ "type": kind, "challenge": challenge, "origin": origin })).unwrap()
+}
+
+// Registers the authenticator the way webauthn_register does, then logs in twice the way
+// webauthn_login does.
+fn register_and_log_in(mut authenticator: Authenticator, algorithm: i32) {
+    let config = config();
+
+    let challenge = generate_challenge();
+    let (client_data_json, attestation_object) = authenticator.register(&challenge);
+    assert_eq!(check_client_data(&config, &client_data_json, "webauthn.create").unwrap(), challenge);
+
+    let auth_data = parse_attestation_object(&attestation_object).unwrap();
+    let registered = parse_authenticator_data(&config, &auth_data).unwrap();
+    assert_eq!(registered.credential_id.as_deref(), Some(authenticator.credential_id.as_slice()));
+    let public_key = registered.public_key.unwrap();
+    assert_eq!(cose_algorithm(&public_key).unwrap(), algorithm);
+    let mut stored_count = i64::from(registered.sign_count);
+
+    for _ in 0..2 {
+        let challenge = generate_challenge();
+        let (client_data_json, authenticator_data, signature) = authenticator.assert(&challenge);
+        assert_eq!(check_client_data(&config, &client_data_json, "webauthn.get").unwrap(), challenge);
+
+        let asserted = parse_authenticator_data(&config, &authenticator_data).unwrap();
+        assert!(asserted.credential_id.is_none());
+        assert!(sign_count_advanced(stored_count, asserted.sign_count));
+        stored_count = i64::from(asserted.sign_count);
+
+        let message = signed_message(&authenticator_data, &client_data_json);
+        assert!(verify_signature(&public_key, &message, &signature).unwrap());
+
+        // Any other challenge makes it someone else's signature.
+        let replayed = signed_message(&authenticator_data, &client_data("webauthn.get", &generate_challenge(), ORIGIN));
+        assert!(!verify_signature(&public_key, &replayed, &signature).unwrap());
+    }
+
+    // Another authenticator's key does not verify it either.
+    let (client_data_json, authenticator_data, _) = authenticator.assert(&generate_challenge());
+    let other = Authenticator::es256();
+    let message = signed_message(&authenticator_data, &client_data_json);
+    assert!(!verify_signature(&public_key, &message, &other.sign(&message)).unwrap());
+}
+
+#[test]
+fn registers_and_logs_in_with_es256() {
+    register_and_log_in(Authenticator::es256(), COSE_ES256);
+}
+
+#[test]
+fn registers_and_logs_in_with_eddsa() {
+    register_and_log_in(Authenticator::eddsa(), COSE_EDDSA);
+}
+
+#[test]
+fn registers_and_logs_in_with_rs256() {
+    register_and_log_in(Authenticator::rs256(), COSE_RS256);
+}
+
+#[test]
+fn refuses_a_signature_counter_that_does_not_move_forward() {
+    // A cloned authenticator replays or lags behind the original's counter.
+    assert!(!sign_count_advanced(5, 5));
+    assert!(!sign_count_advanced(5, 4));
+    assert!(!sign_count_advanced(5, 0));
+    assert!(sign_count_advanced(5, 6));
+
+    // Authenticators without a counter always report 0.
+    assert!(sign_count_advanced(0, 0));
+    assert!(sign_count_advanced(0, 1));
+
+    // The stored count is what the last assertion reported, so a clone that logs in second
+    // is caught.
+    let config = config();
+    let key = p256::ecdsa::SigningKey::random(&mut OsRng);
+    let mut original = Authenticator::new(Key::Es256(key.clone()));
+    let mut clone = Authenticator::new(Key::Es256(key));
+    clone.credential_id = original.credential_id.clone();
+
+    let (_, authenticator_data, _) = original.assert(&generate_challenge());
+    let stored_count = i64::from(parse_authenticator_data(&config, &authenticator_data).unwrap().sign_count);
+    let (_, authenticator_data, _) = clone.assert(&generate_challenge());
+    let reported = parse_authenticator_data(&config, &authenticator_data).unwrap().sign_count;
+    assert!(!sign_count_advanced(stored_count, reported));
+}
+
+#[test]
+fn refuses_the_wrong_ceremony_origin_or_rp_id() {
+    let config = config();
+    let challenge = generate_challenge();
+
+    let (status, _) = check_client_data(&config, &client_data("webauthn.create", &challenge, ORIGIN), "webauthn.get").unwrap_err();
+    assert_eq!(status, 400);
+    let phished = client_data("webauthn.get", &challenge, "https://todos.example.com.evil.example");
+    assert!(check_client_data(&config, &phished, "webauthn.get").is_err());
+
+    let authenticator = Authenticator::es256();
+    assert!(parse_authenticator_data(&config, &authenticator.authenticator_data("evil.example", 0x01, false)).is_err());
+    // User presence flag missing.
+    assert!(parse_authenticator_data(&config, &authenticator.authenticator_data("todos.example.com", 0x00, false)).is_err());
+}
+
+#[test]
+fn refuses_credential_ids_longer_than_the_spec_allows() {
+    let config = config();
+    let mut authenticator = Authenticator::es256();
+
+    authenticator.credential_id = vec![7u8; MAX_CREDENTIAL_ID_LENGTH];
+    let data = authenticator.authenticator_data("todos.example.com", 0x01, true);
+    let registered = parse_authenticator_data(&config, &data).unwrap();
+    // The longest id still fits the credential_id column: VARCHAR(1364).
+    assert_eq!(encode(&registered.credential_id.unwrap()).len(), 1364);
+
+    authenticator.credential_id.push(7);
+    let data = authenticator.authenticator_data("todos.example.com", 0x01, true);
+    assert!(parse_authenticator_data(&config, &data).is_err());
+}
@@ -0,0 +1,385 @@
+// Webhook deliveries to a receiver on a random local port: signatures, retries with backoff,
+// and giving up. The outbox and claim tests need a MySQL database the migrations can run on:
+//   TEST_DATABASE_URL=mysql://root@127.0.0.1/todos_test cargo test --test webhooks -- --ignored
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use chrono::{DateTime, Duration, Local, Utc};
+use reqwest::dns::Resolve;
+use serde_json::json;
+use sqlx::{mysql::MySqlPoolOptions, MySqlPool};
+use uuid::Uuid;
+
+use todos_web_api::{
+    models::webhook::{Webhook, WebhookDelivery},
+    utils::{
+        request_signing::verify,
+        webhooks::{
+            attempt_outcome, enqueue, retry_delay, PublicResolver, WebhookWorker, WEBHOOK_EVENT_HEADER,
+            WEBHOOK_ID_HEADER, WEBHOOK_SIGNATURE_HEADER, WEBHOOK_TIMESTAMP_HEADER,
+        },
+    },
+};
+
+const SECRET: &str = "whsec_test";
+const PAYLOAD: &str = r#"{"id":"evt-1","type":"todo.created","data":{"id":7}}"#;
+
+#[derive(Default)]
+struct Receiver {
+    // Status codes to answer with, in order; 200 once they run out.
+    responses: Mutex<Vec<StatusCode>>,
+    received: Mutex<Vec<(HeaderMap, String)>>,
+}
+
+impl Receiver {
+    async fn start(responses: &[StatusCode]) -> (Arc<Self>, String) {
+        let receiver = Arc::new(Self { responses: Mutex::new(responses.iter().rev().copied().collect()), ..Self::default() });
+
+        let router = Router::new()
+            .route("/hook", post(receive))
+            .route("/elsewhere", post(receive))
+            .with_state(receiver.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        (receiver, url)
+    }
+
+    fn received(&self) -> Vec<(HeaderMap, String)> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+async fn receive(State(receiver): State<Arc<Receiver>>, headers: HeaderMap, body: String) -> Response {
+    receiver.received.lock().unwrap().push((headers, body));
+
+    match receiver.responses.lock().unwrap().pop().unwrap_or(StatusCode::OK) {
+        StatusCode::FOUND => (StatusCode::FOUND, [(header::LOCATION, "/elsewhere")]).into_response(),
+        status => status.into_response(),
+    }
+}
+
+// Local receivers are only allowed with WEBHOOK_ALLOW_LOCAL. The pool is never connected:
+// posting a delivery does not touch the database.
+fn worker() -> WebhookWorker {
+    worker_for(MySqlPoolOptions::new().connect_lazy("mysql://nobody@127.0.0.1:1/none").unwrap())
+}
+
+fn worker_for(pool: MySqlPool) -> WebhookWorker {
+    std::env::set_var("WEBHOOK_ALLOW_LOCAL", "true");
+
+    WebhookWorker::from_env(pool).unwrap()
+}
+
+async fn test_pool() -> MySqlPool {
+    let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL is not set");
+    let pool = MySqlPoolOptions::new().connect(&url).await.unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    pool
+}
+
+async fn insert_user(pool: &MySqlPool) -> i32 {
+    sqlx::query("INSERT INTO users (username, password_hash) VALUES (?, '')")
+        .bind(format!("webhooks-{}", Uuid::new_v4()))
+        .execute(pool)
+        .await
+        .unwrap()
+        .last_insert_id() as i32
+}
+
+async fn insert_webhook(pool: &MySqlPool, user_id: i32, url: &str, event_types: &str) -> i32 {
+    sqlx::query("INSERT INTO webhooks (user_id, url, event_types, secret) VALUES (?, ?, ?, ?)")
+        .bind(user_id)
+        .bind(url)
+        .bind(event_types)
+        .bind(SECRET)
+        .execute(pool)
+        .await
+        .unwrap()
+        .last_insert_id() as i32
+}
+
+async fn deliveries(pool: &MySqlPool, webhook_id: i32) -> Vec<WebhookDelivery> {
+    sqlx::query_as::<_, WebhookDelivery>("SELECT * FROM webhook_deliveries WHERE webhook_id = ? ORDER BY id")
+        .bind(webhook_id)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+fn webhook(url: &str) -> Webhook {
+    Webhook {
+        id: 1,
+        user_id: Some(1),
+        api_key_id: None,
+        url: url.to_string(),
+        event_types: "todo.*".to_string(),
+        secret: SECRET.to_string(),
+        is_active: true,
+        created_at: Local::now(),
+        updated_at: Local::now(),
+    }
+}
+
+fn delivery(attempts: i32) -> WebhookDelivery {
+    WebhookDelivery {
+        id: 1,
+        webhook_id: 1,
+        event_id: "evt-1".to_string(),
+        event_type: "todo.created".to_string(),
+        payload: PAYLOAD.to_string(),
+        status: "pending".to_string(),
+        attempts,
+        next_attempt_at: Local::now(),
+        locked_until: None,
+        last_status_code: None,
+        last_error: None,
+        delivered_at: None,
+        created_at: Local::now(),
+        updated_at: Local::now(),
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> &'a str {
+    headers.get(name).and_then(|value| value.to_str().ok()).unwrap_or_default()
+}
+
+fn assert_between(delay: Duration, min_seconds: i64, max_seconds: i64) {
+    assert!(
+        delay >= Duration::seconds(min_seconds) && delay <= Duration::seconds(max_seconds),
+        "{} is not between {}s and {}s",
+        delay,
+        min_seconds,
+        max_seconds
+    );
+}
+
+#[tokio::test]
+async fn signs_deliveries_so_receivers_can_verify_them() {
+    let (receiver, url) = Receiver::start(&[]).await;
+    let worker = worker();
+
+    let status = worker.post(&webhook(&format!("{}/hook", url)), &delivery(0)).await.unwrap();
+    assert_eq!(status, 200);
+
+    let received = receiver.received();
+    assert_eq!(received.len(), 1);
+    let (headers, body) = &received[0];
+    assert_eq!(body, PAYLOAD);
+    assert_eq!(header(headers, "content-type"), "application/json");
+    assert_eq!(header(headers, WEBHOOK_ID_HEADER), "evt-1");
+    assert_eq!(header(headers, WEBHOOK_EVENT_HEADER), "todo.created");
+
+    let timestamp = header(headers, WEBHOOK_TIMESTAMP_HEADER);
+    assert!((Utc::now().timestamp() - timestamp.parse::<i64>().unwrap()).abs() <= 5);
+
+    let signature = header(headers, WEBHOOK_SIGNATURE_HEADER);
+    assert!(signature.starts_with("v1="));
+    assert!(verify(SECRET, &format!("{}.{}", timestamp, body), signature));
+    assert!(!verify("whsec_other", &format!("{}.{}", timestamp, body), signature));
+    assert!(!verify(SECRET, &format!("{}.{}", timestamp, r#"{"id":"evt-1","type":"todo.deleted"}"#), signature));
+}
+
+#[tokio::test]
+async fn retries_a_failed_delivery_with_backoff_until_it_succeeds() {
+    let (receiver, url) = Receiver::start(&[StatusCode::SERVICE_UNAVAILABLE, StatusCode::INTERNAL_SERVER_ERROR]).await;
+    let worker = worker();
+    let webhook = webhook(&format!("{}/hook", url));
+
+    let first = attempt_outcome(0, 8, worker.post(&webhook, &delivery(0)).await);
+    assert_eq!(first.status, "pending");
+    assert_eq!(first.attempts, 1);
+    assert_eq!(first.status_code, Some(503));
+    assert_eq!(first.error.as_deref(), Some("Receiver responded with HTTP 503"));
+    assert!(first.delivered_at.is_none());
+    assert_between(first.next_attempt_at - Utc::now(), 29, 33);
+
+    let second = attempt_outcome(1, 8, worker.post(&webhook, &delivery(1)).await);
+    assert_eq!(second.status, "pending");
+    assert_eq!(second.attempts, 2);
+    assert_between(second.next_attempt_at - Utc::now(), 59, 66);
+
+    let third = attempt_outcome(2, 8, worker.post(&webhook, &delivery(2)).await);
+    assert_eq!(third.status, "delivered");
+    assert_eq!(third.attempts, 3);
+    assert_eq!(third.status_code, Some(200));
+    assert!(third.error.is_none() && third.delivered_at.is_some());
+
+    // Every retry is the same event, so receivers can tell them apart from new ones.
+    let received = receiver.received();
+    assert_eq!(received.len(), 3);
+    assert!(received.iter().all(|(headers, _)| header(headers, WEBHOOK_ID_HEADER) == "evt-1"));
+}
+
+#[test]
+fn backs_off_exponentially_up_to_six_hours() {
+    assert_between(retry_delay(1), 30, 33);
+    assert_between(retry_delay(2), 60, 66);
+    assert_between(retry_delay(3), 120, 132);
+    assert_between(retry_delay(10), 15360, 15360 + 1536);
+    assert_between(retry_delay(11), 6 * 3600, 6 * 3600 + 2160);
+    assert_between(retry_delay(1000), 6 * 3600, 6 * 3600 + 2160);
+}
+
+#[tokio::test]
+async fn gives_up_after_the_last_attempt() {
+    let (receiver, url) = Receiver::start(&[StatusCode::INTERNAL_SERVER_ERROR]).await;
+    let worker = worker();
+
+    let outcome = attempt_outcome(7, 8, worker.post(&webhook(&format!("{}/hook", url)), &delivery(7)).await);
+    assert_eq!(outcome.status, "dead");
+    assert_eq!(outcome.attempts, 8);
+    assert_eq!(outcome.status_code, Some(500));
+    assert!(outcome.delivered_at.is_none());
+    assert_eq!(receiver.received().len(), 1);
+}
+
+#[tokio::test]
+async fn counts_an_unreachable_receiver_as_a_failure() {
+    // Nothing listens on the port once the listener is gone.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    drop(listener);
+
+    let outcome = attempt_outcome(0, 8, worker().post(&webhook(&url), &delivery(0)).await);
+    assert_eq!(outcome.status, "pending");
+    assert_eq!(outcome.status_code, None);
+    assert!(outcome.error.is_some_and(|error| error.starts_with("Request failed")));
+
+    let outcome = attempt_outcome(7, 8, worker().post(&webhook(&url), &delivery(7)).await);
+    assert_eq!(outcome.status, "dead");
+}
+
+#[tokio::test]
+async fn does_not_follow_redirects() {
+    let (receiver, url) = Receiver::start(&[StatusCode::FOUND]).await;
+
+    let outcome = attempt_outcome(0, 8, worker().post(&webhook(&format!("{}/hook", url)), &delivery(0)).await);
+    assert_eq!(outcome.status, "pending");
+    assert_eq!(outcome.status_code, Some(302));
+    assert_eq!(receiver.received().len(), 1);
+}
+
+#[tokio::test]
+async fn refuses_hosts_that_resolve_to_local_addresses() {
+    for host in ["localhost", "127.0.0.1", "10.1.2.3", "169.254.169.254", "100.64.0.1", "100.127.255.254", "::1"] {
+        let resolved = PublicResolver.resolve(host.parse().unwrap()).await;
+        assert!(resolved.is_err(), "{} was let through", host);
+    }
+
+    // Just outside 100.64.0.0/10. IP literals resolve without asking DNS.
+    for host in ["100.128.0.1", "93.184.215.14"] {
+        let addrs: Vec<_> = PublicResolver.resolve(host.parse().unwrap()).await.unwrap().collect();
+        assert_eq!(addrs[0].ip().to_string(), host);
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a MySQL database in TEST_DATABASE_URL"]
+async fn dispatches_outbox_events_to_subscribed_webhooks_once() {
+    let pool = test_pool().await;
+    let (_, url) = Receiver::start(&[]).await;
+    let user_id = insert_user(&pool).await;
+    let other_user_id = insert_user(&pool).await;
+    let url = format!("{}/hook", url);
+    let subscribed = insert_webhook(&pool, user_id, &url, "todo.*").await;
+    let other_events = insert_webhook(&pool, user_id, &url, "list.*").await;
+    let other_user = insert_webhook(&pool, other_user_id, &url, "todo.*").await;
+
+    enqueue(&pool, user_id, "todo.created", &json!({ "id": 7 })).await.unwrap();
+    let worker = worker_for(pool.clone());
+    worker.dispatch_outbox().await.unwrap();
+
+    let dispatched = deliveries(&pool, subscribed).await;
+    assert_eq!(dispatched.len(), 1);
+    assert_eq!(dispatched[0].event_type, "todo.created");
+    assert!(dispatched[0].payload.contains(&dispatched[0].event_id));
+    assert!(deliveries(&pool, other_events).await.is_empty());
+    assert!(deliveries(&pool, other_user).await.is_empty());
+
+    let event_id = &dispatched[0].event_id;
+    let dispatched_at = sqlx::query_scalar::<_, Option<DateTime<Utc>>>("SELECT dispatched_at FROM webhook_outbox WHERE event_id = ?")
+        .bind(event_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(dispatched_at.is_some());
+
+    // A worker that died before marking the event dispatched sends it again: nothing doubles.
+    sqlx::query("UPDATE webhook_outbox SET dispatched_at = NULL WHERE event_id = ?")
+        .bind(event_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    worker.dispatch_outbox().await.unwrap();
+    assert_eq!(deliveries(&pool, subscribed).await.len(), 1);
+}
+
+#[tokio::test]
+#[ignore = "needs a MySQL database in TEST_DATABASE_URL"]
+async fn delivers_due_deliveries_it_claims_and_retries_failures() {
+    let pool = test_pool().await;
+    let (receiver, url) = Receiver::start(&[StatusCode::INTERNAL_SERVER_ERROR]).await;
+    let user_id = insert_user(&pool).await;
+    let webhook_id = insert_webhook(&pool, user_id, &format!("{}/hook", url), "todo.*").await;
+
+    enqueue(&pool, user_id, "todo.updated", &json!({ "id": 7 })).await.unwrap();
+    let worker = worker_for(pool.clone());
+    worker.dispatch_outbox().await.unwrap();
+    let id = deliveries(&pool, webhook_id).await[0].id;
+    let set = |column: &'static str, value: Option<DateTime<Utc>>| {
+        let pool = pool.clone();
+        async move {
+            sqlx::query(&format!("UPDATE webhook_deliveries SET {} = ? WHERE id = ?", column))
+                .bind(value)
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+    };
+
+    // Another worker holds it: it is left alone.
+    set("locked_until", Some(Utc::now() + Duration::seconds(60))).await;
+    worker.deliver_due().await.unwrap();
+    assert!(receiver.received().is_empty());
+    assert_eq!(deliveries(&pool, webhook_id).await[0].attempts, 0);
+
+    // Once the lock is gone it is claimed and sent; the 500 schedules a retry.
+    set("locked_until", None).await;
+    worker.deliver_due().await.unwrap();
+    assert_eq!(receiver.received().len(), 1);
+    let delivery = &deliveries(&pool, webhook_id).await[0];
+    assert_eq!(delivery.status, "pending");
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.last_status_code, Some(500));
+    assert!(delivery.locked_until.is_none());
+    assert!(delivery.next_attempt_at > Utc::now());
+
+    // Not due yet.
+    worker.deliver_due().await.unwrap();
+    assert_eq!(receiver.received().len(), 1);
+
+    set("next_attempt_at", Some(Utc::now() - Duration::seconds(1))).await;
+    worker.deliver_due().await.unwrap();
+    assert_eq!(receiver.received().len(), 2);
+    let delivery = &deliveries(&pool, webhook_id).await[0];
+    assert_eq!(delivery.status, "delivered");
+    assert_eq!(delivery.attempts, 2);
+    assert!(delivery.delivered_at.is_some() && delivery.last_error.is_none());
+
+    let attempts = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM webhook_delivery_attempts WHERE delivery_id = ?")
+        .bind(id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(attempts, 2);
+}